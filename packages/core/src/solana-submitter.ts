@@ -18,14 +18,18 @@
  *   - proof_a y-coordinate must be negated (Groth16 verification equation)
  *   - Public inputs are 32-byte big-endian: [tierLower, tierUpper, nullifier, timestamp]
  *   - PDA seeds: [b"tier_badge", user_pubkey]
+ *   - Each nullifier is registered once: [b"nullifier", nullifier]
+ *   - The global Config PDA ([b"config"]) must be passed with every verify
  */
 
 import { createHash } from "crypto";
 import {
+  ComputeBudgetProgram,
   Connection,
   Keypair,
   PublicKey,
   SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
   Transaction,
   TransactionInstruction,
   sendAndConfirmTransaction,
@@ -43,9 +47,65 @@ export const PROGRAM_ID = new PublicKey(
 /** Number of public inputs the circuit outputs */
 export const NR_PUBLIC_INPUTS = 4;
 
+/**
+ * `layout_version` for public inputs in the circuit's own order
+ * (`[tier_lower, tier_upper, nullifier, timestamp]`), as snarkjs emits them
+ */
+export const PUBLIC_INPUT_LAYOUT = 1;
+
+/**
+ * Compute units to request for the verify instruction. Mirrors the
+ * program's `RECOMMENDED_VERIFY_COMPUTE_UNITS`; keep the two in sync.
+ */
+export const RECOMMENDED_VERIFY_COMPUTE_UNITS = 300_000;
+
 /** PDA seed prefix */
 const TIER_BADGE_SEED = Buffer.from("tier_badge");
 
+/** NullifierRecord PDA seed prefix */
+const NULLIFIER_SEED = Buffer.from("nullifier");
+
+/** WalletMarker PDA seed prefix */
+const WALLET_MARKER_SEED = Buffer.from("wallet_marker");
+
+/** RequestReceipt PDA seed prefix */
+const REQUEST_SEED = Buffer.from("request");
+
+/** Config PDA seed */
+const CONFIG_SEED = Buffer.from("config");
+
+/** Treasury PDA seed */
+const TREASURY_SEED = Buffer.from("treasury");
+
+/** RewardVault PDA seed */
+const REWARD_VAULT_SEED = Buffer.from("reward_vault");
+
+/** RewardClaim PDA seed prefix */
+const REWARD_CLAIM_SEED = Buffer.from("reward_claim");
+
+/** BadgeTombstone PDA seed prefix */
+const TOMBSTONE_SEED = Buffer.from("tombstone");
+
+/** ExpiryBucket PDA seed prefix */
+const EXPIRY_BUCKET_SEED = Buffer.from("expiry_bucket");
+
+/** The SPL Memo program */
+export const MEMO_PROGRAM_ID = new PublicKey(
+  "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+);
+
+/** Seconds per ExpiryBucket: badges are bucketed by UTC day */
+export const EXPIRY_BUCKET_SECONDS = 24 * 60 * 60;
+
+/** Seed prefix of the badge owner a Secp256k1 key authorizes for */
+const SECP256K1_OWNER_SEED = Buffer.from("secp256k1_owner");
+
+/**
+ * `sig_scheme` values for `verify_delegated`: the owner's authorization is
+ * checked by the Ed25519 or the Secp256k1 precompile.
+ */
+export const SIG_SCHEME = { ed25519: 0, secp256k1: 1 } as const;
+
 /**
  * The alt_bn128 curve order (field modulus for Fr / Fq).
  * Used to negate the proof_a y-coordinate: neg_y = CURVE_ORDER - y
@@ -70,12 +130,93 @@ export interface SnarkjsProof {
 /** The 4 public signals from our circuit, as decimal strings */
 export type PublicSignals = [string, string, string, string];
 
+/** Badge holder kinds, in the program's `AccountType` variant order */
+export const ACCOUNT_TYPES = ["individual", "institution", "contract"] as const;
+export type AccountType = (typeof ACCOUNT_TYPES)[number];
+
 /** Options for building the verify instruction */
 export interface BuildInstructionOptions {
   proof: SnarkjsProof;
   publicSignals: PublicSignals;
   userPubkey: PublicKey;
   programId?: PublicKey;
+  /**
+   * Pass the user's WalletMarker PDA. Required when the deployment sets
+   * `min_wallet_age_slots`; leave unset otherwise.
+   */
+  includeWalletMarker?: boolean;
+  /**
+   * 16-byte id that makes retries safe: the program rejects a second
+   * submission with the same id for about a minute.
+   */
+  clientRequestId?: Uint8Array;
+  /**
+   * 8-byte app namespace for the badge, so one wallet can hold a badge per
+   * app. Omit for the wallet's default badge.
+   */
+  namespace?: Uint8Array;
+  /**
+   * Pass the instructions sysvar so the program checks a prover
+   * attestation. The caller must place the prover's Ed25519 signature over
+   * the concatenated public inputs in the instruction right before this one.
+   */
+  includeProverAttestation?: boolean;
+  /**
+   * Call `create_badge` instead of `verify_and_store_tier`. Required for a
+   * wallet's first badge when the deployment sets `require_explicit_create`.
+   */
+  createBadge?: boolean;
+  /**
+   * What kind of holder the badge represents. Must be allowed by the
+   * deployment's `allowed_account_types`; defaults to `"individual"`.
+   */
+  accountType?: AccountType;
+  /**
+   * Pass the instructions sysvar for bundle checks. Required when the
+   * deployment sets `restrict_bundles`.
+   */
+  includeBundleSysvar?: boolean;
+  /**
+   * The deployment's `hook_program`. Required when the config sets one,
+   * since the program calls it after writing the badge.
+   */
+  hookProgram?: PublicKey;
+  /**
+   * Pass the Treasury PDA so it can pay the badge rent. Only has an effect
+   * on a first verification at or above the deployment's `subsidy_min_tier`.
+   */
+  useTreasury?: boolean;
+  /**
+   * Pass the badge's BadgeTombstone PDA. Required for a first verification
+   * when the deployment sets `tombstone_blocks_recreate`.
+   */
+  includeBadgeTombstone?: boolean;
+  /**
+   * Relayer submitting on the user's behalf. It must also sign the
+   * transaction, and is recorded as a new badge's origin relayer.
+   */
+  relayer?: PublicKey;
+  /**
+   * Day (see `expiryBucketDay`) the badge will expire on, to list it in
+   * that day's ExpiryBucket. The bucket must already exist.
+   */
+  expiryBucketDay?: number;
+  /**
+   * Day a refreshed badge expired on before, when the refresh moves it to
+   * another day, so it is removed from that day's ExpiryBucket.
+   */
+  previousExpiryBucketDay?: number;
+  /**
+   * Pass the Memo program so that, when the deployment sets
+   * `emit_tier_memo`, the verification writes a `POL:T<tier>` memo.
+   */
+  includeMemo?: boolean;
+  /**
+   * Pass the RewardVault and the identity's RewardClaim so a first
+   * verification collects the deployment's `first_verify_reward_lamports`.
+   * The claim is created (at the user's expense) if it doesn't exist.
+   */
+  claimReward?: boolean;
 }
 
 /** Options for the full submit flow */
@@ -174,6 +315,37 @@ export function encodeProofC(pi_c: SnarkjsProof["pi_c"]): Uint8Array {
   return result;
 }
 
+/** Field elements above (p - 1) / 2 count as negative in point compression */
+const HALF_FIELD = (CURVE_ORDER - 1n) / 2n;
+
+function bytesToBigInt(bytes: Uint8Array): bigint {
+  return BigInt("0x" + Buffer.from(bytes).toString("hex"));
+}
+
+/**
+ * Compress an encoded G1 point (proof_a or proof_c, 64 bytes) to the
+ * 32-byte form `verify_and_store_tier_compressed` takes: x big-endian,
+ * with the top bit set when y is the "negative" root.
+ */
+export function compressG1(point: Uint8Array): Uint8Array {
+  const compressed = point.slice(0, 32);
+  if (bytesToBigInt(point.subarray(32)) > HALF_FIELD) compressed[0] |= 0x80;
+  return compressed;
+}
+
+/**
+ * Compress an encoded G2 point (proof_b, 128 bytes) to 64 bytes:
+ * x as `x_c1 | x_c0`, with the top bit set when y is the "negative" root
+ * (decided by y_c1, or by y_c0 when y_c1 is zero).
+ */
+export function compressG2(point: Uint8Array): Uint8Array {
+  const compressed = point.slice(0, 64);
+  const yC1 = bytesToBigInt(point.subarray(64, 96));
+  const yC0 = bytesToBigInt(point.subarray(96));
+  if ((yC1 !== 0n ? yC1 : yC0) > HALF_FIELD) compressed[0] |= 0x80;
+  return compressed;
+}
+
 /**
  * Convert the 4 public signals to [[u8; 32]; 4] format.
  * Order: [tier_lower_bound, tier_upper_bound, nullifier, timestamp]
@@ -182,20 +354,182 @@ export function encodePublicInputs(publicSignals: PublicSignals): Uint8Array[] {
   return publicSignals.map((s) => decimalTo32BytesBE(s));
 }
 
+/**
+ * The message a badge owner signs with Ed25519 (or Secp256k1) to let
+ * `custodian` submit `verify_delegated` for them: "proof-of-love:delegate",
+ * the custodian's key, the 8-byte namespace (zeros for the default badge)
+ * and the encoded public inputs, in circuit order whatever layout the
+ * proof is sent in.
+ */
+export function delegationMessage(
+  custodian: PublicKey,
+  publicSignals: PublicSignals,
+  namespace: Uint8Array = new Uint8Array(8),
+): Buffer {
+  return Buffer.concat([
+    Buffer.from("proof-of-love:delegate"),
+    custodian.toBuffer(),
+    Buffer.from(namespace),
+    ...encodePublicInputs(publicSignals),
+  ]);
+}
+
 // ---------------------------------------------------------------------------
 // PDA derivation
 // ---------------------------------------------------------------------------
 
+/**
+ * Derive the badge owner for a Secp256k1 key, identified by its 20-byte
+ * Ethereum-style address. Pass it as `owner` to `verify_delegated` with
+ * `SIG_SCHEME.secp256k1`.
+ * Seeds: ["secp256k1_owner", eth_address]
+ */
+export function deriveSecp256k1OwnerPDA(
+  ethAddress: Uint8Array,
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [SECP256K1_OWNER_SEED, Buffer.from(ethAddress)],
+    programId,
+  );
+}
+
 /**
  * Derive the TierBadge PDA address for a given user.
- * Seeds: ["tier_badge", user_pubkey]
+ * Seeds: ["tier_badge", user_pubkey] for the default badge, or
+ * ["tier_badge", user_pubkey, namespace] for an 8-byte app namespace
+ * (an all-zero namespace is the default badge).
  */
 export function deriveTierBadgePDA(
   userPubkey: PublicKey,
   programId: PublicKey = PROGRAM_ID,
+  namespace?: Uint8Array,
+): [PublicKey, number] {
+  const seeds = [TIER_BADGE_SEED, userPubkey.toBuffer()];
+  if (namespace && namespace.some((byte) => byte !== 0)) {
+    seeds.push(Buffer.from(namespace));
+  }
+  return PublicKey.findProgramAddressSync(seeds, programId);
+}
+
+/**
+ * Derive the NullifierRecord PDA for a 32-byte big-endian nullifier.
+ * Seeds: ["nullifier", nullifier]
+ */
+export function deriveNullifierPDA(
+  nullifier: Uint8Array,
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [NULLIFIER_SEED, Buffer.from(nullifier)],
+    programId,
+  );
+}
+
+/**
+ * Derive the WalletMarker PDA for a user.
+ * Seeds: ["wallet_marker", user_pubkey]
+ */
+export function deriveWalletMarkerPDA(
+  userPubkey: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [WALLET_MARKER_SEED, userPubkey.toBuffer()],
+    programId,
+  );
+}
+
+/**
+ * Derive the RequestReceipt PDA for a user's client request id.
+ * Seeds: ["request", user_pubkey, client_request_id]
+ */
+export function deriveRequestReceiptPDA(
+  userPubkey: PublicKey,
+  clientRequestId: Uint8Array,
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [REQUEST_SEED, userPubkey.toBuffer(), Buffer.from(clientRequestId)],
+    programId,
+  );
+}
+
+/**
+ * Derive the global Config PDA address.
+ * Seeds: ["config"]
+ */
+export function deriveConfigPDA(
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([CONFIG_SEED], programId);
+}
+
+/**
+ * Derive the Treasury PDA address.
+ * Seeds: ["treasury"]
+ */
+export function deriveTreasuryPDA(
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([TREASURY_SEED], programId);
+}
+
+/**
+ * Derive the RewardVault PDA address.
+ * Seeds: ["reward_vault"]
+ */
+export function deriveRewardVaultPDA(
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([REWARD_VAULT_SEED], programId);
+}
+
+/**
+ * Derive the RewardClaim PDA for a 32-byte big-endian nullifier.
+ * Seeds: ["reward_claim", nullifier]
+ */
+export function deriveRewardClaimPDA(
+  nullifier: Uint8Array,
+  programId: PublicKey = PROGRAM_ID,
 ): [PublicKey, number] {
   return PublicKey.findProgramAddressSync(
-    [TIER_BADGE_SEED, userPubkey.toBuffer()],
+    [REWARD_CLAIM_SEED, Buffer.from(nullifier)],
+    programId,
+  );
+}
+
+/**
+ * Derive the BadgeTombstone PDA address for a tier badge.
+ * Seeds: ["tombstone", tier_badge]
+ */
+export function deriveBadgeTombstonePDA(
+  tierBadge: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [TOMBSTONE_SEED, tierBadge.toBuffer()],
+    programId,
+  );
+}
+
+/** The ExpiryBucket day for a badge's `expires_at` (Unix seconds) */
+export function expiryBucketDay(expiresAt: number): number {
+  return Math.floor(expiresAt / EXPIRY_BUCKET_SECONDS);
+}
+
+/**
+ * Derive the ExpiryBucket PDA address for a day since the Unix epoch.
+ * Seeds: ["expiry_bucket", day (i64 LE)]
+ */
+export function deriveExpiryBucketPDA(
+  day: number,
+  programId: PublicKey = PROGRAM_ID,
+): [PublicKey, number] {
+  const dayBytes = Buffer.alloc(8);
+  dayBytes.writeBigInt64LE(BigInt(day));
+  return PublicKey.findProgramAddressSync(
+    [EXPIRY_BUCKET_SEED, dayBytes],
     programId,
   );
 }
@@ -219,7 +553,9 @@ export function decodeTier(
     [500_000_000n, 10_000_000_000_000n, 7], // Sun: $5M+
   ];
   for (const [lo, hi, tier] of tiers) {
-    if (tierLower === lo && tierUpper === hi) return tier;
+    // The top tier is unbounded: any ceiling at or above its cap matches
+    const ceilingMatches = tier === 7 ? tierUpper >= hi : tierUpper === hi;
+    if (tierLower === lo && ceilingMatches) return tier;
   }
   return null;
 }
@@ -241,6 +577,109 @@ export const PROOF_OF_LOVE_IDL = {
       accounts: [
         { name: "user", isMut: true, isSigner: true },
         { name: "tierBadge", isMut: true, isSigner: false },
+        { name: "nullifierRecord", isMut: true, isSigner: false },
+        {
+          name: "walletMarker",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "requestReceipt",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "instructionsSysvar",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "badgeTombstone",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "rewardClaim",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "shared",
+          accounts: [
+            {
+              name: "identityRotation",
+              isMut: false,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "previousNullifierRecord",
+              isMut: true,
+              isSigner: false,
+              isOptional: true,
+            },
+            { name: "config", isMut: false, isSigner: false },
+            {
+              name: "bundleSysvar",
+              isMut: false,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "hookProgram",
+              isMut: false,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "treasury",
+              isMut: true,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "relayer",
+              isMut: false,
+              isSigner: true,
+              isOptional: true,
+            },
+            {
+              name: "expiryBucket",
+              isMut: true,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "previousExpiryBucket",
+              isMut: true,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "memoProgram",
+              isMut: false,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "rewardVault",
+              isMut: true,
+              isSigner: false,
+              isOptional: true,
+            },
+            {
+              name: "verificationLog",
+              isMut: true,
+              isSigner: false,
+              isOptional: true,
+            },
+          ],
+        },
         { name: "systemProgram", isMut: false, isSigner: false },
       ],
       args: [
@@ -251,9 +690,29 @@ export const PROOF_OF_LOVE_IDL = {
           name: "publicInputs",
           type: { array: [{ array: ["u8", 32] }, 4] },
         },
+        {
+          name: "clientRequestId",
+          type: { option: { array: ["u8", 16] } },
+        },
+        { name: "accountType", type: { defined: "AccountType" } },
+        { name: "namespace", type: { option: { array: ["u8", 8] } } },
+        { name: "layoutVersion", type: "u8" },
       ],
     },
   ],
+  types: [
+    {
+      name: "AccountType",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Individual" },
+          { name: "Institution" },
+          { name: "Contract" },
+        ],
+      },
+    },
+  ],
 } as const;
 
 // ---------------------------------------------------------------------------
@@ -281,21 +740,45 @@ export function buildVerifyInstruction(opts: BuildInstructionOptions): {
   const pubInputs = encodePublicInputs(publicSignals);
 
   // 2. Derive PDA
+  const { namespace } = opts;
+  if (namespace && namespace.length !== 8) {
+    throw new Error("namespace must be 8 bytes");
+  }
   const [tierBadgePDA, tierBadgeBump] = deriveTierBadgePDA(
     userPubkey,
     programId,
+    namespace,
   );
 
   // 3. Build the instruction data manually using Anchor's discriminator
   //    Discriminator = first 8 bytes of sha256("global:verify_and_store_tier")
+  //    (or "global:create_badge", which takes the same args and accounts)
+  const ixName = opts.createBadge ? "create_badge" : "verify_and_store_tier";
   const disc = createHash("sha256")
-    .update("global:verify_and_store_tier")
+    .update(`global:${ixName}`)
     .digest()
     .subarray(0, 8);
 
   // Instruction data layout:
-  // [8 disc | 64 proof_a | 128 proof_b | 64 proof_c | 4*32 public_inputs]
-  const dataLen = 8 + 64 + 128 + 64 + 4 * 32;
+  // [8 disc | 64 proof_a | 128 proof_b | 64 proof_c | 4*32 public_inputs |
+  //  1 option tag | 16 client_request_id (if present) | 1 account_type |
+  //  1 option tag | 8 namespace (if present) | 1 layout_version]
+  const { clientRequestId } = opts;
+  if (clientRequestId && clientRequestId.length !== 16) {
+    throw new Error("clientRequestId must be 16 bytes");
+  }
+  const dataLen =
+    8 +
+    64 +
+    128 +
+    64 +
+    4 * 32 +
+    1 +
+    (clientRequestId ? 16 : 0) +
+    1 +
+    1 +
+    (namespace ? 8 : 0) +
+    1;
   const data = Buffer.alloc(dataLen);
   let offset = 0;
 
@@ -316,12 +799,133 @@ export function buildVerifyInstruction(opts: BuildInstructionOptions): {
     offset += 32;
   }
 
+  if (clientRequestId) {
+    data[offset] = 1;
+    Buffer.from(clientRequestId).copy(data, offset + 1);
+    offset += 16;
+  }
+  offset += 1;
+
+  data[offset] = ACCOUNT_TYPES.indexOf(opts.accountType ?? "individual");
+  offset += 1;
+
+  if (namespace) {
+    data[offset] = 1;
+    Buffer.from(namespace).copy(data, offset + 1);
+    offset += 8;
+  }
+  offset += 1;
+
+  data[offset] = PUBLIC_INPUT_LAYOUT;
+
   // 4. Build the instruction
   const instruction = new TransactionInstruction({
     programId,
     keys: [
       { pubkey: userPubkey, isSigner: true, isWritable: true },
       { pubkey: tierBadgePDA, isSigner: false, isWritable: true },
+      {
+        pubkey: deriveNullifierPDA(pubInputs[2], programId)[0],
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        // Anchor encodes an omitted optional account as the program ID
+        pubkey: opts.includeWalletMarker
+          ? deriveWalletMarkerPDA(userPubkey, programId)[0]
+          : programId,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: clientRequestId
+          ? deriveRequestReceiptPDA(userPubkey, clientRequestId, programId)[0]
+          : programId,
+        isSigner: false,
+        isWritable: !!clientRequestId,
+      },
+      {
+        pubkey: opts.includeProverAttestation
+          ? SYSVAR_INSTRUCTIONS_PUBKEY
+          : programId,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: opts.includeBadgeTombstone
+          ? deriveBadgeTombstonePDA(tierBadgePDA, programId)[0]
+          : programId,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: opts.claimReward
+          ? deriveRewardClaimPDA(pubInputs[2], programId)[0]
+          : programId,
+        isSigner: false,
+        isWritable: !!opts.claimReward,
+      },
+      // The nested `shared` accounts: identity rotation and the retired
+      // nullifier's record (never passed here), then the config
+      { pubkey: programId, isSigner: false, isWritable: false },
+      { pubkey: programId, isSigner: false, isWritable: false },
+      {
+        pubkey: deriveConfigPDA(programId)[0],
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: opts.includeBundleSysvar
+          ? SYSVAR_INSTRUCTIONS_PUBKEY
+          : programId,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: opts.hookProgram ?? programId,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: opts.useTreasury ? deriveTreasuryPDA(programId)[0] : programId,
+        isSigner: false,
+        isWritable: !!opts.useTreasury,
+      },
+      {
+        pubkey: opts.relayer ?? programId,
+        isSigner: !!opts.relayer,
+        isWritable: false,
+      },
+      {
+        pubkey:
+          opts.expiryBucketDay !== undefined
+            ? deriveExpiryBucketPDA(opts.expiryBucketDay, programId)[0]
+            : programId,
+        isSigner: false,
+        isWritable: opts.expiryBucketDay !== undefined,
+      },
+      {
+        pubkey:
+          opts.previousExpiryBucketDay !== undefined
+            ? deriveExpiryBucketPDA(opts.previousExpiryBucketDay, programId)[0]
+            : programId,
+        isSigner: false,
+        isWritable: opts.previousExpiryBucketDay !== undefined,
+      },
+      {
+        pubkey: opts.includeMemo ? MEMO_PROGRAM_ID : programId,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: opts.claimReward
+          ? deriveRewardVaultPDA(programId)[0]
+          : programId,
+        isSigner: false,
+        isWritable: !!opts.claimReward,
+      },
+      // Verification log, never passed here
+      { pubkey: programId, isSigner: false, isWritable: false },
       {
         pubkey: SystemProgram.programId,
         isSigner: false,
@@ -384,7 +988,12 @@ export async function submitProofToSolana(
   });
 
   // Build and send transaction
-  const tx = new Transaction().add(instruction);
+  const tx = new Transaction().add(
+    ComputeBudgetProgram.setComputeUnitLimit({
+      units: RECOMMENDED_VERIFY_COMPUTE_UNITS,
+    }),
+    instruction,
+  );
   tx.feePayer = wallet.publicKey;
 
   const txSignature = await sendAndConfirmTransaction(
@@ -453,7 +1062,12 @@ export async function prepareVerifyTransaction(opts: {
     programId,
   });
 
-  const tx = new Transaction().add(instruction);
+  const tx = new Transaction().add(
+    ComputeBudgetProgram.setComputeUnitLimit({
+      units: RECOMMENDED_VERIFY_COMPUTE_UNITS,
+    }),
+    instruction,
+  );
   tx.feePayer = userPubkey;
   tx.recentBlockhash = (
     await connection.getLatestBlockhash("confirmed")
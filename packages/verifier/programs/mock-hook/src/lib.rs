@@ -0,0 +1,53 @@
+//! Minimal verification hook for the Proof of Love test suite. It accepts
+//! any badge whose stored tier matches the reported one, and rejects Sun
+//! (tier 7) badges so tests can exercise a failing hook.
+
+// The IDL instructions generated by `#[program]` still call the deprecated
+// `AccountInfo::realloc`.
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+
+declare_id!("3hErqKZPaZwy7evE3Ajf3wtP9r2f2EJ6G6XuxAymfgmq");
+
+/// Offset of `TierBadge::tier`: discriminator + owner
+const BADGE_TIER_OFFSET: usize = 8 + 32;
+
+/// The tier this hook refuses
+const REJECTED_TIER: u8 = 7;
+
+#[program]
+pub mod mock_hook {
+    use super::*;
+
+    pub fn on_tier_verified(ctx: Context<OnTierVerified>, tier: u8) -> Result<()> {
+        let data = ctx.accounts.tier_badge.try_borrow_data()?;
+        require!(
+            data.get(BADGE_TIER_OFFSET) == Some(&tier),
+            MockHookError::BadgeNotWritten
+        );
+        require!(tier != REJECTED_TIER, MockHookError::Rejected);
+
+        msg!(
+            "Mock hook: badge {} at tier {}",
+            ctx.accounts.tier_badge.key(),
+            tier
+        );
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct OnTierVerified<'info> {
+    /// CHECK: only the tier byte is read, to confirm the badge was written
+    /// before the hook was called
+    pub tier_badge: UncheckedAccount<'info>,
+}
+
+#[error_code]
+pub enum MockHookError {
+    #[msg("Badge does not hold the reported tier")]
+    BadgeNotWritten,
+    #[msg("Mock hook rejects this tier")]
+    Rejected,
+}
@@ -0,0 +1,331 @@
+//! Prover attestations: an Ed25519 signature over the public signals by the
+//! config-registered prover key, checked through instruction introspection.
+//! Delegated verifications use the same mechanism for the badge owner's
+//! authorization, signed with Ed25519 or, for wallets that can only sign
+//! with Secp256k1, through the Secp256k1 precompile.
+//!
+//! The signature itself is verified by the precompile, which fails the
+//! whole transaction on a bad signature. This module only confirms that the
+//! precompile instruction immediately before ours checked the right key
+//! over the right message.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::{ed25519_program, secp256k1_program};
+
+use crate::errors::ProofOfLoveError;
+
+/// Header layout of an Ed25519 precompile instruction with one signature
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+/// Marks an offset as pointing into the precompile instruction itself
+const THIS_INSTRUCTION: u16 = u16::MAX;
+
+/// Header layout of a Secp256k1 precompile instruction with one signature
+const SECP256K1_OFFSETS_START: usize = 1;
+const SECP256K1_OFFSETS_LEN: usize = 11;
+const ETH_ADDRESS_LEN: usize = 20;
+/// Signature plus recovery id
+const SECP256K1_SIGNATURE_LEN: usize = 65;
+
+/// `sig_scheme` of an owner authorizing with their Ed25519 wallet key
+pub const SIG_SCHEME_ED25519: u8 = 0;
+/// `sig_scheme` of an owner authorizing with a Secp256k1 key, identified
+/// by its Ethereum-style address
+pub const SIG_SCHEME_SECP256K1: u8 = 1;
+
+/// Prefix of a delegation message, so the signature can't be mistaken for
+/// any other message the owner signs
+pub const DELEGATION_DOMAIN: &[u8] = b"proof-of-love:delegate";
+
+/// The message a prover signs: the four public signals, concatenated.
+pub fn attestation_message(public_inputs: &[[u8; 32]]) -> Vec<u8> {
+    public_inputs.concat()
+}
+
+/// The message a badge owner signs to let `custodian` verify this proof
+/// into their badge under `namespace`: `DELEGATION_DOMAIN`, the
+/// custodian's key, the namespace and the four public signals in `LAYOUT_V1`
+/// order, whatever layout they are sent in.
+pub fn delegation_message(
+    custodian: &Pubkey,
+    namespace: &[u8; 8],
+    public_inputs: &[[u8; 32]],
+) -> Vec<u8> {
+    [
+        DELEGATION_DOMAIN,
+        custodian.as_ref(),
+        namespace,
+        &public_inputs.concat(),
+    ]
+    .concat()
+}
+
+/// Require that the instruction before the current one is an Ed25519
+/// precompile check of `prover_key` signing `message`.
+pub fn require_attestation(
+    instructions_sysvar: &AccountInfo,
+    prover_key: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    require!(
+        previous_instruction_signs(instructions_sysvar, prover_key, message)?,
+        ProofOfLoveError::ProverSignatureInvalid
+    );
+    Ok(())
+}
+
+/// Require that the instruction before the current one is an Ed25519
+/// precompile check of `owner` signing the delegation `message`.
+pub fn require_delegation(
+    instructions_sysvar: &AccountInfo,
+    owner: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    require!(
+        previous_instruction_signs(instructions_sysvar, owner, message)?,
+        ProofOfLoveError::DelegationSignatureInvalid
+    );
+    Ok(())
+}
+
+/// The badge owner a Secp256k1 key authorizes for: an address derived from
+/// `eth_address`, off the Ed25519 curve so no other key can sign for it.
+pub fn secp256k1_owner(eth_address: &[u8; ETH_ADDRESS_LEN]) -> Pubkey {
+    Pubkey::find_program_address(&[b"secp256k1_owner", eth_address], &crate::ID).0
+}
+
+/// Require that the instruction before the current one is a Secp256k1
+/// precompile check of `message`, signed by the key whose
+/// `secp256k1_owner` is `owner`.
+pub fn require_secp256k1_delegation(
+    instructions_sysvar: &AccountInfo,
+    owner: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let signer = previous_instruction(instructions_sysvar)?
+        .filter(|(_, ix)| ix.program_id == secp256k1_program::ID)
+        .and_then(|(index, ix)| secp256k1_signer(&ix.data, index, message));
+    require!(
+        signer.is_some_and(|eth_address| secp256k1_owner(&eth_address) == *owner),
+        ProofOfLoveError::DelegationSignatureInvalid
+    );
+    Ok(())
+}
+
+/// The instruction before the current one, with its index, if any.
+fn previous_instruction(instructions_sysvar: &AccountInfo) -> Result<Option<(u16, Instruction)>> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    let Some(previous) = current.checked_sub(1) else {
+        return Ok(None);
+    };
+    let ix = load_instruction_at_checked(previous as usize, instructions_sysvar)?;
+    Ok(Some((previous, ix)))
+}
+
+/// Whether the instruction before the current one is an Ed25519 precompile
+/// check of `pubkey` signing `message`.
+fn previous_instruction_signs(
+    instructions_sysvar: &AccountInfo,
+    pubkey: &Pubkey,
+    message: &[u8],
+) -> Result<bool> {
+    Ok(
+        previous_instruction(instructions_sysvar)?.is_some_and(|(_, ix)| {
+            ix.program_id == ed25519_program::ID && signs(&ix.data, pubkey, message)
+        }),
+    )
+}
+
+/// Whether Ed25519 instruction data holds exactly one signature, by
+/// `pubkey` over `message`, with all offsets inside the instruction.
+fn signs(data: &[u8], pubkey: &Pubkey, message: &[u8]) -> bool {
+    if data.len() < SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN || data[0] != 1 {
+        return false;
+    }
+
+    let field = |i: usize| {
+        let at = SIGNATURE_OFFSETS_START + 2 * i;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let (signature_offset, signature_ix) = (field(0) as usize, field(1));
+    let (pubkey_offset, pubkey_ix) = (field(2) as usize, field(3));
+    let (message_offset, message_len, message_ix) =
+        (field(4) as usize, field(5) as usize, field(6));
+
+    if [signature_ix, pubkey_ix, message_ix]
+        .iter()
+        .any(|&ix| ix != THIS_INSTRUCTION)
+    {
+        return false;
+    }
+
+    let slice = |offset: usize, len: usize| data.get(offset..offset.checked_add(len)?);
+    slice(signature_offset, SIGNATURE_LEN).is_some()
+        && slice(pubkey_offset, PUBKEY_LEN) == Some(pubkey.as_ref())
+        && slice(message_offset, message_len) == Some(message)
+}
+
+/// The Ethereum-style address that signed `message` in Secp256k1
+/// instruction data at transaction index `own_index`, if the data holds
+/// exactly one signature with all offsets inside the instruction.
+fn secp256k1_signer(data: &[u8], own_index: u16, message: &[u8]) -> Option<[u8; ETH_ADDRESS_LEN]> {
+    if data.len() < SECP256K1_OFFSETS_START + SECP256K1_OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+
+    let offsets = &data[SECP256K1_OFFSETS_START..];
+    let u16_at = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]) as usize;
+    let (signature_offset, signature_ix) = (u16_at(0), offsets[2]);
+    let (eth_address_offset, eth_address_ix) = (u16_at(3), offsets[5]);
+    let (message_offset, message_len, message_ix) = (u16_at(6), u16_at(8), offsets[10]);
+
+    if [signature_ix, eth_address_ix, message_ix]
+        .iter()
+        .any(|&ix| u16::from(ix) != own_index)
+    {
+        return None;
+    }
+
+    let slice = |offset: usize, len: usize| data.get(offset..offset.checked_add(len)?);
+    slice(signature_offset, SECP256K1_SIGNATURE_LEN)?;
+    if slice(message_offset, message_len) != Some(message) {
+        return None;
+    }
+    slice(eth_address_offset, ETH_ADDRESS_LEN)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Instruction data in the layout the Ed25519 precompile (and
+    /// `Ed25519Program.createInstructionWithPublicKey`) uses.
+    fn ed25519_data(pubkey: &Pubkey, message: &[u8], ix_index: u16) -> Vec<u8> {
+        let header = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN;
+        let pubkey_offset = header as u16;
+        let signature_offset = pubkey_offset + PUBKEY_LEN as u16;
+        let message_offset = signature_offset + SIGNATURE_LEN as u16;
+
+        let mut data = vec![1, 0];
+        for field in [
+            signature_offset,
+            ix_index,
+            pubkey_offset,
+            ix_index,
+            message_offset,
+            message.len() as u16,
+            ix_index,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(pubkey.as_ref());
+        data.extend_from_slice(&[0xee; SIGNATURE_LEN]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn accepts_matching_key_and_message() {
+        let key = Pubkey::new_unique();
+        let message = attestation_message(&[[1; 32], [2; 32], [3; 32], [4; 32]]);
+        let data = ed25519_data(&key, &message, THIS_INSTRUCTION);
+        assert!(signs(&data, &key, &message));
+    }
+
+    #[test]
+    fn rejects_other_key_or_message() {
+        let key = Pubkey::new_unique();
+        let message = attestation_message(&[[1; 32], [2; 32], [3; 32], [4; 32]]);
+        let data = ed25519_data(&key, &message, THIS_INSTRUCTION);
+        assert!(!signs(&data, &Pubkey::new_unique(), &message));
+        assert!(!signs(&data, &key, &message[..96]));
+    }
+
+    #[test]
+    fn rejects_data_from_other_instructions() {
+        let key = Pubkey::new_unique();
+        let message = attestation_message(&[[1; 32], [2; 32], [3; 32], [4; 32]]);
+        assert!(!signs(&ed25519_data(&key, &message, 0), &key, &message));
+    }
+
+    #[test]
+    fn binds_delegations_to_custodian_and_namespace() {
+        let owner = Pubkey::new_unique();
+        let custodian = Pubkey::new_unique();
+        let inputs = [[1; 32], [2; 32], [3; 32], [4; 32]];
+        let message = delegation_message(&custodian, &[0; 8], &inputs);
+        assert!(message.starts_with(DELEGATION_DOMAIN));
+        assert_eq!(message.len(), DELEGATION_DOMAIN.len() + 32 + 8 + 128);
+
+        let data = ed25519_data(&owner, &message, THIS_INSTRUCTION);
+        assert!(signs(&data, &owner, &message));
+        for other in [
+            delegation_message(&Pubkey::new_unique(), &[0; 8], &inputs),
+            delegation_message(&custodian, &[1; 8], &inputs),
+            attestation_message(&inputs),
+        ] {
+            assert!(!signs(&data, &owner, &other));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        let key = Pubkey::new_unique();
+        let message = attestation_message(&[[1; 32], [2; 32], [3; 32], [4; 32]]);
+        let mut data = ed25519_data(&key, &message, THIS_INSTRUCTION);
+        assert!(!signs(&data[..10], &key, &message));
+        data.truncate(data.len() - 1);
+        assert!(!signs(&data, &key, &message));
+        data[0] = 2;
+        assert!(!signs(&data, &key, &message));
+    }
+
+    /// Instruction data in the layout the Secp256k1 precompile (and
+    /// `Secp256k1Program.createInstructionWithEthAddress`) uses.
+    fn secp256k1_data(eth_address: &[u8; 20], message: &[u8], ix_index: u8) -> Vec<u8> {
+        let eth_address_offset = (SECP256K1_OFFSETS_START + SECP256K1_OFFSETS_LEN) as u16;
+        let signature_offset = eth_address_offset + ETH_ADDRESS_LEN as u16;
+        let message_offset = signature_offset + SECP256K1_SIGNATURE_LEN as u16;
+
+        let mut data = vec![1];
+        data.extend_from_slice(&signature_offset.to_le_bytes());
+        data.push(ix_index);
+        data.extend_from_slice(&eth_address_offset.to_le_bytes());
+        data.push(ix_index);
+        data.extend_from_slice(&message_offset.to_le_bytes());
+        data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+        data.push(ix_index);
+        data.extend_from_slice(eth_address);
+        data.extend_from_slice(&[0xee; SECP256K1_SIGNATURE_LEN]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn reads_the_secp256k1_signer_of_the_message() {
+        let eth_address = [7; 20];
+        let inputs = [[1; 32], [2; 32], [3; 32], [4; 32]];
+        let message = delegation_message(&Pubkey::new_unique(), &[0; 8], &inputs);
+        let data = secp256k1_data(&eth_address, &message, 2);
+        assert_eq!(secp256k1_signer(&data, 2, &message), Some(eth_address));
+
+        // Offsets into another instruction, or another message
+        assert_eq!(secp256k1_signer(&data, 1, &message), None);
+        assert_eq!(secp256k1_signer(&data, 2, &message[1..]), None);
+        assert_eq!(secp256k1_signer(&data[..data.len() - 1], 2, &message), None);
+    }
+
+    #[test]
+    fn derives_distinct_secp256k1_owners() {
+        let owner = secp256k1_owner(&[7; 20]);
+        assert_ne!(owner, secp256k1_owner(&[8; 20]));
+        assert!(!owner.is_on_curve());
+    }
+}
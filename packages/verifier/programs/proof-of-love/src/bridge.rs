@@ -0,0 +1,145 @@
+//! Fixed-layout encoding of a badge's essential fields, for hashing into
+//! cross-chain bridge messages.
+//!
+//! Layout v1 (90 bytes, integers big-endian):
+//!
+//! | Offset | Size | Field            |
+//! |--------|------|------------------|
+//! | 0      | 1    | layout version   |
+//! | 1      | 32   | owner            |
+//! | 33     | 1    | tier             |
+//! | 34     | 8    | tier_lower_bound |
+//! | 42     | 8    | tier_upper_bound |
+//! | 50     | 32   | nullifier        |
+//! | 82     | 8    | expires_at       |
+//!
+//! New fields get a new layout version; existing versions never change.
+
+use anchor_lang::prelude::*;
+
+use crate::state::TierBadge;
+
+/// Current layout version
+pub const BADGE_EXPORT_VERSION: u8 = 1;
+
+/// Encoded length of a v1 export
+pub const BADGE_EXPORT_LEN: usize = 90;
+
+/// The badge fields carried by an export
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BadgeExport {
+    pub owner: Pubkey,
+    pub tier: u8,
+    pub tier_lower_bound: u64,
+    pub tier_upper_bound: u64,
+    pub nullifier: [u8; 32],
+    pub expires_at: i64,
+}
+
+impl From<&TierBadge> for BadgeExport {
+    fn from(badge: &TierBadge) -> Self {
+        BadgeExport {
+            owner: badge.owner,
+            tier: badge.tier,
+            tier_lower_bound: badge.tier_lower_bound,
+            tier_upper_bound: badge.tier_upper_bound,
+            nullifier: badge.nullifier,
+            expires_at: badge.expires_at,
+        }
+    }
+}
+
+/// Encode a badge using the current layout.
+pub fn export_badge_bytes(badge: &TierBadge) -> [u8; BADGE_EXPORT_LEN] {
+    encode(&BadgeExport::from(badge))
+}
+
+/// Encode export fields using the current layout.
+pub fn encode(export: &BadgeExport) -> [u8; BADGE_EXPORT_LEN] {
+    let mut out = [0u8; BADGE_EXPORT_LEN];
+    out[0] = BADGE_EXPORT_VERSION;
+    out[1..33].copy_from_slice(export.owner.as_ref());
+    out[33] = export.tier;
+    out[34..42].copy_from_slice(&export.tier_lower_bound.to_be_bytes());
+    out[42..50].copy_from_slice(&export.tier_upper_bound.to_be_bytes());
+    out[50..82].copy_from_slice(&export.nullifier);
+    out[82..90].copy_from_slice(&export.expires_at.to_be_bytes());
+    out
+}
+
+/// Decode an export. Returns `None` for an unknown layout version.
+pub fn decode_badge_bytes(bytes: &[u8; BADGE_EXPORT_LEN]) -> Option<BadgeExport> {
+    if bytes[0] != BADGE_EXPORT_VERSION {
+        return None;
+    }
+    Some(BadgeExport {
+        owner: Pubkey::new_from_array(bytes[1..33].try_into().unwrap()),
+        tier: bytes[33],
+        tier_lower_bound: u64::from_be_bytes(bytes[34..42].try_into().unwrap()),
+        tier_upper_bound: u64::from_be_bytes(bytes[42..50].try_into().unwrap()),
+        nullifier: bytes[50..82].try_into().unwrap(),
+        expires_at: i64::from_be_bytes(bytes[82..90].try_into().unwrap()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AccountType;
+
+    fn sample_badge() -> TierBadge {
+        TierBadge {
+            owner: Pubkey::new_from_array([7; 32]),
+            tier: 6,
+            tier_lower_bound: 100_000_000,
+            tier_upper_bound: 500_000_000,
+            nullifier: [0xab; 32],
+            verified_at: 1_700_000_000,
+            expires_at: 1_702_592_000,
+            bump: 254,
+            prover_attested: false,
+            account_type: AccountType::Individual,
+            trusted_after: 0,
+            namespace: [0; 8],
+            origin_relayer: Pubkey::default(),
+            refresh_window_start: 0,
+            refresh_count: 0,
+            created_at: 0,
+            tier_high_water: 6,
+            currency_code: *b"USD",
+            sig_scheme: 0,
+            label: [0; 32],
+            vk_id: 0,
+            program_version: 0,
+            verification_epoch: 0,
+            nullifier_enforced: false,
+            expiry_listed: false,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let badge = sample_badge();
+        let bytes = export_badge_bytes(&badge);
+        assert_eq!(decode_badge_bytes(&bytes), Some(BadgeExport::from(&badge)));
+    }
+
+    #[test]
+    fn uses_documented_layout() {
+        let bytes = export_badge_bytes(&sample_badge());
+        assert_eq!(bytes[0], BADGE_EXPORT_VERSION);
+        assert_eq!(&bytes[1..33], &[7; 32]);
+        assert_eq!(bytes[33], 6);
+        assert_eq!(&bytes[34..42], &100_000_000u64.to_be_bytes());
+        assert_eq!(&bytes[42..50], &500_000_000u64.to_be_bytes());
+        assert_eq!(&bytes[50..82], &[0xab; 32]);
+        assert_eq!(&bytes[82..90], &1_702_592_000i64.to_be_bytes());
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut bytes = export_badge_bytes(&sample_badge());
+        bytes[0] = BADGE_EXPORT_VERSION + 1;
+        assert_eq!(decode_badge_bytes(&bytes), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! Bundle restrictions: deployments can forbid a verification from sharing
+//! its transaction with instructions to denied programs (e.g. a token
+//! drain riding along with the verification), checked through instruction
+//! introspection.
+//!
+//! Only top-level instructions are visible to introspection. A denied
+//! program reached by CPI from an allowed one is not caught.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+
+use crate::errors::ProofOfLoveError;
+
+/// Require that no top-level instruction in the transaction targets a
+/// program in `denied`. Default keys in `denied` are unused slots.
+pub fn require_allowed_bundle(instructions_sysvar: &AccountInfo, denied: &[Pubkey]) -> Result<()> {
+    let count = {
+        let data = instructions_sysvar.try_borrow_data()?;
+        u16::from_le_bytes([data[0], data[1]])
+    };
+    let program_ids = (0..count)
+        .map(|i| {
+            load_instruction_at_checked(i as usize, instructions_sysvar).map(|ix| ix.program_id)
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    require!(
+        !bundles_denied_program(&program_ids, denied),
+        ProofOfLoveError::DisallowedInstructionBundle
+    );
+    Ok(())
+}
+
+/// Whether any of `program_ids` is a non-default entry of `denied`.
+fn bundles_denied_program(program_ids: &[Pubkey], denied: &[Pubkey]) -> bool {
+    program_ids
+        .iter()
+        .any(|id| *id != Pubkey::default() && denied.contains(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_denied_programs_only() {
+        let allowed = Pubkey::new_unique();
+        let denied = Pubkey::new_unique();
+        let list = [denied, Pubkey::default()];
+
+        assert!(!bundles_denied_program(&[allowed, crate::ID], &list));
+        assert!(bundles_denied_program(&[allowed, denied], &list));
+    }
+
+    #[test]
+    fn ignores_unused_slots() {
+        // The system program ID is the default key, which marks an empty slot
+        let list = [Pubkey::default(); 4];
+        assert!(!bundles_denied_program(&[Pubkey::default()], &list));
+    }
+}
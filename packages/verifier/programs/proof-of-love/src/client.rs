@@ -0,0 +1,406 @@
+//! Off-chain helpers for clients and indexers. Compiled only with the
+//! `client` feature, so none of this lands in the on-chain binary.
+
+use anchor_lang::prelude::Pubkey;
+
+use crate::encoding::is_canonical_scalar;
+use crate::state::TierBadge;
+
+/// Format a USD amount held in cents as `$1,234.56`.
+pub fn cents_to_usd_string(cents: u64) -> String {
+    let dollars = (cents / 100).to_string();
+    let mut grouped = String::with_capacity(dollars.len() + dollars.len() / 3);
+    for (i, digit) in dollars.chars().enumerate() {
+        if i > 0 && (dollars.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("${}.{:02}", grouped, cents % 100)
+}
+
+/// Parse a USD amount into cents. Accepts an optional `$`, correctly placed
+/// thousands separators and up to two decimal places, e.g. `$1,234.5`.
+/// Returns `None` for malformed input or amounts that overflow `u64`.
+pub fn usd_string_to_cents(s: &str) -> Option<u64> {
+    let s = s.strip_prefix('$').unwrap_or(s);
+    let (whole, fraction) = match s.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (s, ""),
+    };
+
+    let groups: Vec<&str> = whole.split(',').collect();
+    let well_grouped = groups.len() == 1
+        || (!groups[0].is_empty()
+            && groups[0].len() <= 3
+            && groups[1..].iter().all(|g| g.len() == 3));
+    if whole.is_empty() || !well_grouped {
+        return None;
+    }
+
+    let mut dollars: u64 = 0;
+    for digit in groups.concat().chars() {
+        dollars = dollars
+            .checked_mul(10)?
+            .checked_add(digit.to_digit(10)? as u64)?;
+    }
+
+    let cents = match fraction.len() {
+        0 if !s.ends_with('.') => 0,
+        1 | 2 if fraction.chars().all(|c| c.is_ascii_digit()) => {
+            let value: u64 = fraction.parse().ok()?;
+            if fraction.len() == 1 {
+                value * 10
+            } else {
+                value
+            }
+        }
+        _ => return None,
+    };
+
+    dollars.checked_mul(100)?.checked_add(cents)
+}
+
+/// How long after expiry a badge counts as just lapsed: frontends should
+/// prompt a re-verification rather than treat it as gone. Display-only, the
+/// program itself has no grace period.
+pub const EXPIRY_GRACE_SECONDS: i64 = 24 * 60 * 60;
+
+/// Describe a badge expiry relative to `now`, e.g. `expires in 12 days, 4
+/// hours`, `expires now`, `expired 3 hours ago (grace period)` or
+/// `expired 5 days ago`. A badge stays valid up to and including
+/// `expires_at`, matching `revoke_expired_tier`.
+pub fn format_expiry_countdown(expires_at: i64, now: i64) -> String {
+    let remaining = expires_at.saturating_sub(now);
+    match remaining {
+        1.. => format!("expires in {}", format_duration(remaining)),
+        0 => "expires now".to_string(),
+        _ => {
+            let lapsed = remaining.saturating_neg();
+            if lapsed <= EXPIRY_GRACE_SECONDS {
+                format!("expired {} ago (grace period)", format_duration(lapsed))
+            } else {
+                format!("expired {} ago", format_duration(lapsed))
+            }
+        }
+    }
+}
+
+/// The two largest units of a positive duration, e.g. `12 days, 4 hours`.
+/// The smaller unit is left out when it is zero.
+fn format_duration(seconds: i64) -> String {
+    const UNITS: [(i64, &str); 4] = [
+        (86_400, "day"),
+        (3_600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ];
+    let unit =
+        |value: i64, name: &str| format!("{value} {name}{}", if value == 1 { "" } else { "s" });
+
+    let largest = UNITS
+        .iter()
+        .position(|&(size, _)| seconds >= size)
+        .unwrap_or(3);
+    let (size, name) = UNITS[largest];
+    let mut formatted = unit(seconds / size, name);
+    if let Some(&(next_size, next_name)) = UNITS.get(largest + 1) {
+        let next = seconds % size / next_size;
+        if next > 0 {
+            formatted.push_str(", ");
+            formatted.push_str(&unit(next, next_name));
+        }
+    }
+    formatted
+}
+
+/// Render a badge as a W3C-style verifiable credential, ready to be signed
+/// or embedded.
+///
+/// The output is canonical: compact, with keys in lexicographic order, so
+/// the same badge always produces the same bytes. The issuer is the program
+/// ID and the credential subject id is the badge's nullifier.
+pub fn badge_to_credential_json(badge: &TierBadge, program_id: &Pubkey) -> String {
+    let nullifier: String = badge.nullifier.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        concat!(
+            r#"{{"@context":["https://www.w3.org/2018/credentials/v1"],"#,
+            r#""credentialSubject":{{"id":"0x{}","owner":"{}","tier":{},"#,
+            r#""tierLowerBoundCents":{},"tierUpperBoundCents":{}}},"#,
+            r#""expirationDate":"{}","issuanceDate":"{}","issuer":"{}","#,
+            r#""type":["VerifiableCredential","ProofOfLoveTierCredential"]}}"#,
+        ),
+        nullifier,
+        badge.owner,
+        badge.tier,
+        badge.tier_lower_bound,
+        badge.tier_upper_bound,
+        unix_to_rfc3339(badge.expires_at),
+        unix_to_rfc3339(badge.verified_at),
+        program_id,
+    )
+}
+
+/// Encode a nullifier public signal, as the decimal string snarkjs outputs
+/// it, in the 32-byte big-endian form the program stores and seeds the
+/// NullifierRecord with. Returns `None` for anything but a decimal number
+/// below the BN254 scalar field order; the program would reject those
+/// with `NonCanonicalNullifier`.
+pub fn nullifier_bytes(signal: &str) -> Option<[u8; 32]> {
+    if signal.is_empty() {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    for digit in signal.chars() {
+        let mut carry = digit.to_digit(10)?;
+        for byte in bytes.iter_mut().rev() {
+            let value = u32::from(*byte) * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    is_canonical_scalar(&bytes).then_some(bytes)
+}
+
+/// Address of the NullifierRecord for `nullifier`. If the account exists
+/// the nullifier is claimed, and a verification from any wallet but the
+/// record's owner fails with `NullifierAlreadyUsed`.
+pub fn nullifier_record_address(nullifier: &[u8; 32], program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifier", nullifier], program_id).0
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC date, e.g.
+/// `2023-11-14T22:13:20Z`.
+fn unix_to_rfc3339(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant), valid for the proleptic Gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AccountType, DEFAULT_TIERS};
+
+    #[test]
+    fn encodes_nullifier_signals() {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        assert_eq!(nullifier_bytes("1"), Some(one));
+        assert_eq!(nullifier_bytes("0001"), Some(one));
+        assert_eq!(
+            nullifier_bytes(
+                "514631507721405312519378913364952599439317176483405283752361400573829067280"
+            ),
+            Some([
+                0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54,
+                0x32, 0x10, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98,
+                0x76, 0x54, 0x32, 0x10,
+            ])
+        );
+
+        // The largest field element is canonical; the field order isn't
+        let mut largest = crate::encoding::BN254_SCALAR_MODULUS;
+        largest[31] -= 1;
+        let order = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+        let below = "21888242871839275222246405745257275088548364400416034343698204186575808495616";
+        assert_eq!(nullifier_bytes(below), Some(largest));
+        assert_eq!(nullifier_bytes(order), None);
+        assert_eq!(nullifier_bytes(&"9".repeat(80)), None);
+        for signal in ["", "-1", "0x10", "1 2", "1.0"] {
+            assert_eq!(nullifier_bytes(signal), None, "{signal:?}");
+        }
+
+        let (expected, _) = Pubkey::find_program_address(&[b"nullifier", &one], &crate::ID);
+        assert_eq!(nullifier_record_address(&one, &crate::ID), expected);
+    }
+
+    #[test]
+    fn formats_zero() {
+        assert_eq!(cents_to_usd_string(0), "$0.00");
+        assert_eq!(usd_string_to_cents("$0.00"), Some(0));
+        assert_eq!(usd_string_to_cents("0"), Some(0));
+    }
+
+    #[test]
+    fn formats_tier_boundaries() {
+        let expected = [
+            "$0.00",
+            "$1,000.00",
+            "$10,000.00",
+            "$50,000.00",
+            "$250,000.00",
+            "$1,000,000.00",
+            "$5,000,000.00",
+        ];
+        for (tier, formatted) in DEFAULT_TIERS.iter().zip(expected) {
+            assert_eq!(cents_to_usd_string(tier.lower), formatted);
+            assert_eq!(usd_string_to_cents(formatted), Some(tier.lower));
+        }
+        assert_eq!(
+            cents_to_usd_string(DEFAULT_TIERS[6].upper),
+            "$100,000,000,000.00"
+        );
+    }
+
+    #[test]
+    fn formats_max_value() {
+        assert_eq!(cents_to_usd_string(u64::MAX), "$184,467,440,737,095,516.15");
+        assert_eq!(
+            usd_string_to_cents("$184,467,440,737,095,516.15"),
+            Some(u64::MAX)
+        );
+        assert_eq!(usd_string_to_cents("$184,467,440,737,095,516.16"), None);
+    }
+
+    #[test]
+    fn parses_fractions() {
+        assert_eq!(usd_string_to_cents("$1,234.5"), Some(123_450));
+        assert_eq!(usd_string_to_cents("1234.56"), Some(123_456));
+        assert_eq!(usd_string_to_cents("$0.07"), Some(7));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for input in [
+            "", "$", "1.", ".50", "1.234", "12,34", ",123", "1,2345", "$-1", "1e3",
+        ] {
+            assert_eq!(usd_string_to_cents(input), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn formats_future_expiry() {
+        let now = 1_700_000_000;
+        assert_eq!(
+            format_expiry_countdown(now + 12 * 86_400 + 4 * 3_600 + 59, now),
+            "expires in 12 days, 4 hours"
+        );
+        assert_eq!(
+            format_expiry_countdown(now + 86_400, now),
+            "expires in 1 day"
+        );
+    }
+
+    #[test]
+    fn formats_near_expiry() {
+        let now = 1_700_000_000;
+        assert_eq!(
+            format_expiry_countdown(now + 61, now),
+            "expires in 1 minute, 1 second"
+        );
+        assert_eq!(format_expiry_countdown(now + 1, now), "expires in 1 second");
+    }
+
+    #[test]
+    fn formats_exact_expiry() {
+        assert_eq!(
+            format_expiry_countdown(1_700_000_000, 1_700_000_000),
+            "expires now"
+        );
+    }
+
+    #[test]
+    fn formats_lapsed_badges() {
+        let expires_at = 1_700_000_000;
+        assert_eq!(
+            format_expiry_countdown(expires_at, expires_at + 3 * 3_600),
+            "expired 3 hours ago (grace period)"
+        );
+        assert_eq!(
+            format_expiry_countdown(expires_at, expires_at + EXPIRY_GRACE_SECONDS),
+            "expired 1 day ago (grace period)"
+        );
+        assert_eq!(
+            format_expiry_countdown(expires_at, expires_at + EXPIRY_GRACE_SECONDS + 1),
+            "expired 1 day ago"
+        );
+        assert_eq!(
+            format_expiry_countdown(expires_at, expires_at + 5 * 86_400 + 7_200),
+            "expired 5 days, 2 hours ago"
+        );
+    }
+
+    #[test]
+    fn formats_rfc3339_dates() {
+        assert_eq!(unix_to_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(unix_to_rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(unix_to_rfc3339(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(unix_to_rfc3339(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn maps_badge_to_credential() {
+        let badge = TierBadge {
+            owner: Pubkey::new_from_array([7; 32]),
+            tier: 6,
+            tier_lower_bound: 100_000_000,
+            tier_upper_bound: 500_000_000,
+            nullifier: [0xab; 32],
+            verified_at: 1_700_000_000,
+            expires_at: 1_702_592_000,
+            bump: 254,
+            prover_attested: false,
+            account_type: AccountType::Individual,
+            trusted_after: 0,
+            namespace: [0; 8],
+            origin_relayer: Pubkey::default(),
+            refresh_window_start: 0,
+            refresh_count: 0,
+            created_at: 0,
+            tier_high_water: 6,
+            currency_code: *b"USD",
+            sig_scheme: 0,
+            label: [0; 32],
+            vk_id: 0,
+            program_version: 0,
+            verification_epoch: 0,
+            nullifier_enforced: false,
+            expiry_listed: false,
+        };
+        let json = badge_to_credential_json(&badge, &crate::ID);
+        let vc: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            vc["@context"],
+            serde_json::json!(["https://www.w3.org/2018/credentials/v1"])
+        );
+        assert_eq!(
+            vc["type"],
+            serde_json::json!(["VerifiableCredential", "ProofOfLoveTierCredential"])
+        );
+        assert_eq!(vc["issuer"], crate::ID.to_string());
+        assert_eq!(vc["issuanceDate"], "2023-11-14T22:13:20Z");
+        assert_eq!(vc["expirationDate"], "2023-12-14T22:13:20Z");
+
+        let subject = &vc["credentialSubject"];
+        assert_eq!(subject["id"], format!("0x{}", "ab".repeat(32)));
+        assert_eq!(subject["owner"], badge.owner.to_string());
+        assert_eq!(subject["tier"], 6);
+        assert_eq!(subject["tierLowerBoundCents"], 100_000_000);
+        assert_eq!(subject["tierUpperBoundCents"], 500_000_000);
+
+        // Canonical: compact, and re-serializing with sorted keys is a no-op
+        assert_eq!(serde_json::to_string(&vc).unwrap(), json);
+    }
+}
@@ -0,0 +1,145 @@
+//! Poseidon commitment to a badge, returned by `badge_commitment` so other
+//! circuits can take the badge as a private input and check it against a
+//! single public field element.
+
+use anchor_lang::prelude::*;
+
+use crate::encoding::is_canonical_scalar;
+use crate::errors::ProofOfLoveError;
+use crate::state::TierBadge;
+
+/// Number of field elements a badge commits to
+pub const COMMITMENT_INPUTS: usize = 7;
+
+/// A u64 as a 32-byte big-endian field element
+fn field_u64(value: u64) -> [u8; 32] {
+    let mut element = [0; 32];
+    element[24..].copy_from_slice(&value.to_be_bytes());
+    element
+}
+
+/// The badge fields a commitment covers, as 32-byte big-endian BN254
+/// scalars: `owner_hi | owner_lo | tier | tier_lower_bound |
+/// tier_upper_bound | nullifier | expires_at`. The owner doesn't fit in one
+/// element, so it is split into its high and low 16 bytes. `None` for a
+/// badge whose nullifier isn't a canonical scalar, which only legacy badges
+/// can have.
+pub fn commitment_inputs(badge: &TierBadge) -> Option<[[u8; 32]; COMMITMENT_INPUTS]> {
+    if !is_canonical_scalar(&badge.nullifier) {
+        return None;
+    }
+    let owner = badge.owner.to_bytes();
+    let mut owner_hi = [0; 32];
+    let mut owner_lo = [0; 32];
+    owner_hi[16..].copy_from_slice(&owner[..16]);
+    owner_lo[16..].copy_from_slice(&owner[16..]);
+    Some([
+        owner_hi,
+        owner_lo,
+        field_u64(badge.tier.into()),
+        field_u64(badge.tier_lower_bound),
+        field_u64(badge.tier_upper_bound),
+        badge.nullifier,
+        field_u64(badge.expires_at as u64),
+    ])
+}
+
+/// Poseidon (circom's BN254 x^5 parameters) over `commitment_inputs`, as a
+/// 32-byte big-endian field element. Equal to circomlib's `Poseidon(7)`
+/// and circomlibjs' `poseidon` over the same inputs. Fails with
+/// `NonCanonicalNullifier` for a legacy badge that can't be committed to.
+pub fn badge_commitment(badge: &TierBadge) -> Result<[u8; 32]> {
+    let inputs = commitment_inputs(badge).ok_or(ProofOfLoveError::NonCanonicalNullifier)?;
+    poseidon(&inputs)
+}
+
+/// The runtime's Poseidon syscall. There is no host implementation, so
+/// off-chain callers hash `commitment_inputs` themselves.
+#[cfg(target_os = "solana")]
+fn poseidon(inputs: &[[u8; 32]]) -> Result<[u8; 32]> {
+    // Syscall arguments: BN254 x^5 parameters, big-endian
+    const BN254_X5: u64 = 0;
+    const BIG_ENDIAN: u64 = 0;
+
+    let vals: Vec<&[u8]> = inputs.iter().map(|input| input.as_slice()).collect();
+    let mut hash = [0; 32];
+    let status = unsafe {
+        solana_define_syscall::definitions::sol_poseidon(
+            BN254_X5,
+            BIG_ENDIAN,
+            vals.as_ptr() as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        )
+    };
+    require!(status == 0, ProofOfLoveError::CommitmentFailed);
+    Ok(hash)
+}
+
+#[cfg(not(target_os = "solana"))]
+fn poseidon(_inputs: &[[u8; 32]]) -> Result<[u8; 32]> {
+    err!(ProofOfLoveError::CommitmentFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AccountType, DEFAULT_CURRENCY_CODE, MAX_LABEL_LEN};
+
+    fn badge() -> TierBadge {
+        TierBadge {
+            owner: Pubkey::new_from_array(core::array::from_fn(|i| i as u8)),
+            tier: 5,
+            tier_lower_bound: 25_000_000,
+            tier_upper_bound: 100_000_000,
+            nullifier: [7; 32],
+            verified_at: 0,
+            expires_at: 1_700_000_000,
+            bump: 0,
+            prover_attested: false,
+            account_type: AccountType::Individual,
+            trusted_after: 0,
+            namespace: [0; 8],
+            origin_relayer: Pubkey::default(),
+            refresh_window_start: 0,
+            refresh_count: 0,
+            created_at: 0,
+            tier_high_water: 5,
+            currency_code: DEFAULT_CURRENCY_CODE,
+            sig_scheme: 0,
+            label: [0; MAX_LABEL_LEN],
+            vk_id: 0,
+            program_version: 0,
+            verification_epoch: 0,
+            nullifier_enforced: false,
+            expiry_listed: false,
+        }
+    }
+
+    #[test]
+    fn lays_out_commitment_inputs() {
+        let inputs = commitment_inputs(&badge()).unwrap();
+        let owner: Vec<u8> = inputs[0][16..]
+            .iter()
+            .chain(&inputs[1][16..])
+            .copied()
+            .collect();
+        assert_eq!(owner, badge().owner.to_bytes());
+        assert!(inputs[..2].iter().all(|half| half[..16] == [0; 16]));
+        assert_eq!(inputs[2], field_u64(5));
+        assert_eq!(inputs[3], field_u64(25_000_000));
+        assert_eq!(inputs[4], field_u64(100_000_000));
+        assert_eq!(inputs[5], [7; 32]);
+        assert_eq!(inputs[6], field_u64(1_700_000_000));
+        assert!(inputs.iter().all(is_canonical_scalar));
+    }
+
+    #[test]
+    fn refuses_non_canonical_nullifiers() {
+        let legacy = TierBadge {
+            nullifier: [0xff; 32],
+            ..badge()
+        };
+        assert!(commitment_inputs(&legacy).is_none());
+    }
+}
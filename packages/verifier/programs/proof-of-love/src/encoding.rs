@@ -0,0 +1,208 @@
+//! Structural checks on proof encodings and public inputs, run before the
+//! pairing check so that malformed input gets a clearer error than a failed
+//! proof, and decompression of compressed proofs.
+
+use groth16_solana::decompression::{decompress_g1, decompress_g2};
+
+use crate::verifying_key::NR_PUBLIC_INPUTS;
+
+/// BN254 base field modulus, big-endian
+pub const BN254_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// BN254 scalar field order, big-endian. Public inputs are elements of
+/// this field.
+pub const BN254_SCALAR_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+/// Whether every 32-byte big-endian coordinate half of `proof_b` (a G2
+/// point, `x.c1 | x.c0 | y.c1 | y.c0`) is a canonical field element.
+pub fn proof_b_in_field(proof_b: &[u8; 128]) -> bool {
+    proof_b
+        .chunks_exact(32)
+        .all(|half| half < BN254_FIELD_MODULUS.as_slice())
+}
+
+/// Whether a 32-byte big-endian public input is a canonical scalar, i.e.
+/// the only byte encoding of its field element.
+pub fn is_canonical_scalar(value: &[u8; 32]) -> bool {
+    value.as_slice() < BN254_SCALAR_MODULUS.as_slice()
+}
+
+/// Decode a 32-byte big-endian public input as a u64, or `None` if it
+/// doesn't fit. The circuit compares bounds with 64-bit comparators, which
+/// are only sound for inputs of that width.
+pub fn decode_u64_signal(value: &[u8; 32]) -> Option<u64> {
+    if value[..24] != [0u8; 24] {
+        return None;
+    }
+    Some(u64::from_be_bytes(value[24..].try_into().unwrap()))
+}
+
+/// Public input layout of `wealth_tier.circom`:
+/// `[tier_lower, tier_upper, nullifier, timestamp]`.
+pub const LAYOUT_V1: u8 = 1;
+
+/// The same signals with the nullifier first:
+/// `[nullifier, tier_lower, tier_upper, timestamp]`.
+pub const LAYOUT_V2: u8 = 2;
+
+/// For each `LAYOUT_V1` input, its position in layout `layout_version`
+fn input_positions(layout_version: u8) -> Option<[usize; NR_PUBLIC_INPUTS]> {
+    match layout_version {
+        LAYOUT_V1 => Some([0, 1, 2, 3]),
+        LAYOUT_V2 => Some([1, 2, 0, 3]),
+        _ => None,
+    }
+}
+
+/// Reorder public inputs sent in layout `layout_version` into the
+/// `LAYOUT_V1` order the verifying key expects, or `None` for an unknown
+/// layout. Layouts only change the order, so every one is checked against
+/// the same circuit; a circuit with different signals needs its own
+/// verifying key as well as a layout.
+pub fn canonical_public_inputs(
+    layout_version: u8,
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> Option<[[u8; 32]; NR_PUBLIC_INPUTS]> {
+    input_positions(layout_version).map(|positions| positions.map(|i| public_inputs[i]))
+}
+
+/// The nullifier in public inputs sent in layout `layout_version`, for
+/// deriving the NullifierRecord PDA before the instruction runs. Unknown
+/// layouts read the `LAYOUT_V1` position; the instruction rejects them.
+pub fn nullifier_input(
+    layout_version: u8,
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> &[u8; 32] {
+    let positions = input_positions(layout_version).unwrap_or([0, 1, 2, 3]);
+    &public_inputs[positions[2]]
+}
+
+/// Decompress a proof sent with compressed points into the affine
+/// encodings `Groth16Verifier::new` expects. Each compressed point is its
+/// big-endian x-coordinate (`x.c1 | x.c0` for `proof_b`) with the y sign
+/// and infinity flags in the top bits of the first byte, as produced by
+/// the `alt_bn128` compression syscalls. Returns `None` for bytes that
+/// don't decode to a curve point.
+pub fn decompress_proof(
+    proof_a: &[u8; 32],
+    proof_b: &[u8; 64],
+    proof_c: &[u8; 32],
+) -> Option<([u8; 64], [u8; 128], [u8; 64])> {
+    Some((
+        decompress_g1(proof_a).ok()?,
+        decompress_g2(proof_b).ok()?,
+        decompress_g1(proof_c).ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_half(index: usize, value: [u8; 32]) -> [u8; 128] {
+        let mut proof_b = [0u8; 128];
+        proof_b[index * 32..(index + 1) * 32].copy_from_slice(&value);
+        proof_b
+    }
+
+    #[test]
+    fn accepts_field_elements() {
+        let mut max = BN254_FIELD_MODULUS;
+        max[31] -= 1;
+        assert!(proof_b_in_field(&[0u8; 128]));
+        assert!(proof_b_in_field(
+            &[max, max, max, max].concat().try_into().unwrap()
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_field_halves() {
+        for index in 0..4 {
+            assert!(!proof_b_in_field(&with_half(index, BN254_FIELD_MODULUS)));
+            assert!(!proof_b_in_field(&with_half(index, [0xff; 32])));
+        }
+    }
+
+    #[test]
+    fn checks_scalar_canonicity() {
+        let mut max = BN254_SCALAR_MODULUS;
+        max[31] -= 1;
+        assert!(is_canonical_scalar(&[0u8; 32]));
+        assert!(is_canonical_scalar(&max));
+        assert!(!is_canonical_scalar(&BN254_SCALAR_MODULUS));
+        assert!(!is_canonical_scalar(&[0xff; 32]));
+    }
+
+    #[test]
+    fn decodes_u64_signals() {
+        let mut value = [0u8; 32];
+        value[24..].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(decode_u64_signal(&value), Some(u64::MAX));
+        value[23] = 1;
+        assert_eq!(decode_u64_signal(&value), None);
+    }
+
+    #[test]
+    fn decodes_public_input_layouts() {
+        let [lower, upper, nullifier, timestamp] = [[1u8; 32], [2; 32], [3; 32], [4; 32]];
+        let canonical = [lower, upper, nullifier, timestamp];
+
+        let v1 = [lower, upper, nullifier, timestamp];
+        assert_eq!(canonical_public_inputs(LAYOUT_V1, &v1), Some(canonical));
+        assert_eq!(nullifier_input(LAYOUT_V1, &v1), &nullifier);
+
+        let v2 = [nullifier, lower, upper, timestamp];
+        assert_eq!(canonical_public_inputs(LAYOUT_V2, &v2), Some(canonical));
+        assert_eq!(nullifier_input(LAYOUT_V2, &v2), &nullifier);
+    }
+
+    #[test]
+    fn rejects_unknown_layouts() {
+        let inputs = [[1u8; 32], [2; 32], [3; 32], [4; 32]];
+        assert_eq!(canonical_public_inputs(0, &inputs), None);
+        assert_eq!(canonical_public_inputs(3, &inputs), None);
+        assert_eq!(nullifier_input(0, &inputs), &inputs[2]);
+    }
+
+    /// The G1 generator (1, 2), and its negation (1, p - 2)
+    fn generator(negated: bool) -> [u8; 64] {
+        let mut point = [0u8; 64];
+        point[31] = 1;
+        if negated {
+            point[32..].copy_from_slice(&BN254_FIELD_MODULUS);
+            point[63] -= 2;
+        } else {
+            point[63] = 2;
+        }
+        point
+    }
+
+    #[test]
+    fn decompresses_g1_points() {
+        let mut compressed = [0u8; 32];
+        compressed[31] = 1;
+        let (a, _, c) = decompress_proof(&compressed, &[0u8; 64], &compressed).unwrap();
+        assert_eq!(a, generator(false));
+        assert_eq!(c, generator(false));
+
+        // The top bit selects the other y
+        compressed[0] |= 0x80;
+        let (a, _, _) = decompress_proof(&compressed, &[0u8; 64], &compressed).unwrap();
+        assert_eq!(a, generator(true));
+    }
+
+    #[test]
+    fn rejects_undecodable_points() {
+        let mut g1 = [0u8; 32];
+        g1[31] = 1;
+        assert!(decompress_proof(&BN254_FIELD_MODULUS, &[0u8; 64], &g1).is_none());
+        assert!(decompress_proof(&g1, &[0u8; 64], &[0x3f; 32]).is_none());
+        assert!(decompress_proof(&g1, &[0x3f; 64], &g1).is_none());
+    }
+}
@@ -1,19 +1,732 @@
 use anchor_lang::prelude::*;
 
+/// Program errors. Each variant carries an explicit discriminant so its
+/// on-chain error code (`6000 + discriminant`) is pinned; the same values
+/// are published in [`codes`]. Discriminants are never reused or reordered
+/// — new variants take the next free value.
 #[error_code]
 pub enum ProofOfLoveError {
     #[msg("Groth16 proof verification failed")]
-    ProofVerificationFailed,
+    ProofVerificationFailed = 0,
 
     #[msg("Invalid tier: bounds do not match any known tier")]
-    InvalidTier,
+    InvalidTier = 1,
 
-    #[msg("Proof timestamp is too old (must be within 10 minutes)")]
-    ProofTooOld,
+    #[msg("Proof timestamp is older than the configured max proof age")]
+    ProofTooOld = 2,
 
     #[msg("Nullifier already used by another account")]
-    NullifierAlreadyUsed,
+    NullifierAlreadyUsed = 3,
 
     #[msg("Tier badge has not expired yet")]
-    BadgeNotExpired,
-}
\ No newline at end of file
+    BadgeNotExpired = 4,
+
+    #[msg("Nullifier does not match the one stored on this badge")]
+    NullifierChanged = 5,
+
+    #[msg("Verification is paused")]
+    Paused = 6,
+
+    #[msg("Signer is not the config authority")]
+    Unauthorized = 7,
+
+    #[msg("Config values are out of range or the tier table is not contiguous")]
+    InvalidConfig = 8,
+
+    #[msg("Pending config change is still timelocked")]
+    TimelockNotElapsed = 9,
+
+    #[msg("No pending config change to apply")]
+    NoPendingChange = 10,
+
+    #[msg("Wallet marker is missing or younger than the configured minimum age")]
+    WalletTooNew = 11,
+
+    #[msg("Verifying key was rotated too recently")]
+    PostRotationCooldown = 12,
+
+    #[msg("Mint is not controlled by the mint authority PDA for the verified tier")]
+    MintTierMismatch = 13,
+
+    #[msg("Request with this client_request_id was already processed")]
+    DuplicateRequest = 14,
+
+    #[msg("client_request_id requires its RequestReceipt account")]
+    MissingRequestReceipt = 15,
+
+    #[msg("Tier table is frozen and can no longer be changed")]
+    TierTableFrozen = 16,
+
+    #[msg("Tier bounds are both zero; the circuit inputs were likely left uninitialized")]
+    ZeroTierBounds = 17,
+
+    #[msg("Prover attestation is missing, malformed or not signed by the configured prover key")]
+    ProverSignatureInvalid = 18,
+
+    #[msg("Downgrade tier must be a valid tier below the current one")]
+    InvalidDowngrade = 19,
+
+    #[msg("Badge validity exceeds the configured max_validity_seconds")]
+    ValidityExceedsCap = 20,
+
+    #[msg("Badge owner must be the signing wallet")]
+    OwnerSignerMismatch = 21,
+
+    #[msg(
+        "Sweep accounts must be (nullifier_record, tier_badge, owner) triples matching each record"
+    )]
+    InvalidSweepAccounts = 22,
+
+    #[msg("Badge does not exist yet; create it with create_badge")]
+    BadgeNotCreated = 23,
+
+    #[msg("Badge already exists; refresh it with verify_and_store_tier")]
+    BadgeAlreadyExists = 24,
+
+    #[msg("Malformed proof: a proof_b coordinate is not below the BN254 field modulus")]
+    ProofMalformed = 25,
+
+    #[msg("Signer is not the config's pauser")]
+    NotPauser = 26,
+
+    #[msg("Account type is not allowed by the config")]
+    AccountTypeNotAllowed = 27,
+
+    #[msg("Nullifier is not a canonical BN254 scalar (must be below the field order)")]
+    NonCanonicalNullifier = 28,
+
+    #[msg("Hook program account is missing or does not match the config's hook_program")]
+    HookProgramMismatch = 29,
+
+    #[msg("Badge is still in its probation window")]
+    BadgeInProbation = 30,
+
+    #[msg("Transaction bundles an instruction to a denied program")]
+    DisallowedInstructionBundle = 31,
+
+    #[msg("A wallet cannot refer itself")]
+    SelfReferral = 32,
+
+    #[msg("Referrer has no valid badge")]
+    ReferrerWithoutBadge = 33,
+
+    #[msg("Wallet balance is below the configured min_wallet_lamports")]
+    WalletUnderfunded = 34,
+
+    #[msg("Treasury cannot cover the rent subsidy this epoch")]
+    TreasuryInsufficient = 35,
+
+    #[msg("Compressed proof points do not decode to curve points")]
+    ProofDecompressionFailed = 36,
+
+    #[msg("Range bounds must satisfy lower < upper <= MAX_RANGE_UPPER_BOUND")]
+    InvalidRange = 37,
+
+    #[msg("This badge was burned and can't be created again")]
+    BadgeBurned = 38,
+
+    #[msg("Badge refreshes must be co-signed by the relayer that created it")]
+    RelayerMismatch = 39,
+
+    #[msg("Missing or invalid Ed25519 authorization from the badge owner")]
+    DelegationSignatureInvalid = 40,
+
+    #[msg("Badge has reached max_refreshes_per_window for this window")]
+    RefreshLimitReached = 41,
+
+    #[msg("Unknown public input layout version")]
+    UnknownLayoutVersion = 42,
+
+    #[msg("On-chain clock is earlier than the badge's creation")]
+    ClockRegressed = 43,
+
+    #[msg("Expiry bucket is not for the badge's expiry day")]
+    InvalidExpiryBucket = 44,
+
+    #[msg("Expiry bucket is full")]
+    ExpiryBucketFull = 45,
+
+    #[msg("Nullifier matches a denied prefix")]
+    NullifierDenied = 46,
+
+    #[msg("Verifying key is malformed")]
+    InvalidVerifyingKey = 47,
+
+    #[msg("Badge tier is below the pool's minimum tier")]
+    TierBelowPoolMinimum = 48,
+
+    #[msg("Unsupported signature scheme")]
+    UnsupportedSigScheme = 49,
+
+    #[msg("Label is too long, not UTF-8, or contains control characters")]
+    InvalidLabel = 50,
+
+    #[msg("Reward vault cannot cover the first-verification reward")]
+    RewardVaultEmpty = 51,
+
+    #[msg("No verifying key accepts proofs made at this timestamp")]
+    NoActiveVerifyingKey = 52,
+
+    #[msg("Withdrawal exceeds the vault's balance above its rent-exempt reserve")]
+    WithdrawalExceedsAvailable = 53,
+
+    #[msg("Withdrawals take exactly one of the treasury and the reward vault")]
+    InvalidWithdrawalSource = 54,
+
+    #[msg("Tier is above the highest tier this deployment accepts")]
+    TierAboveMaximum = 55,
+
+    #[msg("Badge was created by a program version older than required")]
+    ProgramVersionTooOld = 56,
+
+    #[msg("Staged verification is too old to store")]
+    StaleVerification = 57,
+
+    #[msg("Badge was verified before the current verification epoch")]
+    BadgeEpochExpired = 58,
+
+    #[msg("Tier bounds are implausibly large; check their unit")]
+    BoundsImplausible = 59,
+
+    #[msg("Coupon has no uses left")]
+    CouponExhausted = 60,
+
+    #[msg("Coupon has expired")]
+    CouponExpired = 61,
+
+    #[msg("Badge has expired")]
+    BadgeExpired = 62,
+
+    #[msg("Badge and nullifier record disagree")]
+    NullifierDesync = 63,
+
+    #[msg("Requested validity horizon exceeds max_validity_seconds; no badge can satisfy it")]
+    HorizonExceedsMaxValidity = 64,
+
+    #[msg("Badge nullifier was never registered")]
+    NullifierNotEnforced = 65,
+
+    #[msg("Verification log does not belong to the badge owner")]
+    InvalidVerificationLog = 66,
+
+    #[msg("Verification log is full")]
+    VerificationLogFull = 67,
+
+    #[msg("Badge account must be writable for this instruction")]
+    BadgeNotWritable = 68,
+
+    #[msg("Badge tier is below the DAO's minimum tier")]
+    TierBelowDaoMinimum = 69,
+
+    #[msg("Wallet is already a member of this DAO")]
+    AlreadyMember = 70,
+
+    #[msg("Nullifier record is required while nullifier uniqueness is enforced")]
+    NullifierRecordRequired = 71,
+
+    #[msg("Identity rotation does not match this badge, wallet and nullifier")]
+    InvalidIdentityRotation = 72,
+
+    #[msg("Poseidon hashing of the badge commitment failed")]
+    CommitmentFailed = 73,
+
+    #[msg("Account is not a TierBadge in a known layout")]
+    UnknownBadgeLayout = 74,
+
+    #[msg("Listed badge's expiry bucket must be passed")]
+    ExpiryBucketRequired = 75,
+}
+
+/// Numeric error codes as returned by the program, for clients that match
+/// on codes rather than IDL names.
+pub mod codes {
+    pub const PROOF_VERIFICATION_FAILED: u32 = 6000;
+    pub const INVALID_TIER: u32 = 6001;
+    pub const PROOF_TOO_OLD: u32 = 6002;
+    pub const NULLIFIER_ALREADY_USED: u32 = 6003;
+    pub const BADGE_NOT_EXPIRED: u32 = 6004;
+    pub const NULLIFIER_CHANGED: u32 = 6005;
+    pub const PAUSED: u32 = 6006;
+    pub const UNAUTHORIZED: u32 = 6007;
+    pub const INVALID_CONFIG: u32 = 6008;
+    pub const TIMELOCK_NOT_ELAPSED: u32 = 6009;
+    pub const NO_PENDING_CHANGE: u32 = 6010;
+    pub const WALLET_TOO_NEW: u32 = 6011;
+    pub const POST_ROTATION_COOLDOWN: u32 = 6012;
+    pub const MINT_TIER_MISMATCH: u32 = 6013;
+    pub const DUPLICATE_REQUEST: u32 = 6014;
+    pub const MISSING_REQUEST_RECEIPT: u32 = 6015;
+    pub const TIER_TABLE_FROZEN: u32 = 6016;
+    pub const ZERO_TIER_BOUNDS: u32 = 6017;
+    pub const PROVER_SIGNATURE_INVALID: u32 = 6018;
+    pub const INVALID_DOWNGRADE: u32 = 6019;
+    pub const VALIDITY_EXCEEDS_CAP: u32 = 6020;
+    pub const OWNER_SIGNER_MISMATCH: u32 = 6021;
+    pub const INVALID_SWEEP_ACCOUNTS: u32 = 6022;
+    pub const BADGE_NOT_CREATED: u32 = 6023;
+    pub const BADGE_ALREADY_EXISTS: u32 = 6024;
+    pub const PROOF_MALFORMED: u32 = 6025;
+    pub const NOT_PAUSER: u32 = 6026;
+    pub const ACCOUNT_TYPE_NOT_ALLOWED: u32 = 6027;
+    pub const NON_CANONICAL_NULLIFIER: u32 = 6028;
+    pub const HOOK_PROGRAM_MISMATCH: u32 = 6029;
+    pub const BADGE_IN_PROBATION: u32 = 6030;
+    pub const DISALLOWED_INSTRUCTION_BUNDLE: u32 = 6031;
+    pub const SELF_REFERRAL: u32 = 6032;
+    pub const REFERRER_WITHOUT_BADGE: u32 = 6033;
+    pub const WALLET_UNDERFUNDED: u32 = 6034;
+    pub const TREASURY_INSUFFICIENT: u32 = 6035;
+    pub const PROOF_DECOMPRESSION_FAILED: u32 = 6036;
+    pub const INVALID_RANGE: u32 = 6037;
+    pub const BADGE_BURNED: u32 = 6038;
+    pub const RELAYER_MISMATCH: u32 = 6039;
+    pub const DELEGATION_SIGNATURE_INVALID: u32 = 6040;
+    pub const REFRESH_LIMIT_REACHED: u32 = 6041;
+    pub const UNKNOWN_LAYOUT_VERSION: u32 = 6042;
+    pub const CLOCK_REGRESSED: u32 = 6043;
+    pub const INVALID_EXPIRY_BUCKET: u32 = 6044;
+    pub const EXPIRY_BUCKET_FULL: u32 = 6045;
+    pub const NULLIFIER_DENIED: u32 = 6046;
+    pub const INVALID_VERIFYING_KEY: u32 = 6047;
+    pub const TIER_BELOW_POOL_MINIMUM: u32 = 6048;
+    pub const UNSUPPORTED_SIG_SCHEME: u32 = 6049;
+    pub const INVALID_LABEL: u32 = 6050;
+    pub const REWARD_VAULT_EMPTY: u32 = 6051;
+    pub const NO_ACTIVE_VERIFYING_KEY: u32 = 6052;
+    pub const WITHDRAWAL_EXCEEDS_AVAILABLE: u32 = 6053;
+    pub const INVALID_WITHDRAWAL_SOURCE: u32 = 6054;
+    pub const TIER_ABOVE_MAXIMUM: u32 = 6055;
+    pub const PROGRAM_VERSION_TOO_OLD: u32 = 6056;
+    pub const STALE_VERIFICATION: u32 = 6057;
+    pub const BADGE_EPOCH_EXPIRED: u32 = 6058;
+    pub const BOUNDS_IMPLAUSIBLE: u32 = 6059;
+    pub const COUPON_EXHAUSTED: u32 = 6060;
+    pub const COUPON_EXPIRED: u32 = 6061;
+    pub const BADGE_EXPIRED: u32 = 6062;
+    pub const NULLIFIER_DESYNC: u32 = 6063;
+    pub const HORIZON_EXCEEDS_MAX_VALIDITY: u32 = 6064;
+    pub const NULLIFIER_NOT_ENFORCED: u32 = 6065;
+    pub const INVALID_VERIFICATION_LOG: u32 = 6066;
+    pub const VERIFICATION_LOG_FULL: u32 = 6067;
+    pub const BADGE_NOT_WRITABLE: u32 = 6068;
+    pub const TIER_BELOW_DAO_MINIMUM: u32 = 6069;
+    pub const ALREADY_MEMBER: u32 = 6070;
+    pub const NULLIFIER_RECORD_REQUIRED: u32 = 6071;
+    pub const INVALID_IDENTITY_ROTATION: u32 = 6072;
+    pub const COMMITMENT_FAILED: u32 = 6073;
+    pub const UNKNOWN_BADGE_LAYOUT: u32 = 6074;
+    pub const EXPIRY_BUCKET_REQUIRED: u32 = 6075;
+}
+
+/// Reason code for failures raised outside this program (account
+/// constraints, sysvar access, ...).
+pub const REASON_OTHER: u32 = 0;
+
+impl ProofOfLoveError {
+    /// Every variant, in declaration order.
+    pub const ALL: [ProofOfLoveError; 76] = [
+        ProofOfLoveError::ProofVerificationFailed,
+        ProofOfLoveError::InvalidTier,
+        ProofOfLoveError::ProofTooOld,
+        ProofOfLoveError::NullifierAlreadyUsed,
+        ProofOfLoveError::BadgeNotExpired,
+        ProofOfLoveError::NullifierChanged,
+        ProofOfLoveError::Paused,
+        ProofOfLoveError::Unauthorized,
+        ProofOfLoveError::InvalidConfig,
+        ProofOfLoveError::TimelockNotElapsed,
+        ProofOfLoveError::NoPendingChange,
+        ProofOfLoveError::WalletTooNew,
+        ProofOfLoveError::PostRotationCooldown,
+        ProofOfLoveError::MintTierMismatch,
+        ProofOfLoveError::DuplicateRequest,
+        ProofOfLoveError::MissingRequestReceipt,
+        ProofOfLoveError::TierTableFrozen,
+        ProofOfLoveError::ZeroTierBounds,
+        ProofOfLoveError::ProverSignatureInvalid,
+        ProofOfLoveError::InvalidDowngrade,
+        ProofOfLoveError::ValidityExceedsCap,
+        ProofOfLoveError::OwnerSignerMismatch,
+        ProofOfLoveError::InvalidSweepAccounts,
+        ProofOfLoveError::BadgeNotCreated,
+        ProofOfLoveError::BadgeAlreadyExists,
+        ProofOfLoveError::ProofMalformed,
+        ProofOfLoveError::NotPauser,
+        ProofOfLoveError::AccountTypeNotAllowed,
+        ProofOfLoveError::NonCanonicalNullifier,
+        ProofOfLoveError::HookProgramMismatch,
+        ProofOfLoveError::BadgeInProbation,
+        ProofOfLoveError::DisallowedInstructionBundle,
+        ProofOfLoveError::SelfReferral,
+        ProofOfLoveError::ReferrerWithoutBadge,
+        ProofOfLoveError::WalletUnderfunded,
+        ProofOfLoveError::TreasuryInsufficient,
+        ProofOfLoveError::ProofDecompressionFailed,
+        ProofOfLoveError::InvalidRange,
+        ProofOfLoveError::BadgeBurned,
+        ProofOfLoveError::RelayerMismatch,
+        ProofOfLoveError::DelegationSignatureInvalid,
+        ProofOfLoveError::RefreshLimitReached,
+        ProofOfLoveError::UnknownLayoutVersion,
+        ProofOfLoveError::ClockRegressed,
+        ProofOfLoveError::InvalidExpiryBucket,
+        ProofOfLoveError::ExpiryBucketFull,
+        ProofOfLoveError::NullifierDenied,
+        ProofOfLoveError::InvalidVerifyingKey,
+        ProofOfLoveError::TierBelowPoolMinimum,
+        ProofOfLoveError::UnsupportedSigScheme,
+        ProofOfLoveError::InvalidLabel,
+        ProofOfLoveError::RewardVaultEmpty,
+        ProofOfLoveError::NoActiveVerifyingKey,
+        ProofOfLoveError::WithdrawalExceedsAvailable,
+        ProofOfLoveError::InvalidWithdrawalSource,
+        ProofOfLoveError::TierAboveMaximum,
+        ProofOfLoveError::ProgramVersionTooOld,
+        ProofOfLoveError::StaleVerification,
+        ProofOfLoveError::BadgeEpochExpired,
+        ProofOfLoveError::BoundsImplausible,
+        ProofOfLoveError::CouponExhausted,
+        ProofOfLoveError::CouponExpired,
+        ProofOfLoveError::BadgeExpired,
+        ProofOfLoveError::NullifierDesync,
+        ProofOfLoveError::HorizonExceedsMaxValidity,
+        ProofOfLoveError::NullifierNotEnforced,
+        ProofOfLoveError::InvalidVerificationLog,
+        ProofOfLoveError::VerificationLogFull,
+        ProofOfLoveError::BadgeNotWritable,
+        ProofOfLoveError::TierBelowDaoMinimum,
+        ProofOfLoveError::AlreadyMember,
+        ProofOfLoveError::NullifierRecordRequired,
+        ProofOfLoveError::InvalidIdentityRotation,
+        ProofOfLoveError::CommitmentFailed,
+        ProofOfLoveError::UnknownBadgeLayout,
+        ProofOfLoveError::ExpiryBucketRequired,
+    ];
+
+    /// Stable reason code reported in `VerificationFailed` events.
+    /// Codes are never reused or renumbered — new variants take the next
+    /// free value.
+    pub fn reason_code(self) -> u32 {
+        match self {
+            ProofOfLoveError::ProofVerificationFailed => 1,
+            ProofOfLoveError::InvalidTier => 2,
+            ProofOfLoveError::ProofTooOld => 3,
+            ProofOfLoveError::NullifierAlreadyUsed => 4,
+            ProofOfLoveError::BadgeNotExpired => 5,
+            ProofOfLoveError::NullifierChanged => 6,
+            ProofOfLoveError::Paused => 7,
+            ProofOfLoveError::Unauthorized => 8,
+            ProofOfLoveError::InvalidConfig => 9,
+            ProofOfLoveError::TimelockNotElapsed => 10,
+            ProofOfLoveError::NoPendingChange => 11,
+            ProofOfLoveError::WalletTooNew => 12,
+            ProofOfLoveError::PostRotationCooldown => 13,
+            ProofOfLoveError::MintTierMismatch => 14,
+            ProofOfLoveError::DuplicateRequest => 15,
+            ProofOfLoveError::MissingRequestReceipt => 16,
+            ProofOfLoveError::TierTableFrozen => 17,
+            ProofOfLoveError::ZeroTierBounds => 18,
+            ProofOfLoveError::ProverSignatureInvalid => 19,
+            ProofOfLoveError::InvalidDowngrade => 20,
+            ProofOfLoveError::ValidityExceedsCap => 21,
+            ProofOfLoveError::OwnerSignerMismatch => 22,
+            ProofOfLoveError::InvalidSweepAccounts => 23,
+            ProofOfLoveError::BadgeNotCreated => 24,
+            ProofOfLoveError::BadgeAlreadyExists => 25,
+            ProofOfLoveError::ProofMalformed => 26,
+            ProofOfLoveError::NotPauser => 27,
+            ProofOfLoveError::AccountTypeNotAllowed => 28,
+            ProofOfLoveError::NonCanonicalNullifier => 29,
+            ProofOfLoveError::HookProgramMismatch => 30,
+            ProofOfLoveError::BadgeInProbation => 31,
+            ProofOfLoveError::DisallowedInstructionBundle => 32,
+            ProofOfLoveError::SelfReferral => 33,
+            ProofOfLoveError::ReferrerWithoutBadge => 34,
+            ProofOfLoveError::WalletUnderfunded => 35,
+            ProofOfLoveError::TreasuryInsufficient => 36,
+            ProofOfLoveError::ProofDecompressionFailed => 37,
+            ProofOfLoveError::InvalidRange => 38,
+            ProofOfLoveError::BadgeBurned => 39,
+            ProofOfLoveError::RelayerMismatch => 40,
+            ProofOfLoveError::DelegationSignatureInvalid => 41,
+            ProofOfLoveError::RefreshLimitReached => 42,
+            ProofOfLoveError::UnknownLayoutVersion => 43,
+            ProofOfLoveError::ClockRegressed => 44,
+            ProofOfLoveError::InvalidExpiryBucket => 45,
+            ProofOfLoveError::ExpiryBucketFull => 46,
+            ProofOfLoveError::NullifierDenied => 47,
+            ProofOfLoveError::InvalidVerifyingKey => 48,
+            ProofOfLoveError::TierBelowPoolMinimum => 49,
+            ProofOfLoveError::UnsupportedSigScheme => 50,
+            ProofOfLoveError::InvalidLabel => 51,
+            ProofOfLoveError::RewardVaultEmpty => 52,
+            ProofOfLoveError::NoActiveVerifyingKey => 53,
+            ProofOfLoveError::WithdrawalExceedsAvailable => 54,
+            ProofOfLoveError::InvalidWithdrawalSource => 55,
+            ProofOfLoveError::TierAboveMaximum => 56,
+            ProofOfLoveError::ProgramVersionTooOld => 57,
+            ProofOfLoveError::StaleVerification => 58,
+            ProofOfLoveError::BadgeEpochExpired => 59,
+            ProofOfLoveError::BoundsImplausible => 60,
+            ProofOfLoveError::CouponExhausted => 61,
+            ProofOfLoveError::CouponExpired => 62,
+            ProofOfLoveError::BadgeExpired => 63,
+            ProofOfLoveError::NullifierDesync => 64,
+            ProofOfLoveError::HorizonExceedsMaxValidity => 65,
+            ProofOfLoveError::NullifierNotEnforced => 66,
+            ProofOfLoveError::InvalidVerificationLog => 67,
+            ProofOfLoveError::VerificationLogFull => 68,
+            ProofOfLoveError::BadgeNotWritable => 69,
+            ProofOfLoveError::TierBelowDaoMinimum => 70,
+            ProofOfLoveError::AlreadyMember => 71,
+            ProofOfLoveError::NullifierRecordRequired => 72,
+            ProofOfLoveError::InvalidIdentityRotation => 73,
+            ProofOfLoveError::CommitmentFailed => 74,
+            ProofOfLoveError::UnknownBadgeLayout => 75,
+            ProofOfLoveError::ExpiryBucketRequired => 76,
+        }
+    }
+}
+
+/// Map any instruction error to its stable reason code.
+pub fn reason_code(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(e) => ProofOfLoveError::ALL
+            .into_iter()
+            .find(|variant| u32::from(*variant) == e.error_code_number)
+            .map_or(REASON_OTHER, ProofOfLoveError::reason_code),
+        Error::ProgramError(_) => REASON_OTHER,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes_match_pinned_constants() {
+        let pinned = [
+            (
+                ProofOfLoveError::ProofVerificationFailed,
+                codes::PROOF_VERIFICATION_FAILED,
+            ),
+            (ProofOfLoveError::InvalidTier, codes::INVALID_TIER),
+            (ProofOfLoveError::ProofTooOld, codes::PROOF_TOO_OLD),
+            (
+                ProofOfLoveError::NullifierAlreadyUsed,
+                codes::NULLIFIER_ALREADY_USED,
+            ),
+            (ProofOfLoveError::BadgeNotExpired, codes::BADGE_NOT_EXPIRED),
+            (ProofOfLoveError::NullifierChanged, codes::NULLIFIER_CHANGED),
+            (ProofOfLoveError::Paused, codes::PAUSED),
+            (ProofOfLoveError::Unauthorized, codes::UNAUTHORIZED),
+            (ProofOfLoveError::InvalidConfig, codes::INVALID_CONFIG),
+            (
+                ProofOfLoveError::TimelockNotElapsed,
+                codes::TIMELOCK_NOT_ELAPSED,
+            ),
+            (ProofOfLoveError::NoPendingChange, codes::NO_PENDING_CHANGE),
+            (ProofOfLoveError::WalletTooNew, codes::WALLET_TOO_NEW),
+            (
+                ProofOfLoveError::PostRotationCooldown,
+                codes::POST_ROTATION_COOLDOWN,
+            ),
+            (
+                ProofOfLoveError::MintTierMismatch,
+                codes::MINT_TIER_MISMATCH,
+            ),
+            (ProofOfLoveError::DuplicateRequest, codes::DUPLICATE_REQUEST),
+            (
+                ProofOfLoveError::MissingRequestReceipt,
+                codes::MISSING_REQUEST_RECEIPT,
+            ),
+            (ProofOfLoveError::TierTableFrozen, codes::TIER_TABLE_FROZEN),
+            (ProofOfLoveError::ZeroTierBounds, codes::ZERO_TIER_BOUNDS),
+            (
+                ProofOfLoveError::ProverSignatureInvalid,
+                codes::PROVER_SIGNATURE_INVALID,
+            ),
+            (ProofOfLoveError::InvalidDowngrade, codes::INVALID_DOWNGRADE),
+            (
+                ProofOfLoveError::ValidityExceedsCap,
+                codes::VALIDITY_EXCEEDS_CAP,
+            ),
+            (
+                ProofOfLoveError::OwnerSignerMismatch,
+                codes::OWNER_SIGNER_MISMATCH,
+            ),
+            (
+                ProofOfLoveError::InvalidSweepAccounts,
+                codes::INVALID_SWEEP_ACCOUNTS,
+            ),
+            (ProofOfLoveError::BadgeNotCreated, codes::BADGE_NOT_CREATED),
+            (
+                ProofOfLoveError::BadgeAlreadyExists,
+                codes::BADGE_ALREADY_EXISTS,
+            ),
+            (ProofOfLoveError::ProofMalformed, codes::PROOF_MALFORMED),
+            (ProofOfLoveError::NotPauser, codes::NOT_PAUSER),
+            (
+                ProofOfLoveError::AccountTypeNotAllowed,
+                codes::ACCOUNT_TYPE_NOT_ALLOWED,
+            ),
+            (
+                ProofOfLoveError::NonCanonicalNullifier,
+                codes::NON_CANONICAL_NULLIFIER,
+            ),
+            (
+                ProofOfLoveError::HookProgramMismatch,
+                codes::HOOK_PROGRAM_MISMATCH,
+            ),
+            (
+                ProofOfLoveError::BadgeInProbation,
+                codes::BADGE_IN_PROBATION,
+            ),
+            (
+                ProofOfLoveError::DisallowedInstructionBundle,
+                codes::DISALLOWED_INSTRUCTION_BUNDLE,
+            ),
+            (ProofOfLoveError::SelfReferral, codes::SELF_REFERRAL),
+            (
+                ProofOfLoveError::ReferrerWithoutBadge,
+                codes::REFERRER_WITHOUT_BADGE,
+            ),
+            (
+                ProofOfLoveError::WalletUnderfunded,
+                codes::WALLET_UNDERFUNDED,
+            ),
+            (
+                ProofOfLoveError::TreasuryInsufficient,
+                codes::TREASURY_INSUFFICIENT,
+            ),
+            (
+                ProofOfLoveError::ProofDecompressionFailed,
+                codes::PROOF_DECOMPRESSION_FAILED,
+            ),
+            (ProofOfLoveError::InvalidRange, codes::INVALID_RANGE),
+            (ProofOfLoveError::BadgeBurned, codes::BADGE_BURNED),
+            (ProofOfLoveError::RelayerMismatch, codes::RELAYER_MISMATCH),
+            (
+                ProofOfLoveError::DelegationSignatureInvalid,
+                codes::DELEGATION_SIGNATURE_INVALID,
+            ),
+            (
+                ProofOfLoveError::RefreshLimitReached,
+                codes::REFRESH_LIMIT_REACHED,
+            ),
+            (
+                ProofOfLoveError::UnknownLayoutVersion,
+                codes::UNKNOWN_LAYOUT_VERSION,
+            ),
+            (ProofOfLoveError::ClockRegressed, codes::CLOCK_REGRESSED),
+            (
+                ProofOfLoveError::InvalidExpiryBucket,
+                codes::INVALID_EXPIRY_BUCKET,
+            ),
+            (
+                ProofOfLoveError::ExpiryBucketFull,
+                codes::EXPIRY_BUCKET_FULL,
+            ),
+            (ProofOfLoveError::NullifierDenied, codes::NULLIFIER_DENIED),
+            (
+                ProofOfLoveError::InvalidVerifyingKey,
+                codes::INVALID_VERIFYING_KEY,
+            ),
+            (
+                ProofOfLoveError::TierBelowPoolMinimum,
+                codes::TIER_BELOW_POOL_MINIMUM,
+            ),
+            (
+                ProofOfLoveError::UnsupportedSigScheme,
+                codes::UNSUPPORTED_SIG_SCHEME,
+            ),
+            (ProofOfLoveError::InvalidLabel, codes::INVALID_LABEL),
+            (
+                ProofOfLoveError::RewardVaultEmpty,
+                codes::REWARD_VAULT_EMPTY,
+            ),
+            (
+                ProofOfLoveError::NoActiveVerifyingKey,
+                codes::NO_ACTIVE_VERIFYING_KEY,
+            ),
+            (
+                ProofOfLoveError::WithdrawalExceedsAvailable,
+                codes::WITHDRAWAL_EXCEEDS_AVAILABLE,
+            ),
+            (
+                ProofOfLoveError::InvalidWithdrawalSource,
+                codes::INVALID_WITHDRAWAL_SOURCE,
+            ),
+            (
+                ProofOfLoveError::TierAboveMaximum,
+                codes::TIER_ABOVE_MAXIMUM,
+            ),
+            (
+                ProofOfLoveError::ProgramVersionTooOld,
+                codes::PROGRAM_VERSION_TOO_OLD,
+            ),
+            (
+                ProofOfLoveError::StaleVerification,
+                codes::STALE_VERIFICATION,
+            ),
+            (
+                ProofOfLoveError::BadgeEpochExpired,
+                codes::BADGE_EPOCH_EXPIRED,
+            ),
+            (
+                ProofOfLoveError::BoundsImplausible,
+                codes::BOUNDS_IMPLAUSIBLE,
+            ),
+            (ProofOfLoveError::CouponExhausted, codes::COUPON_EXHAUSTED),
+            (ProofOfLoveError::CouponExpired, codes::COUPON_EXPIRED),
+            (ProofOfLoveError::BadgeExpired, codes::BADGE_EXPIRED),
+            (ProofOfLoveError::NullifierDesync, codes::NULLIFIER_DESYNC),
+            (
+                ProofOfLoveError::HorizonExceedsMaxValidity,
+                codes::HORIZON_EXCEEDS_MAX_VALIDITY,
+            ),
+            (
+                ProofOfLoveError::NullifierNotEnforced,
+                codes::NULLIFIER_NOT_ENFORCED,
+            ),
+            (
+                ProofOfLoveError::InvalidVerificationLog,
+                codes::INVALID_VERIFICATION_LOG,
+            ),
+            (
+                ProofOfLoveError::VerificationLogFull,
+                codes::VERIFICATION_LOG_FULL,
+            ),
+            (
+                ProofOfLoveError::BadgeNotWritable,
+                codes::BADGE_NOT_WRITABLE,
+            ),
+            (
+                ProofOfLoveError::TierBelowDaoMinimum,
+                codes::TIER_BELOW_DAO_MINIMUM,
+            ),
+            (ProofOfLoveError::AlreadyMember, codes::ALREADY_MEMBER),
+            (
+                ProofOfLoveError::NullifierRecordRequired,
+                codes::NULLIFIER_RECORD_REQUIRED,
+            ),
+            (
+                ProofOfLoveError::InvalidIdentityRotation,
+                codes::INVALID_IDENTITY_ROTATION,
+            ),
+            (ProofOfLoveError::CommitmentFailed, codes::COMMITMENT_FAILED),
+            (
+                ProofOfLoveError::UnknownBadgeLayout,
+                codes::UNKNOWN_BADGE_LAYOUT,
+            ),
+            (
+                ProofOfLoveError::ExpiryBucketRequired,
+                codes::EXPIRY_BUCKET_REQUIRED,
+            ),
+        ];
+        assert_eq!(pinned.len(), ProofOfLoveError::ALL.len());
+        for (variant, code) in pinned {
+            assert_eq!(u32::from(variant), code, "{variant:?}");
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+
+use crate::state::AccountType;
+
+/// Emitted by `verify_and_store_tier` when verification fails (requires the
+/// `log-failures` feature). The transaction still fails and rolls back, but
+/// the event survives in the transaction logs.
+#[event]
+pub struct VerificationFailed {
+    /// Stable reason code, see `errors::reason_code`
+    pub reason_code: u32,
+}
+
+/// Emitted whenever a verification creates or refreshes a TierBadge.
+#[event]
+pub struct TierVerified {
+    /// The wallet that owns the badge
+    pub owner: Pubkey,
+    /// Verified tier (1-7)
+    pub tier: u8,
+    /// Kind of holder the badge was verified for
+    pub account_type: AccountType,
+    /// Unix timestamp of the proof
+    pub verified_at: i64,
+    /// Unix timestamp when the badge expires
+    pub expires_at: i64,
+    /// Address of the TierBadge account that was written
+    pub tier_badge: Pubkey,
+    /// ISO 4217 code of the currency the tier's bounds are denominated in
+    pub currency_code: [u8; 3],
+    /// The badge's display label, zero-padded; all zeros if unset
+    pub label: [u8; 32],
+}
+
+/// Emitted whenever `verify_range` creates or refreshes a RangeBadge.
+#[event]
+pub struct RangeVerified {
+    /// The wallet that owns the badge
+    pub owner: Pubkey,
+    /// Proven lower bound in USD cents
+    pub lower_bound: u64,
+    /// Proven upper bound in USD cents
+    pub upper_bound: u64,
+    /// Unix timestamp when the badge expires
+    pub expires_at: i64,
+    /// Address of the RangeBadge account that was written
+    pub range_badge: Pubkey,
+}
+
+/// Emitted by `downgrade_badge` when the owner voluntarily lowers a badge's
+/// tier. The badge stays open and nothing was revoked; closing a badge
+/// emits only `BadgeClosed`.
+#[event]
+pub struct TierDowngraded {
+    /// Address of the TierBadge account
+    pub tier_badge: Pubkey,
+    /// The wallet that owns the badge
+    pub owner: Pubkey,
+    /// The tier the badge held before the downgrade
+    pub from: u8,
+    /// The tier the badge holds now
+    pub to: u8,
+}
+
+/// Emitted whenever a TierBadge account is closed: by `revoke_expired_tier`
+/// and by `burn_badge`, and by nothing else. Indexers should drop the
+/// account on this event whatever the reason for the closure.
+#[event]
+pub struct BadgeClosed {
+    /// Address of the closed TierBadge account
+    pub tier_badge: Pubkey,
+    /// The wallet that owned the badge
+    pub owner: Pubkey,
+}
+
+/// Emitted by `set_label` when the owner sets or clears a badge's label.
+#[event]
+pub struct BadgeLabelSet {
+    /// Address of the TierBadge account
+    pub tier_badge: Pubkey,
+    /// The wallet that owns the badge
+    pub owner: Pubkey,
+    /// The new label, zero-padded; all zeros when cleared
+    pub label: [u8; 32],
+}
+
+/// Emitted by `redeem_coupon` when a coupon extends a badge.
+#[event]
+pub struct CouponRedeemed {
+    /// Address of the Coupon account
+    pub coupon: Pubkey,
+    /// Address of the TierBadge account
+    pub tier_badge: Pubkey,
+    /// The wallet that owns the badge
+    pub owner: Pubkey,
+    /// The badge's new expiry
+    pub expires_at: i64,
+}
+
+/// Emitted by `withdraw_treasury` for every withdrawal, so the vaults'
+/// outflows can be audited from logs.
+#[event]
+pub struct VaultWithdrawn {
+    /// The Treasury or RewardVault the lamports left
+    pub vault: Pubkey,
+    /// The wallet that received them
+    pub recipient: Pubkey,
+    /// Lamports withdrawn
+    pub amount: u64,
+}
+
+/// Emitted by `verify_with_referral` when a referral is registered.
+#[event]
+pub struct ReferralRegistered {
+    /// The newly verified wallet
+    pub referee: Pubkey,
+    /// The wallet credited with the referral
+    pub referrer: Pubkey,
+}
+
+/// Emitted for every leaf appended to a VerificationLog, so indexers can
+/// rebuild the tree and prove any past verification against its root.
+#[event]
+pub struct VerificationLogged {
+    /// Address of the VerificationLog account
+    pub verification_log: Pubkey,
+    /// The wallet whose verification was logged
+    pub owner: Pubkey,
+    /// Index of the new leaf
+    pub index: u64,
+    /// The leaf, see `VerificationLog::leaf`
+    pub leaf: [u8; 32],
+    /// The log's root after the append
+    pub root: [u8; 32],
+}
+
+/// Emitted by `verify_and_join` when a wallet joins a gated DAO.
+#[event]
+pub struct MemberJoined {
+    /// The DAO joined
+    pub dao: Pubkey,
+    /// The wallet that joined
+    pub member: Pubkey,
+    /// Tier the wallet verified at
+    pub tier: u8,
+}
+
+/// Emitted when a refresh moves a badge to a new nullifier under an
+/// `IdentityRotation`.
+#[event]
+pub struct IdentityRotated {
+    /// The wallet whose badge rotated
+    pub owner: Pubkey,
+    /// The retired nullifier, whose registry entry was closed
+    pub old_nullifier: [u8; 32],
+    /// The badge's new nullifier
+    pub new_nullifier: [u8; 32],
+}
@@ -0,0 +1,116 @@
+//! Deployment health checks, reported by `health_check` as a bitmask with
+//! one bit per check that passed.
+
+use anchor_lang::solana_program::hash::hashv;
+use groth16_solana::groth16::Groth16Verifyingkey;
+
+use crate::state::{tiers_contiguous, Config, TierBounds, TIER_COUNT};
+
+/// The tier table ascends contiguously from zero
+pub const TIER_TABLE_CONTIGUOUS: u8 = 1 << 0;
+/// The badge validity window is positive and within `max_validity_seconds`
+pub const VALIDITY_WITHIN_CAP: u8 = 1 << 1;
+/// The compiled verifying key hashes to the caller's expected hash
+pub const VK_HASH_MATCHES: u8 = 1 << 2;
+/// Verification is not paused
+pub const NOT_PAUSED: u8 = 1 << 3;
+
+/// Every check passed
+pub const ALL_CHECKS: u8 =
+    TIER_TABLE_CONTIGUOUS | VALIDITY_WITHIN_CAP | VK_HASH_MATCHES | NOT_PAUSED;
+
+/// SHA-256 over the verifying key's points in their on-chain encoding:
+/// `alpha_g1 | beta_g2 | gamma_g2 | delta_g2 | ic[0] | ic[1] | ...`. Equal
+/// to hashing the byte arrays `scripts/parse-vk.js` generates.
+pub fn vk_hash(vk: &Groth16Verifyingkey) -> [u8; 32] {
+    let mut parts: Vec<&[u8]> = vec![
+        &vk.vk_alpha_g1,
+        &vk.vk_beta_g2,
+        &vk.vk_gamme_g2,
+        &vk.vk_delta_g2,
+    ];
+    parts.extend(vk.vk_ic.iter().map(|point| point.as_slice()));
+    hashv(&parts).to_bytes()
+}
+
+/// Length of a verifying key fingerprint, in bytes
+pub const VK_FINGERPRINT_LEN: usize = 8;
+
+/// Short form of `vk_hash` for display and quick comparison: its first
+/// `VK_FINGERPRINT_LEN` bytes.
+pub fn vk_fingerprint(vk: &Groth16Verifyingkey) -> [u8; VK_FINGERPRINT_LEN] {
+    let mut fingerprint = [0; VK_FINGERPRINT_LEN];
+    fingerprint.copy_from_slice(&vk_hash(vk)[..VK_FINGERPRINT_LEN]);
+    fingerprint
+}
+
+/// SHA-256 over the tier table's bounds as big-endian u64s, in tier
+/// order: `tier1.lower | tier1.upper | tier2.lower | ...`. Clients hash
+/// their embedded table the same way to check it against the deployment
+/// before proving.
+pub fn tier_table_hash(tiers: &[TierBounds; TIER_COUNT]) -> [u8; 32] {
+    let bytes: Vec<[u8; 8]> = tiers
+        .iter()
+        .flat_map(|tier| [tier.lower.to_be_bytes(), tier.upper.to_be_bytes()])
+        .collect();
+    let parts: Vec<&[u8]> = bytes.iter().map(|b| b.as_slice()).collect();
+    hashv(&parts).to_bytes()
+}
+
+/// Run every check against `config` and the compiled verifying key.
+pub fn health_bits(config: &Config, vk: &Groth16Verifyingkey, expected_vk_hash: &[u8; 32]) -> u8 {
+    let checks = [
+        (TIER_TABLE_CONTIGUOUS, tiers_contiguous(&config.tiers)),
+        (
+            VALIDITY_WITHIN_CAP,
+            0 < config.badge_validity_seconds
+                && config.badge_validity_seconds <= config.max_validity_seconds,
+        ),
+        (VK_HASH_MATCHES, vk_hash(vk) == *expected_vk_hash),
+        (NOT_PAUSED, !config.paused),
+    ];
+    checks
+        .iter()
+        .filter(|(_, passed)| *passed)
+        .fold(0, |bits, (bit, _)| bits | bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DEFAULT_TIERS;
+    use crate::verifying_key::VERIFYING_KEY;
+
+    #[test]
+    fn vk_hash_covers_every_point() {
+        let hash = vk_hash(&VERIFYING_KEY);
+        let mut ic = VERIFYING_KEY.vk_ic.to_vec();
+        ic[0][0] ^= 1;
+        let altered = Groth16Verifyingkey {
+            vk_ic: &ic,
+            ..VERIFYING_KEY
+        };
+        assert_ne!(vk_hash(&altered), hash);
+        assert_eq!(vk_hash(&VERIFYING_KEY), hash);
+    }
+
+    #[test]
+    fn tier_table_hash_covers_every_bound() {
+        let hash = tier_table_hash(&DEFAULT_TIERS);
+        for i in 0..TIER_COUNT {
+            let mut altered = DEFAULT_TIERS;
+            altered[i].upper += 1;
+            assert_ne!(tier_table_hash(&altered), hash);
+            altered[i] = DEFAULT_TIERS[i];
+            altered[i].lower += 1;
+            assert_ne!(tier_table_hash(&altered), hash);
+        }
+        assert_eq!(tier_table_hash(&DEFAULT_TIERS), hash);
+    }
+
+    #[test]
+    fn vk_fingerprint_prefixes_vk_hash() {
+        let fingerprint = vk_fingerprint(&VERIFYING_KEY);
+        assert_eq!(fingerprint, vk_hash(&VERIFYING_KEY)[..VK_FINGERPRINT_LEN]);
+    }
+}
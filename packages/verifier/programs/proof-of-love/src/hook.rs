@@ -0,0 +1,62 @@
+//! Verification hooks: an integrator program, registered as the config's
+//! `hook_program`, that the program CPIs into after every successful
+//! verification.
+//!
+//! Interface: the hook receives a single Anchor-style instruction named
+//! `on_tier_verified`, i.e. data `sha256("global:on_tier_verified")[..8]`
+//! followed by the verified tier as one byte, and one read-only account,
+//! the freshly written TierBadge. The badge is serialized before the call,
+//! so the hook sees its final state.
+//!
+//! Failures are not best-effort: an error in the hook fails the whole
+//! verification. The hook cannot re-enter this program, since the runtime
+//! rejects indirect reentrancy, and `update_config` refuses this program as
+//! its own hook.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+
+/// Name of the instruction every hook program must expose
+pub const HOOK_INSTRUCTION: &str = "on_tier_verified";
+
+/// Instruction data for a hook call reporting `tier`.
+pub fn hook_data(tier: u8) -> Vec<u8> {
+    let preimage = format!("global:{HOOK_INSTRUCTION}");
+    let mut data = hash(preimage.as_bytes()).to_bytes()[..8].to_vec();
+    data.push(tier);
+    data
+}
+
+/// Call `hook_program` with the written badge and its tier.
+pub fn call_hook<'info>(
+    hook_program: &AccountInfo<'info>,
+    tier_badge: &AccountInfo<'info>,
+    tier: u8,
+) -> Result<()> {
+    let ix = Instruction {
+        program_id: hook_program.key(),
+        accounts: vec![AccountMeta::new_readonly(tier_badge.key(), false)],
+        data: hook_data(tier),
+    };
+    invoke(&ix, &[tier_badge.clone(), hook_program.clone()])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_anchor_discriminator() {
+        // sha256("global:on_tier_verified")[..8]
+        let data = hook_data(6);
+        assert_eq!(data.len(), 9);
+        assert_eq!(data[8], 6);
+        assert_eq!(
+            &data[..8],
+            &hash(b"global:on_tier_verified").to_bytes()[..8]
+        );
+    }
+}
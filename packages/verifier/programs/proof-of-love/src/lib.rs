@@ -1,21 +1,78 @@
+// The IDL instructions generated by `#[program]` still call the deprecated
+// `AccountInfo::realloc`.
+#![allow(deprecated)]
+// `verify_with_referral` takes nine arguments, and so do the CPI and client
+// helpers `#[program]` generates for it.
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::system_program;
+use anchor_spl::memo::{self, BuildMemo, Memo};
+use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount, Transfer};
 use groth16_solana::groth16::Groth16Verifier;
 
+pub mod attestation;
+pub mod bridge;
+pub mod bundle;
+#[cfg(any(test, feature = "client"))]
+pub mod client;
+pub mod commitment;
+pub mod encoding;
 pub mod errors;
+pub mod events;
+pub mod health;
+pub mod hook;
 pub mod state;
+#[cfg(feature = "test-vk")]
+pub mod test_vk;
 pub mod verifying_key;
 
 use errors::ProofOfLoveError;
-use state::TierBadge;
-use verifying_key::{NR_PUBLIC_INPUTS, VERIFYING_KEY};
+#[cfg(feature = "log-failures")]
+use events::VerificationFailed;
+use events::{
+    BadgeClosed, BadgeLabelSet, CouponRedeemed, IdentityRotated, MemberJoined, RangeVerified,
+    ReferralRegistered, TierDowngraded, TierVerified, VaultWithdrawn, VerificationLogged,
+};
+use state::{
+    encode_label, namespace_seed, AccountType, BadgeTombstone, Config, ConfigSnapshot,
+    ConfigUpdate, Coupon, DaoGate, ExpiryBucket, GateResult, IdentityRotation, Membership,
+    MintReceipt, NullifierRecord, PendingConfigChange, PendingVerification, RangeBadge, Referral,
+    RequestReceipt, RevocationReport, RewardClaim, RewardVault, StakePool, StakeReceipt, Tier,
+    TierBadge, TierDefinition, Treasury, VerificationLog, VotingPower, WalletMarker,
+    DEFAULT_BADGE_VALIDITY_SECONDS, DEFAULT_CURRENCY_CODE, DEFAULT_MAX_PROOF_AGE_SECONDS,
+    DEFAULT_MAX_VALIDITY_SECONDS, DEFAULT_NAMESPACE, DEFAULT_REFRESH_WINDOW_SECONDS,
+    DEFAULT_SANE_BOUNDS_MAX, DEFAULT_TIERS, DEFAULT_TIER_WEIGHTS, DEFAULT_VK_WINDOWS,
+    EXPIRY_BUCKET_PAGE_SIZE, LEGACY_TIER_BADGE_LEN, MAX_DENIED_PROGRAMS, REQUEST_ID_WINDOW_SLOTS,
+};
+use verifying_key::{NR_PUBLIC_INPUTS, PREVIOUS_VERIFYING_KEYS, VERIFYING_KEY};
 
 declare_id!("BBDtJxqUFWpCXMvZjtCFQyYGJ698o84H3RpqcJQjnGLR");
 
-/// 30 days in seconds
-const BADGE_VALIDITY_SECONDS: i64 = 30 * 24 * 60 * 60;
+/// Verifying keys compiled into this program: the current key and
+/// `PREVIOUS_VERIFYING_KEYS`.
+#[constant]
+pub const COMPILED_VERIFYING_KEYS: u8 = 1 + PREVIOUS_VERIFYING_KEYS.len() as u8;
+
+/// Compute units clients should request (via `ComputeBudgetInstruction`)
+/// for `verify_and_store_tier`. A proof is tried against every key whose
+/// window covers it, current key first, so during a migration overlap a
+/// proof made under the oldest key pays for a failed pairing against each
+/// newer one first. This covers one Groth16 verification of the 4 public
+/// inputs (roughly 200k CU on the alt_bn128 syscalls) per compiled key,
+/// plus first-time creation of the badge and nullifier record, with
+/// headroom. The test suite fails if a simulated verification, priced as
+/// if it matched the last key tried, exceeds it.
+#[constant]
+pub const RECOMMENDED_VERIFY_COMPUTE_UNITS: u32 =
+    100_000 + 200_000 * COMPILED_VERIFYING_KEYS as u32;
 
-/// 10 minutes in seconds — max age for a proof timestamp
-const MAX_PROOF_AGE_SECONDS: i64 = 10 * 60;
+/// Release of this program, bumped with every deployed upgrade. Stamped on
+/// each badge at creation as `TierBadge.program_version`, so integrators
+/// can reject badges created by releases they haven't reviewed.
+#[constant]
+pub const PROGRAM_VERSION: u16 = 1;
 
 #[program]
 pub mod proof_of_love {
@@ -27,120 +84,3885 @@ pub mod proof_of_love {
     /// Public signals: [tier_lower_bound, tier_upper_bound, nullifier, timestamp]
     ///
     /// proof_a must already have its y-coordinate negated before submission.
+    ///
+    /// To mark the badge prover-attested, precede this instruction with an
+    /// Ed25519 instruction in which the config's prover key signs the
+    /// concatenated public inputs, and pass the instructions sysvar.
+    ///
+    /// Retrying clients can pass a `client_request_id`; a second submission
+    /// with the same id within `REQUEST_ID_WINDOW_SLOTS` fails with
+    /// `DuplicateRequest` instead of being processed again.
+    ///
+    /// `account_type` records what kind of holder the badge represents and
+    /// must be allowed by `config.allowed_account_types`.
+    ///
+    /// A `namespace` stores the badge in an app-scoped slot at
+    /// `[b"tier_badge", user, namespace]`, so one wallet can hold a badge
+    /// per app. `None` (or all zeros) is the wallet's default badge.
+    ///
+    /// `layout_version` says how `public_inputs` are ordered (see
+    /// `encoding::LAYOUT_V1` and later layouts); unknown layouts fail with
+    /// `UnknownLayoutVersion`.
     pub fn verify_and_store_tier(
         ctx: Context<VerifyAndStoreTier>,
         proof_a: [u8; 64],
         proof_b: [u8; 128],
         proof_c: [u8; 64],
         public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+    ) -> Result<()> {
+        report_failure(
+            decode_layout(layout_version, &public_inputs).and_then(|public_inputs| {
+                verify_and_store(
+                    ctx.accounts,
+                    &ctx.bumps,
+                    &proof_a,
+                    &proof_b,
+                    &proof_c,
+                    &public_inputs,
+                    client_request_id,
+                    account_type,
+                    namespace,
+                    false,
+                )
+            }),
+        )
+    }
+
+    /// Verify a proof like `verify_and_store_tier`, with the proof points
+    /// compressed to save transaction space: `proof_a` and `proof_c` are
+    /// 32-byte compressed G1 points and `proof_b` a 64-byte compressed G2
+    /// point (the `alt_bn128` compression format, `proof_a` still negated).
+    /// Points that don't decompress fail with `ProofDecompressionFailed`.
+    pub fn verify_and_store_tier_compressed(
+        ctx: Context<VerifyAndStoreTierCompressed>,
+        proof_a: [u8; 32],
+        proof_b: [u8; 64],
+        proof_c: [u8; 32],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
     ) -> Result<()> {
-        // 1. Verify the Groth16 proof on-chain
-        let mut verifier = Groth16Verifier::new(
+        report_failure(process_verify_compressed(
+            ctx,
             &proof_a,
             &proof_b,
             &proof_c,
             &public_inputs,
-            &VERIFYING_KEY,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+        ))
+    }
+
+    /// First half of a verification split across two transactions, for
+    /// deployments whose checks don't fit in one transaction's compute
+    /// budget next to the pairing check. Prechecks and verifies the proof
+    /// like `verify_and_store_tier`, then stages the verified public inputs
+    /// in the caller's PendingVerification PDA, replacing any staged before.
+    pub fn verify_proof_stage1(
+        ctx: Context<VerifyProofStage1>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        layout_version: u8,
+    ) -> Result<()> {
+        report_failure(process_verify_stage1(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            layout_version,
+        ))
+    }
+
+    /// Second half of a split verification: runs the remaining checks on
+    /// the proof staged by `verify_proof_stage1` and stores the badge as
+    /// `verify_and_store_tier` would, closing the pending record. Records
+    /// older than `PENDING_VERIFICATION_SLOTS` fail with
+    /// `StaleVerification`.
+    pub fn verify_proof_stage2(
+        ctx: Context<VerifyProofStage2>,
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+    ) -> Result<()> {
+        report_failure(process_verify_stage2(
+            ctx,
+            client_request_id,
+            account_type,
+            namespace,
+        ))
+    }
+
+    /// Verify a proof like `verify_and_store_tier`, but only to create the
+    /// caller's first badge; an existing badge fails with
+    /// `BadgeAlreadyExists`. Deployments with `require_explicit_create` set
+    /// must use this for first verifications.
+    pub fn create_badge(
+        ctx: Context<VerifyAndStoreTier>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+    ) -> Result<()> {
+        report_failure(
+            decode_layout(layout_version, &public_inputs).and_then(|public_inputs| {
+                verify_and_store(
+                    ctx.accounts,
+                    &ctx.bumps,
+                    &proof_a,
+                    &proof_b,
+                    &proof_c,
+                    &public_inputs,
+                    client_request_id,
+                    account_type,
+                    namespace,
+                    true,
+                )
+            }),
         )
-        .map_err(|_| ProofOfLoveError::ProofVerificationFailed)?;
+    }
 
-        verifier
-            .verify()
-            .map_err(|_| ProofOfLoveError::ProofVerificationFailed)?;
-
-        // 2. Decode public signals
-        let tier_lower = u64::from_be_bytes(public_inputs[0][24..32].try_into().unwrap());
-        let tier_upper = u64::from_be_bytes(public_inputs[1][24..32].try_into().unwrap());
-        let nullifier = public_inputs[2];
-        let timestamp = i64::from_be_bytes(public_inputs[3][24..32].try_into().unwrap());
-
-        // 3. Validate tier bounds match a known tier
-        let tier = match (tier_lower, tier_upper) {
-            (0, 100_000) => 1,                     // Seed: < $1K
-            (100_000, 1_000_000) => 2,              // Sprout: $1K - $10K
-            (1_000_000, 5_000_000) => 3,            // Tree: $10K - $50K
-            (5_000_000, 25_000_000) => 4,           // Mountain: $50K - $250K
-            (25_000_000, 100_000_000) => 5,         // Ocean: $250K - $1M
-            (100_000_000, 500_000_000) => 6,        // Moon: $1M - $5M
-            (500_000_000, 10_000_000_000_000) => 7, // Sun: $5M+
-            _ => return Err(ProofOfLoveError::InvalidTier.into()),
-        };
+    /// Verify a proof exactly like `verify_and_store_tier`, then mint one
+    /// base unit of `mint` to the caller's token account.
+    ///
+    /// The mint represents a tier: its mint authority must be the
+    /// `[b"tier_mint_authority", tier]` PDA for the tier just verified. A
+    /// MintReceipt tied to the identity's nullifier and the mint makes a
+    /// second mint fail, from any namespace or a recreated badge.
+    pub fn verify_and_mint(
+        ctx: Context<VerifyAndMint>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+    ) -> Result<()> {
+        report_failure(process_verify_and_mint(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+        ))
+    }
+
+    /// Register a tier-gated staking pool. The signing `authority` must own
+    /// `vault`, and `min_tier` is the lowest tier `verify_and_stake` accepts
+    /// into it.
+    pub fn create_stake_pool(ctx: Context<CreateStakePool>, min_tier: u8) -> Result<()> {
+        Tier::try_from(min_tier)?;
+
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.vault = ctx.accounts.vault.key();
+        pool.min_tier = min_tier;
+        pool.bump = ctx.bumps.stake_pool;
+
+        msg!(
+            "Proof of Love: vault {} gated at Tier {}",
+            pool.vault,
+            min_tier
+        );
+
+        Ok(())
+    }
+
+    /// Verify a proof exactly like `verify_and_store_tier`, then transfer
+    /// `amount` tokens from the caller's `source` account into the
+    /// `stake_pool`'s vault and add them to the StakeReceipt for this badge,
+    /// vault and the pool's `min_tier`.
+    ///
+    /// Fails with `TierBelowPoolMinimum` if the verified tier is below the
+    /// pool's `min_tier`. Either failure rolls back both the badge and the
+    /// stake.
+    pub fn verify_and_stake(
+        ctx: Context<VerifyAndStake>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+        amount: u64,
+    ) -> Result<()> {
+        report_failure(process_verify_and_stake(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+            amount,
+        ))
+    }
+
+    /// Verify a proof exactly like `verify_and_store_tier`, then record
+    /// `referrer` as the caller's referrer in a one-time Referral PDA.
+    /// `referrer_namespace` picks which of the referrer's badges vouches
+    /// for them, the default one when `None`.
+    ///
+    /// Self-referrals fail with `SelfReferral`, and a referrer without an
+    /// unexpired badge in that namespace fails with `ReferrerWithoutBadge`.
+    pub fn verify_with_referral(
+        ctx: Context<VerifyWithReferral>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+        referrer: Pubkey,
+        referrer_namespace: Option<[u8; 8]>,
+    ) -> Result<()> {
+        report_failure(process_verify_with_referral(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+            referrer,
+            referrer_namespace,
+        ))
+    }
+
+    /// Register a gated DAO. The signing `dao` key (a wallet, or a PDA of
+    /// the DAO's program signing by CPI) owns the gate, and `min_tier` is
+    /// the lowest tier `verify_and_join` admits.
+    pub fn create_dao_gate(ctx: Context<CreateDaoGate>, min_tier: u8) -> Result<()> {
+        Tier::try_from(min_tier)?;
+
+        let gate = &mut ctx.accounts.dao_gate;
+        gate.dao = ctx.accounts.dao.key();
+        gate.min_tier = min_tier;
+        gate.bump = ctx.bumps.dao_gate;
+
+        msg!("Proof of Love: DAO {} gated at Tier {}", gate.dao, min_tier);
+
+        Ok(())
+    }
+
+    /// Verify a proof exactly like `verify_and_store_tier`, then admit the
+    /// caller to `dao_gate`'s DAO by recording a Membership PDA.
+    ///
+    /// Fails with `TierBelowDaoMinimum` if the verified tier is below the
+    /// gate's `min_tier`, and with `AlreadyMember` if the caller already
+    /// joined. Either failure rolls back the badge write too.
+    pub fn verify_and_join(
+        ctx: Context<VerifyAndJoin>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+    ) -> Result<()> {
+        report_failure(process_verify_and_join(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+        ))
+    }
+
+    /// Verify a proof exactly like `verify_and_store_tier`, then set the
+    /// identity's VotingPower to `config.tier_weights` for the verified
+    /// tier. A refresh through this instruction recomputes the weight, so
+    /// it follows tier changes and table updates. The VotingPower is keyed
+    /// by nullifier, so verifying in another namespace moves the one weight
+    /// to that badge rather than adding a second.
+    pub fn verify_and_set_voting_power(
+        ctx: Context<VerifyAndSetVotingPower>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+    ) -> Result<()> {
+        report_failure(process_verify_and_set_voting_power(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+        ))
+    }
+
+    /// Verify a proof like `verify_and_store_tier` into `owner`'s badge,
+    /// submitted and paid for by a custodian. `owner` doesn't sign the
+    /// transaction; instead the instruction right before this one must be
+    /// a precompile check of `attestation::delegation_message` for this
+    /// custodian, namespace and proof, or the call fails with
+    /// `DelegationSignatureInvalid`. With `sig_scheme` Ed25519 that is an
+    /// Ed25519 signature by `owner`; with Secp256k1 it is a Secp256k1
+    /// signature by a key whose `attestation::secp256k1_owner` is `owner`.
+    /// Other schemes fail with `UnsupportedSigScheme`. The badge and
+    /// nullifier still belong to `owner`, and the badge records the scheme.
+    /// Any rent subsidy or first-verification reward is paid to `owner`.
+    /// Delegated badges are never prover-attested.
+    pub fn verify_delegated(
+        ctx: Context<VerifyDelegated>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+        client_request_id: Option<[u8; 16]>,
+        account_type: AccountType,
+        namespace: Option<[u8; 8]>,
+        layout_version: u8,
+        sig_scheme: u8,
+    ) -> Result<()> {
+        report_failure(process_verify_delegated(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            client_request_id,
+            account_type,
+            namespace,
+            layout_version,
+            sig_scheme,
+        ))
+    }
+
+    /// Verify a proof of an arbitrary wealth range and store the raw
+    /// bounds in the caller's RangeBadge PDA, for consumers that need finer
+    /// gating than the tier table. The bounds are not matched against the
+    /// tiers; they must satisfy `state::range_is_valid` or the call fails
+    /// with `InvalidRange`.
+    ///
+    /// The proof's nullifier is claimed in the same registry as tier
+    /// proofs, so a wallet's range and tier badges share one nullifier.
+    pub fn verify_range(
+        ctx: Context<VerifyRange>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    ) -> Result<()> {
+        report_failure(process_verify_range(
+            ctx,
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+        ))
+    }
 
-        // 4. Validate proof freshness
+    /// Revoke an expired tier badge, reclaiming the rent. Returns a
+    /// `RevocationReport` (via return data) with the lamports reclaimed.
+    /// Zeroes the identity's VotingPower if this badge set it, releases the
+    /// badge from its NullifierRecord, and drops it from the ExpiryBucket
+    /// listing it, which must then be passed.
+    pub fn revoke_expired_tier(ctx: Context<RevokeExpiredTier>) -> Result<RevocationReport> {
+        // `expires_at` is deserialized from the live account when this
+        // instruction executes, so a refresh that lands first has already
+        // pushed it out and the strict comparison rejects the revoke.
         let clock = Clock::get()?;
-        let now = clock.unix_timestamp;
         require!(
-            now - timestamp <= MAX_PROOF_AGE_SECONDS,
-            ProofOfLoveError::ProofTooOld
+            clock.unix_timestamp > ctx.accounts.tier_badge.expires_at,
+            ProofOfLoveError::BadgeNotExpired
+        );
+
+        let badge_key = ctx.accounts.tier_badge.key();
+        unlist_expiry(
+            &ctx.accounts.tier_badge,
+            &badge_key,
+            ctx.accounts
+                .expiry_bucket
+                .as_deref_mut()
+                .map(|bucket| &mut **bucket),
+        )?;
+        update_voting_power(&ctx.accounts.voting_power, &badge_key, VotingPower::zero)?;
+        release_nullifier(&ctx.accounts.nullifier_record, &ctx.accounts.tier_badge)?;
+
+        emit!(BadgeClosed {
+            tier_badge: ctx.accounts.tier_badge.key(),
+            owner: ctx.accounts.tier_badge.owner,
+        });
+
+        msg!(
+            "Proof of Love: Tier badge revoked for {}",
+            ctx.accounts.tier_badge.owner
         );
 
-        // 5. Write the TierBadge PDA
+        // `close = user` moves the badge's whole balance once this returns
+        Ok(RevocationReport {
+            lamports_reclaimed: ctx.accounts.tier_badge.to_account_info().lamports(),
+            badges_closed: 1,
+        })
+    }
+
+    /// Lower the caller's badge to a lower tier from the table, e.g. to
+    /// present less in some context. Proving wealth above a tier implies
+    /// wealth above every lower one, so no new proof is needed. Expiry,
+    /// nullifier and attestation are kept, so any ExpiryBucket listing
+    /// still holds, and the identity's VotingPower is recomputed if this
+    /// badge set it. Emits `TierDowngraded`.
+    pub fn downgrade_badge(ctx: Context<DowngradeBadge>, tier: u8) -> Result<()> {
         let badge = &mut ctx.accounts.tier_badge;
-        badge.owner = ctx.accounts.user.key();
+        require!(
+            tier >= 1 && tier < badge.tier,
+            ProofOfLoveError::InvalidDowngrade
+        );
+
+        let previous_tier = badge.tier;
+        let bounds = ctx.accounts.config.tiers[(tier - 1) as usize];
         badge.tier = tier;
-        badge.tier_lower_bound = tier_lower;
-        badge.tier_upper_bound = tier_upper;
-        badge.nullifier = nullifier;
-        badge.verified_at = timestamp;
-        badge.expires_at = timestamp + BADGE_VALIDITY_SECONDS;
-        badge.bump = ctx.bumps.tier_badge;
+        badge.tier_lower_bound = bounds.lower;
+        badge.tier_upper_bound = bounds.upper;
+        badge.currency_code = ctx.accounts.config.currency_code;
+
+        let weight = ctx.accounts.config.tier_weight(tier);
+        update_voting_power(&ctx.accounts.voting_power, &badge.key(), |power| {
+            power.tier = tier;
+            power.weight = weight;
+        })?;
+
+        emit!(TierDowngraded {
+            tier_badge: badge.key(),
+            owner: badge.owner,
+            from: previous_tier,
+            to: tier,
+        });
 
         msg!(
-            "Proof of Love: {} verified as Tier {} (bounds: {} - {})",
-            ctx.accounts.user.key(),
+            "Proof of Love: {} downgraded to Tier {} (bounds: {} - {})",
+            badge.owner,
             tier,
-            tier_lower,
-            tier_upper
+            bounds.lower,
+            bounds.upper
         );
 
         Ok(())
     }
 
-    /// Revoke an expired tier badge, reclaiming the rent.
-    pub fn revoke_expired_tier(ctx: Context<RevokeExpiredTier>) -> Result<()> {
-        let clock = Clock::get()?;
+    /// Close the caller's badge for good, leaving a BadgeTombstone for its
+    /// identity that records the owner, tier and burn time. Unlike a revoke,
+    /// the tombstone is never closed, and with `tombstone_blocks_recreate`
+    /// set it keeps the identity from creating a badge again, under any
+    /// wallet or namespace. Only the identity's first burn is recorded;
+    /// burning its other badges leaves the tombstone as it is. The badge
+    /// rent goes back to the owner; the tombstone's is spent. Zeroes the identity's
+    /// VotingPower if this badge set it, releases the badge from its
+    /// NullifierRecord, and drops it from the ExpiryBucket listing it, which
+    /// must then be passed.
+    pub fn burn_badge(ctx: Context<BurnBadge>) -> Result<()> {
+        update_voting_power(
+            &ctx.accounts.voting_power,
+            &ctx.accounts.tier_badge.key(),
+            VotingPower::zero,
+        )?;
+        release_nullifier(&ctx.accounts.nullifier_record, &ctx.accounts.tier_badge)?;
+        unlist_expiry(
+            &ctx.accounts.tier_badge,
+            &ctx.accounts.tier_badge.key(),
+            ctx.accounts
+                .expiry_bucket
+                .as_deref_mut()
+                .map(|bucket| &mut **bucket),
+        )?;
+
+        let badge = &ctx.accounts.tier_badge;
+        let tombstone = &mut ctx.accounts.badge_tombstone;
+        if tombstone.burned_at == 0 {
+            tombstone.owner = badge.owner;
+            tombstone.tier = badge.tier;
+            tombstone.burned_at = Clock::get()?.unix_timestamp;
+            tombstone.bump = ctx.bumps.badge_tombstone;
+        }
+
+        emit!(BadgeClosed {
+            tier_badge: badge.key(),
+            owner: badge.owner,
+        });
+
+        msg!(
+            "Proof of Love: Tier {} badge burned for {}",
+            badge.tier,
+            badge.owner
+        );
+
+        Ok(())
+    }
+
+    /// Set the display label wallets show next to the caller's badge, or
+    /// clear it with an empty label. See `state::encode_label` for what a
+    /// label may contain. Labels are chosen by the owner and unverified.
+    pub fn set_label(ctx: Context<SetLabel>, label: Vec<u8>) -> Result<()> {
+        let badge = &mut ctx.accounts.tier_badge;
+        badge.label = encode_label(&label).ok_or(ProofOfLoveError::InvalidLabel)?;
+
+        emit!(BadgeLabelSet {
+            tier_badge: badge.key(),
+            owner: badge.owner,
+            label: badge.label,
+        });
+
+        Ok(())
+    }
+
+    /// Extend the caller's unexpired badge by a coupon's
+    /// `extension_seconds`, using up one of its uses. The new expiry may be
+    /// at most `max_validity_seconds` away, or the call fails with
+    /// `ValidityExceedsCap`. Moves the badge between ExpiryBuckets like a
+    /// refresh does.
+    pub fn redeem_coupon(ctx: Context<RedeemCoupon>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let badge = &mut ctx.accounts.tier_badge;
+        require!(now <= badge.expires_at, ProofOfLoveError::BadgeExpired);
+
+        let previous_expires_at = badge.expires_at;
+        let expires_at = ctx.accounts.coupon.redeem(badge.expires_at, now)?;
         require!(
-            clock.unix_timestamp > ctx.accounts.tier_badge.expires_at,
-            ProofOfLoveError::BadgeNotExpired
+            expires_at.saturating_sub(now) <= ctx.accounts.config.max_validity_seconds,
+            ProofOfLoveError::ValidityExceedsCap
         );
+        badge.expires_at = expires_at;
+        relist_expiry(
+            badge,
+            Some(previous_expires_at),
+            ctx.accounts
+                .previous_expiry_bucket
+                .as_deref_mut()
+                .map(|bucket| &mut **bucket),
+            ctx.accounts
+                .expiry_bucket
+                .as_deref_mut()
+                .map(|bucket| &mut **bucket),
+        )?;
+
+        emit!(CouponRedeemed {
+            coupon: ctx.accounts.coupon.key(),
+            tier_badge: badge.key(),
+            owner: badge.owner,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Create the global Config PDA with the launch parameters.
+    ///
+    /// Only the program's upgrade authority may initialize, and it becomes
+    /// the config authority.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, timelock_slots: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.authority.key();
+        config.paused = false;
+        config.badge_validity_seconds = DEFAULT_BADGE_VALIDITY_SECONDS;
+        config.max_proof_age_seconds = DEFAULT_MAX_PROOF_AGE_SECONDS;
+        config.tiers = DEFAULT_TIERS;
+        config.timelock_slots = timelock_slots;
+        config.min_wallet_age_slots = 0;
+        config.vk_rotation_slot = 0;
+        config.post_rotation_cooldown_slots = 0;
+        config.tier_table_frozen = false;
+        config.prover_key = Pubkey::default();
+        config.max_validity_seconds = DEFAULT_MAX_VALIDITY_SECONDS;
+        config.require_explicit_create = false;
+        config.pauser = Pubkey::default();
+        config.allowed_account_types = AccountType::Individual.mask();
+        config.hook_program = Pubkey::default();
+        config.probation_seconds = 0;
+        config.restrict_bundles = false;
+        config.denied_programs = [Pubkey::default(); MAX_DENIED_PROGRAMS];
+        config.min_wallet_lamports = 0;
+        config.subsidy_min_tier = 0;
+        config.subsidy_epoch_cap_lamports = 0;
+        config.tombstone_blocks_recreate = false;
+        config.require_origin_relayer = false;
+        config.max_refreshes_per_window = 0;
+        config.refresh_window_seconds = DEFAULT_REFRESH_WINDOW_SECONDS;
+        config.anchor_expiry_to_submission = false;
+        config.denied_nullifier_prefixes = Default::default();
+        config.currency_code = DEFAULT_CURRENCY_CODE;
+        config.emit_tier_memo = false;
+        config.first_verify_reward_lamports = 0;
+        config.vk_windows = DEFAULT_VK_WINDOWS;
+        config.withdrawal_recipient = Pubkey::default();
+        config.max_accepted_tier = state::TIER_COUNT as u8;
+        config.verification_epoch = 1;
+        config.strict_epoch = false;
+        config.sane_bounds_max = DEFAULT_SANE_BOUNDS_MAX;
+        config.log_verifications = false;
+        config.tier_weights = DEFAULT_TIER_WEIGHTS;
+        config.require_nullifier_uniqueness = true;
+        config.pending_change = None;
+        config.bump = ctx.bumps.config;
 
         msg!(
-            "Proof of Love: Tier badge revoked for {}",
-            ctx.accounts.tier_badge.owner
+            "Proof of Love: config initialized (authority: {}, timelock: {} slots)",
+            config.authority,
+            timelock_slots
         );
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct VerifyAndStoreTier<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// Change config parameters.
+    ///
+    /// Safety-increasing changes apply immediately; sensitive ones are
+    /// queued as the pending change (replacing any earlier one) and must be
+    /// finalized with `apply_config` after `timelock_slots`.
+    pub fn update_config(ctx: Context<UpdateConfig>, update: ConfigUpdate) -> Result<()> {
+        require!(update.is_valid(), ProofOfLoveError::InvalidConfig);
 
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + TierBadge::INIT_SPACE,
-        seeds = [b"tier_badge", user.key().as_ref()],
-        bump,
-    )]
-    pub tier_badge: Account<'info, TierBadge>,
+        let config = &mut ctx.accounts.config;
+        require!(
+            !(config.tier_table_frozen && update.tiers.is_some()),
+            ProofOfLoveError::TierTableFrozen
+        );
+        require!(
+            config.validity_within_cap(&update),
+            ProofOfLoveError::ValidityExceedsCap
+        );
 
-    pub system_program: Program<'info, System>,
-}
+        let deferred = config.stage_update(&update);
 
-#[derive(Accounts)]
-pub struct RevokeExpiredTier<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+        if !deferred.is_empty() {
+            let effective_slot = Clock::get()?.slot.saturating_add(config.timelock_slots);
+            msg!(
+                "Proof of Love: config change queued until slot {}",
+                effective_slot
+            );
+            config.pending_change = Some(PendingConfigChange {
+                update: deferred,
+                effective_slot,
+            });
+        }
 
-    #[account(
-        mut,
-        close = user,
-        seeds = [b"tier_badge", tier_badge.owner.as_ref()],
-        bump = tier_badge.bump,
-        constraint = tier_badge.owner == user.key(),
-    )]
-    pub tier_badge: Account<'info, TierBadge>,
-}
\ No newline at end of file
+        Ok(())
+    }
+
+    /// Finalize the pending config change once its timelock has elapsed.
+    pub fn apply_config(ctx: Context<UpdateConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let pending = config
+            .pending_change
+            .take()
+            .ok_or(ProofOfLoveError::NoPendingChange)?;
+        require!(
+            Clock::get()?.slot >= pending.effective_slot,
+            ProofOfLoveError::TimelockNotElapsed
+        );
+        require!(
+            !(config.tier_table_frozen && pending.update.tiers.is_some()),
+            ProofOfLoveError::TierTableFrozen
+        );
+        // The cap may have been lowered since the change was queued
+        require!(
+            config.validity_within_cap(&pending.update),
+            ProofOfLoveError::ValidityExceedsCap
+        );
+
+        config.apply_change(&pending.update);
+        msg!("Proof of Love: pending config change applied");
+
+        Ok(())
+    }
+
+    /// Permanently freeze the tier table. There is no way to unfreeze, so
+    /// a deployment can make a binding commitment never to re-tier.
+    pub fn freeze_tier_table(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.config.tier_table_frozen = true;
+        msg!("Proof of Love: tier table frozen");
+
+        Ok(())
+    }
+
+    /// Pause or unpause verification. Only the config's `pauser` may call
+    /// this, so an on-call key can stop the program without holding the
+    /// authority.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+        msg!("Proof of Love: paused set to {}", paused);
+
+        Ok(())
+    }
+
+    /// Fail with `BadgeInProbation` unless `tier_badge` is past its
+    /// probation window. Consumers can CPI into this, or place it in the
+    /// same transaction, to gate on fully trusted badges only.
+    ///
+    /// Like the other gating instructions, it takes an optional
+    /// `nullifier_record`; when passed, the badge and the record must agree
+    /// on the nullifier and owner, or the call fails with `NullifierDesync`.
+    pub fn require_trusted_badge(ctx: Context<RequireTrustedBadge>) -> Result<()> {
+        require_in_sync(&ctx.accounts.tier_badge, &ctx.accounts.nullifier_record)?;
+        ctx.accounts
+            .tier_badge
+            .require_trusted(Clock::get()?.unix_timestamp)
+    }
+
+    /// Fail with `ProgramVersionTooOld` unless `tier_badge` was created by
+    /// release `min_version` or later, for consumers that only trust
+    /// badges from audited releases.
+    pub fn require_badge_version(
+        ctx: Context<RequireTrustedBadge>,
+        min_version: u16,
+    ) -> Result<()> {
+        require_in_sync(&ctx.accounts.tier_badge, &ctx.accounts.nullifier_record)?;
+        ctx.accounts.tier_badge.require_program_version(min_version)
+    }
+
+    /// Fail with `NullifierNotEnforced` unless `tier_badge`'s nullifier was
+    /// registered in the nullifier registry, either at verification or by
+    /// `backfill_nullifier`, for consumers that don't accept badges whose
+    /// uniqueness was never checked.
+    pub fn require_nullifier_enforced(ctx: Context<RequireTrustedBadge>) -> Result<()> {
+        require_in_sync(&ctx.accounts.tier_badge, &ctx.accounts.nullifier_record)?;
+        ctx.accounts.tier_badge.require_nullifier_enforced()
+    }
+
+    /// Fail with `BadgeEpochExpired` if the config is in strict epoch mode
+    /// and `tier_badge` was last verified before the current
+    /// `verification_epoch`, for consumers that honor re-verification
+    /// campaigns.
+    pub fn require_current_epoch(ctx: Context<RequireCurrentEpoch>) -> Result<()> {
+        require_in_sync(&ctx.accounts.tier_badge, &ctx.accounts.nullifier_record)?;
+        ctx.accounts
+            .tier_badge
+            .require_current_epoch(&ctx.accounts.config)
+    }
+
+    /// Check `tier_badge` against a composite gate in one call: at least
+    /// `min_tier` (by its high-water mark with `use_high_water`),
+    /// unexpired, past probation when `require_trusted`, and valid for at
+    /// least `min_remaining_seconds` more. Returns a
+    /// `GateResult` (via return data) whose `reason` names the first
+    /// failed condition, so consumers can tell users exactly why.
+    ///
+    /// No new badge stays valid longer than `max_validity_seconds`, so a
+    /// larger `min_remaining_seconds` is a caller bug and fails with
+    /// `HorizonExceedsMaxValidity` instead of failing every badge. A badge
+    /// verified before the cap was lowered may still run longer, so the
+    /// horizon is only an error past both the cap and the badge's own
+    /// validity window.
+    pub fn gate_check(
+        ctx: Context<GateCheck>,
+        min_tier: u8,
+        use_high_water: bool,
+        require_trusted: bool,
+        min_remaining_seconds: i64,
+    ) -> Result<GateResult> {
+        let badge = &ctx.accounts.tier_badge;
+        let window = badge.expires_at.saturating_sub(badge.verified_at);
+        require!(
+            min_remaining_seconds <= ctx.accounts.config.max_validity_seconds.max(window),
+            ProofOfLoveError::HorizonExceedsMaxValidity
+        );
+        require_in_sync(&ctx.accounts.tier_badge, &ctx.accounts.nullifier_record)?;
+        Ok(ctx.accounts.tier_badge.gate(
+            Clock::get()?.unix_timestamp,
+            min_tier,
+            use_high_water,
+            require_trusted,
+            min_remaining_seconds,
+        ))
+    }
+
+    /// Return `commitment::badge_commitment` of `tier_badge` (via return
+    /// data): a Poseidon hash of its owner, tier, bounds, nullifier and
+    /// expiry as one BN254 field element, so another circuit can take the
+    /// badge as a private input and check it against the commitment.
+    pub fn badge_commitment(ctx: Context<BadgeCommitment>) -> Result<[u8; 32]> {
+        commitment::badge_commitment(&ctx.accounts.tier_badge)
+    }
+
+    /// Return the active config parameters as a `ConfigSnapshot` (via
+    /// return data), for clients that shouldn't depend on the `Config`
+    /// account layout.
+    pub fn get_config(ctx: Context<GetConfig>) -> Result<ConfigSnapshot> {
+        Ok(ctx.accounts.config.snapshot())
+    }
+
+    /// Return every tier's number, display name and bounds from the active
+    /// tier table (via return data), so clients needn't hardcode the ladder.
+    pub fn list_tiers(ctx: Context<GetConfig>) -> Result<Vec<TierDefinition>> {
+        Ok(ctx.accounts.config.tier_definitions())
+    }
+
+    /// Return `health::tier_table_hash` of the active tier table (via return
+    /// data), so clients can check their embedded table against it before
+    /// generating a proof that would fail with `InvalidTier`.
+    pub fn get_tier_table_hash(ctx: Context<GetConfig>) -> Result<[u8; 32]> {
+        Ok(health::tier_table_hash(&ctx.accounts.config.tiers))
+    }
+
+    /// Check that the deployment is self-consistent: tier table contiguous,
+    /// validity within its cap, the compiled verifying key matching
+    /// `expected_vk_hash` (see `health::vk_hash`), and not paused. Returns a
+    /// bitmask of the `health` checks that passed (via return data).
+    pub fn health_check(ctx: Context<GetConfig>, expected_vk_hash: [u8; 32]) -> Result<u8> {
+        Ok(health::health_bits(
+            &ctx.accounts.config,
+            &VERIFYING_KEY,
+            &expected_vk_hash,
+        ))
+    }
+
+    /// Return the compiled verifying key's fingerprint (via return data):
+    /// the first 8 bytes of `health::vk_hash`, for comparing a deployment
+    /// against a published value at a glance. Use `health_check` to compare
+    /// the full hash.
+    pub fn get_vk_fingerprint(_ctx: Context<GetConfig>) -> Result<[u8; 8]> {
+        Ok(health::vk_fingerprint(&VERIFYING_KEY))
+    }
+
+    /// Verify a proof against `verifying_key` (compressed, see
+    /// `test_vk::OwnedVerifyingKey::decode`) instead of the pinned key, and
+    /// store nothing. For testing circuit iterations locally; only compiled
+    /// with the `test-vk` feature, never in a deployed program.
+    #[cfg(feature = "test-vk")]
+    pub fn verify_with_test_key(
+        _ctx: Context<VerifyWithTestKey>,
+        proof_a: [u8; 64],
+        proof_b: [u8; 128],
+        proof_c: [u8; 64],
+        public_inputs: [[u8; 32]; 4],
+        verifying_key: Vec<u8>,
+    ) -> Result<()> {
+        let key = test_vk::OwnedVerifyingKey::decode(&verifying_key)
+            .ok_or(ProofOfLoveError::InvalidVerifyingKey)?;
+        Groth16Verifier::new(
+            &proof_a,
+            &proof_b,
+            &proof_c,
+            &public_inputs,
+            &key.as_groth16(),
+        )
+        .map_err(|_| ProofOfLoveError::ProofVerificationFailed)?
+        .verify()
+        .map_err(|_| ProofOfLoveError::ProofVerificationFailed)?;
+
+        msg!("Proof of Love: Proof verified against a test key");
+        Ok(())
+    }
+
+    /// Dry-run tier classification: return the tier that `lower`..`upper`
+    /// would map to under the active tier table (via return data), without
+    /// a proof. Fails with `InvalidTier` when the bounds match no tier.
+    pub fn classify_bounds(ctx: Context<GetConfig>, lower: u64, upper: u64) -> Result<Tier> {
+        let tier = ctx
+            .accounts
+            .config
+            .classify_tier(lower, upper)
+            .ok_or(ProofOfLoveError::InvalidTier)?;
+        Ok(tier)
+    }
+
+    /// Record that the verifying key was just rotated (by upgrading the
+    /// program), starting the `post_rotation_cooldown_slots` window during
+    /// which proofs are rejected.
+    pub fn record_vk_rotation(ctx: Context<UpdateConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.vk_rotation_slot = Clock::get()?.slot;
+
+        msg!(
+            "Proof of Love: VK rotation recorded at slot {}, proofs accepted from slot {}",
+            config.vk_rotation_slot,
+            config
+                .vk_rotation_slot
+                .saturating_add(config.post_rotation_cooldown_slots)
+        );
+
+        Ok(())
+    }
+
+    /// Close NullifierRecords no open badge is registered against, in any
+    /// namespace or as a RangeBadge, returning the rent to the wallet that
+    /// paid for each record. Sweeping a record frees its nullifier for
+    /// reuse.
+    ///
+    /// `remaining_accounts` holds `(nullifier_record, payer)` pairs, where
+    /// `payer` is the wallet the record names as having paid for it.
+    /// Records that still back a badge are skipped.
+    pub fn sweep_orphaned_nullifiers<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepOrphanedNullifiers<'info>>,
+    ) -> Result<()> {
+        let pairs = ctx.remaining_accounts.chunks_exact(2);
+        require!(
+            pairs.remainder().is_empty(),
+            ProofOfLoveError::InvalidSweepAccounts
+        );
+
+        let mut swept = 0u32;
+        for pair in pairs {
+            let (record_info, payer_info) = (&pair[0], &pair[1]);
+            let record = Account::<NullifierRecord>::try_from(record_info)?;
+            require!(
+                payer_info.key() == record.payer,
+                ProofOfLoveError::InvalidSweepAccounts
+            );
+            if record.badges > 0 {
+                continue;
+            }
+
+            record.close(payer_info.clone())?;
+            swept += 1;
+        }
+
+        msg!("Proof of Love: swept {} orphaned nullifier records", swept);
+
+        Ok(())
+    }
+
+    /// Create the caller's WalletMarker, starting the clock for the
+    /// `min_wallet_age_slots` gate.
+    pub fn create_wallet_marker(ctx: Context<CreateWalletMarker>) -> Result<()> {
+        let marker = &mut ctx.accounts.wallet_marker;
+        marker.owner = ctx.accounts.user.key();
+        marker.created_slot = Clock::get()?.slot;
+        marker.bump = ctx.bumps.wallet_marker;
+
+        msg!(
+            "Proof of Love: wallet marker created for {} at slot {}",
+            marker.owner,
+            marker.created_slot
+        );
+
+        Ok(())
+    }
+
+    /// Create the ExpiryBucket for `day` (days since the Unix epoch), so
+    /// verifications of badges expiring that day can be listed in it.
+    pub fn create_expiry_bucket(ctx: Context<CreateExpiryBucket>, day: i64) -> Result<()> {
+        let bucket = &mut ctx.accounts.expiry_bucket;
+        bucket.day = day;
+        bucket.bump = ctx.bumps.expiry_bucket;
+        Ok(())
+    }
+
+    /// Create the signer's VerificationLog, so that while
+    /// `config.log_verifications` is set, verifications that pass it append
+    /// their history to it.
+    pub fn create_verification_log(ctx: Context<CreateVerificationLog>) -> Result<()> {
+        let log = &mut ctx.accounts.verification_log;
+        log.owner = ctx.accounts.owner.key();
+        log.bump = ctx.bumps.verification_log;
+        Ok(())
+    }
+
+    /// Return up to `EXPIRY_BUCKET_PAGE_SIZE` badges from an ExpiryBucket,
+    /// starting at entry `start` (via return data, which can't hold a full
+    /// bucket).
+    pub fn list_expiring_badges(
+        ctx: Context<ListExpiringBadges>,
+        start: u32,
+    ) -> Result<Vec<Pubkey>> {
+        Ok(ctx
+            .accounts
+            .expiry_bucket
+            .badges
+            .iter()
+            .skip(start as usize)
+            .take(EXPIRY_BUCKET_PAGE_SIZE)
+            .copied()
+            .collect())
+    }
+
+    /// Deposit `amount` lamports from the authority into the Treasury PDA,
+    /// creating it on first use. The treasury pays badge rent for tiers at
+    /// or above `subsidy_min_tier`, up to `subsidy_epoch_cap_lamports` per
+    /// epoch.
+    pub fn fund_treasury(ctx: Context<FundTreasury>, amount: u64) -> Result<()> {
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        msg!("Proof of Love: treasury funded with {} lamports", amount);
+
+        Ok(())
+    }
+
+    /// Deposit `amount` lamports from the authority into the RewardVault
+    /// PDA, creating it on first use. The vault pays each identity
+    /// `first_verify_reward_lamports` once, on its first verification.
+    pub fn fund_reward_vault(ctx: Context<FundRewardVault>, amount: u64) -> Result<()> {
+        ctx.accounts.reward_vault.bump = ctx.bumps.reward_vault;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.reward_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        msg!(
+            "Proof of Love: reward vault funded with {} lamports",
+            amount
+        );
+
+        Ok(())
+    }
+
+    /// Issue a coupon that extends badges by `extension_seconds` up to
+    /// `max_uses` times, until `expires_at`. The extension must be positive
+    /// and the coupon usable at least once, or the call fails with
+    /// `InvalidConfig`.
+    pub fn create_coupon(
+        ctx: Context<CreateCoupon>,
+        id: [u8; 8],
+        extension_seconds: i64,
+        max_uses: u32,
+        expires_at: i64,
+    ) -> Result<()> {
+        require!(
+            extension_seconds > 0 && max_uses > 0,
+            ProofOfLoveError::InvalidConfig
+        );
+
+        let coupon = &mut ctx.accounts.coupon;
+        coupon.id = id;
+        coupon.extension_seconds = extension_seconds;
+        coupon.remaining_uses = max_uses;
+        coupon.expires_at = expires_at;
+        coupon.bump = ctx.bumps.coupon;
+
+        Ok(())
+    }
+
+    /// Move `amount` lamports out of the Treasury or the RewardVault,
+    /// whichever is passed, to the authority or the configured
+    /// `withdrawal_recipient`. Only these two vaults can be drained; badge
+    /// and other user accounts never hold program funds. A vault always
+    /// keeps its rent-exempt reserve, so a withdrawal can't close it, and
+    /// what the config still promises out of it: the rest of this epoch's
+    /// subsidy cap while subsidies are on, and the next first-verification
+    /// reward. Lowering the cap or the reward, which applies at once,
+    /// releases the difference.
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let accounts = ctx.accounts;
+        let config = &accounts.config;
+        let (vault, committed) = match (&accounts.treasury, &accounts.reward_vault) {
+            (Some(treasury), None) => (
+                treasury.to_account_info(),
+                if config.subsidy_min_tier != 0 {
+                    treasury.remaining_cap(Clock::get()?.epoch, config.subsidy_epoch_cap_lamports)
+                } else {
+                    0
+                },
+            ),
+            (None, Some(reward_vault)) => (
+                reward_vault.to_account_info(),
+                config.first_verify_reward_lamports,
+            ),
+            _ => return err!(ProofOfLoveError::InvalidWithdrawalSource),
+        };
+        let reserve = Rent::get()?.minimum_balance(vault.data_len());
+        require!(
+            amount
+                <= vault
+                    .lamports()
+                    .saturating_sub(reserve)
+                    .saturating_sub(committed),
+            ProofOfLoveError::WithdrawalExceedsAvailable
+        );
+        vault.sub_lamports(amount)?;
+        accounts.recipient.add_lamports(amount)?;
+
+        emit!(VaultWithdrawn {
+            vault: vault.key(),
+            recipient: accounts.recipient.key(),
+            amount,
+        });
+
+        msg!(
+            "Proof of Love: {} lamports withdrawn from {} to {}",
+            amount,
+            vault.key(),
+            accounts.recipient.key()
+        );
+
+        Ok(())
+    }
+
+    /// Attest that `owner` legitimately rotated their identity commitment,
+    /// e.g. after a key rotation in an external identity system, so their
+    /// next refresh may move their badge from `old_nullifier` to
+    /// `new_nullifier` without being taken for a sybil. Only the config
+    /// authority can attest, and each old nullifier rotates at most once.
+    pub fn attest_identity_rotation(
+        ctx: Context<AttestIdentityRotation>,
+        old_nullifier: [u8; 32],
+        new_nullifier: [u8; 32],
+        owner: Pubkey,
+    ) -> Result<()> {
+        require!(
+            old_nullifier != new_nullifier && encoding::is_canonical_scalar(&new_nullifier),
+            ProofOfLoveError::InvalidIdentityRotation
+        );
+
+        let rotation = &mut ctx.accounts.identity_rotation;
+        rotation.old_nullifier = old_nullifier;
+        rotation.new_nullifier = new_nullifier;
+        rotation.owner = owner;
+        rotation.attested_at = Clock::get()?.unix_timestamp;
+        rotation.bump = ctx.bumps.identity_rotation;
+
+        msg!("Proof of Love: identity rotation attested for {}", owner);
+
+        Ok(())
+    }
+
+    /// Register the nullifier of a badge created before nullifier
+    /// enforcement existed, so later reuse by another wallet is rejected,
+    /// and mark the badge `nullifier_enforced`. Badges whose nullifier is
+    /// already registered are left untouched, except that the flag is set
+    /// when the registered owner is the badge's own. A badge still in the
+    /// first release's layout must be grown with `migrate_badge` first,
+    /// which can go earlier in the same transaction.
+    pub fn backfill_nullifier(ctx: Context<BackfillNullifier>) -> Result<()> {
+        let badge = &mut ctx.accounts.tier_badge;
+        let record = &mut ctx.accounts.nullifier_record;
+
+        if record.owner != Pubkey::default() {
+            if record.owner == badge.owner && !badge.nullifier_enforced {
+                record.register_badge();
+                badge.nullifier_enforced = true;
+            }
+            msg!(
+                "Proof of Love: nullifier for {} already registered",
+                badge.owner
+            );
+            return Ok(());
+        }
+
+        record.nullifier = badge.nullifier;
+        record.owner = badge.owner;
+        record.bump = ctx.bumps.nullifier_record;
+        record.payer = ctx.accounts.authority.key();
+        record.register_badge();
+        badge.nullifier_enforced = true;
+
+        msg!("Proof of Love: nullifier backfilled for {}", badge.owner);
+
+        Ok(())
+    }
+
+    /// Grow a badge written under an older layout to the current `TierBadge`
+    /// layout, so instructions that load it accept it again. Fields added
+    /// since read as zeros, apart from those `fill_migrated_defaults` sets,
+    /// and `payer` tops up the rent for the larger account. A badge already
+    /// in the current layout is left untouched; anything else fails with
+    /// `UnknownBadgeLayout`.
+    pub fn migrate_badge(ctx: Context<MigrateBadge>) -> Result<()> {
+        let badge_info = ctx.accounts.tier_badge.to_account_info();
+        let current_len = 8 + TierBadge::INIT_SPACE;
+        let len = badge_info.data_len();
+        require!(
+            (LEGACY_TIER_BADGE_LEN..=current_len).contains(&len)
+                && badge_info
+                    .try_borrow_data()?
+                    .starts_with(TierBadge::DISCRIMINATOR),
+            ProofOfLoveError::UnknownBadgeLayout
+        );
+        if len == current_len {
+            return Ok(());
+        }
+
+        let shortfall = Rent::get()?
+            .minimum_balance(current_len)
+            .saturating_sub(badge_info.lamports());
+        if shortfall > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: badge_info.clone(),
+                    },
+                ),
+                shortfall,
+            )?;
+        }
+        badge_info.realloc(current_len, true)?;
+
+        let mut badge = TierBadge::try_deserialize(&mut &badge_info.try_borrow_data()?[..])?;
+        badge.fill_migrated_defaults();
+        badge.try_serialize(&mut &mut badge_info.try_borrow_mut_data()?[..])?;
+
+        msg!(
+            "Proof of Love: badge for {} migrated from {} to {} bytes",
+            badge.owner,
+            len,
+            current_len
+        );
+
+        Ok(())
+    }
+}
+
+/// Cross-check a gated badge against its nullifier's registry record, if
+/// the caller passed one.
+fn require_in_sync(badge: &TierBadge, record: &Option<Account<NullifierRecord>>) -> Result<()> {
+    match record {
+        Some(record) => badge.require_in_sync(record),
+        None => Ok(()),
+    }
+}
+
+/// Failed transactions roll back state but keep their logs, so with
+/// `log-failures` the failure reason is reported as an event for off-chain
+/// monitoring before the error propagates.
+fn report_failure(result: Result<()>) -> Result<()> {
+    #[cfg(feature = "log-failures")]
+    if let Err(err) = &result {
+        emit!(VerificationFailed {
+            reason_code: errors::reason_code(err),
+        });
+    }
+
+    result
+}
+
+/// Public inputs sent in layout `layout_version`, in the order the rest of
+/// the program reads them.
+fn decode_layout(
+    layout_version: u8,
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> Result<[[u8; 32]; NR_PUBLIC_INPUTS]> {
+    encoding::canonical_public_inputs(layout_version, public_inputs)
+        .ok_or_else(|| ProofOfLoveError::UnknownLayoutVersion.into())
+}
+
+// The key ids of `PREVIOUS_VERIFYING_KEYS` must have a `vk_windows` slot
+const _: () = assert!(PREVIOUS_VERIFYING_KEYS.len() < state::MAX_VERIFYING_KEYS);
+
+/// Verify a proof against each compiled verifying key whose
+/// `config.vk_windows` entry covers the proof's timestamp, current key
+/// first, returning the id of the key that accepted it.
+fn verify_groth16(
+    config: &Config,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> Result<u8> {
+    let timestamp = i64::from_be_bytes(public_inputs[3][24..32].try_into().unwrap());
+    let mut active = core::iter::once(&VERIFYING_KEY)
+        .chain(PREVIOUS_VERIFYING_KEYS)
+        .zip(&config.vk_windows)
+        .enumerate()
+        .filter(|(_, (_, window))| window.contains(timestamp))
+        .peekable();
+    require!(
+        active.peek().is_some(),
+        ProofOfLoveError::NoActiveVerifyingKey
+    );
+    active
+        .find(|(_, (vk, _))| {
+            Groth16Verifier::new(proof_a, proof_b, proof_c, public_inputs, vk)
+                .is_ok_and(|mut verifier| verifier.verify().is_ok())
+        })
+        .map(|(id, _)| id as u8)
+        .ok_or_else(|| error!(ProofOfLoveError::ProofVerificationFailed))
+}
+
+/// Checks that need only the config and public inputs, run before a proof
+/// is verified so that doomed submissions fail without paying for it, and
+/// again when a staged verification is stored.
+fn precheck(config: &Config, public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS]) -> Result<()> {
+    require!(!config.paused, ProofOfLoveError::Paused);
+
+    // Proofs landing right after a VK rotation may have been generated
+    // under the old key, so none are accepted until the cooldown ends
+    let clock = Clock::get()?;
+    require!(
+        clock.slot
+            >= config
+                .vk_rotation_slot
+                .saturating_add(config.post_rotation_cooldown_slots),
+        ProofOfLoveError::PostRotationCooldown
+    );
+
+    // 0. All-zero bounds usually mean an uninitialized circuit input. No
+    // proof can satisfy them, so flag them before verification would fail
+    // with a generic error
+    require!(
+        public_inputs[0] != [0u8; 32] || public_inputs[1] != [0u8; 32],
+        ProofOfLoveError::ZeroTierBounds
+    );
+
+    // 0a. Bounds beyond any plausible holding mean a unit mismatch
+    // somewhere, which verification alone wouldn't catch. The top tier has
+    // no real ceiling (see `Config::classify_tier`), so a proof starting at
+    // its lower bound may use any ceiling
+    let top_lower = config.tiers[state::TIER_COUNT - 1].lower;
+    require!(
+        encoding::decode_u64_signal(&public_inputs[1]).is_some_and(|upper| {
+            upper <= config.sane_bounds_max
+                || encoding::decode_u64_signal(&public_inputs[0]) == Some(top_lower)
+        }),
+        ProofOfLoveError::BoundsImplausible
+    );
+
+    // 0b. The registry is keyed by the nullifier's bytes, so only the
+    // canonical encoding of its field element may be claimed
+    require!(
+        encoding::is_canonical_scalar(&public_inputs[2]),
+        ProofOfLoveError::NonCanonicalNullifier
+    );
+
+    // 0c. Nullifier classes denied during an incident never verify
+    require!(
+        !config.nullifier_denied(&public_inputs[2]),
+        ProofOfLoveError::NullifierDenied
+    );
+
+    Ok(())
+}
+
+/// Precheck and verify a proof (steps 0-1), returning the id of the
+/// verifying key that accepted it.
+fn verify_proof(
+    config: &Config,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> Result<u8> {
+    precheck(config, public_inputs)?;
+
+    // 0d. An out-of-field proof_b is almost always a client encoding bug
+    // (e.g. little-endian or unreduced coordinates), so report it as such
+    require!(
+        encoding::proof_b_in_field(proof_b),
+        ProofOfLoveError::ProofMalformed
+    );
+
+    // 1. Verify the Groth16 proof on-chain, under any key whose window
+    // covers the proof's timestamp
+    verify_groth16(config, proof_a, proof_b, proof_c, public_inputs)
+}
+
+/// The accounts `store_verified` writes, borrowed from whichever verifying
+/// instruction's accounts are in use, with the bumps of those it may
+/// create.
+struct BadgeAccounts<'a, 'info> {
+    /// Signs and pays for new accounts: the badge owner, or a custodian
+    /// verifying on their behalf
+    payer: &'a Signer<'info>,
+    /// The wallet the badge belongs to, which collects any rent subsidy
+    /// and first-verification reward
+    owner: AccountInfo<'info>,
+    tier_badge: &'a mut Account<'info, TierBadge>,
+    tier_badge_bump: u8,
+    nullifier_record: Option<&'a mut Box<Account<'info, NullifierRecord>>>,
+    nullifier_record_bump: Option<u8>,
+    wallet_marker: Option<&'a Account<'info, WalletMarker>>,
+    request_receipt: Option<&'a mut Account<'info, RequestReceipt>>,
+    request_receipt_bump: Option<u8>,
+    instructions_sysvar: Option<&'a UncheckedAccount<'info>>,
+    badge_tombstone: Option<&'a UncheckedAccount<'info>>,
+    reward_claim: Option<&'a mut Box<Account<'info, RewardClaim>>>,
+    reward_claim_bump: Option<u8>,
+    shared: &'a mut VerifyShared<'info>,
+}
+
+impl<'info> VerifyAndStoreTier<'info> {
+    fn badge_accounts<'a>(
+        &'a mut self,
+        bumps: &VerifyAndStoreTierBumps,
+    ) -> BadgeAccounts<'a, 'info> {
+        BadgeAccounts {
+            payer: &self.user,
+            owner: self.user.to_account_info(),
+            tier_badge: &mut self.tier_badge,
+            tier_badge_bump: bumps.tier_badge,
+            nullifier_record: self.nullifier_record.as_mut(),
+            nullifier_record_bump: bumps.nullifier_record,
+            wallet_marker: self.wallet_marker.as_ref(),
+            request_receipt: self.request_receipt.as_mut(),
+            request_receipt_bump: bumps.request_receipt,
+            instructions_sysvar: self.instructions_sysvar.as_ref(),
+            badge_tombstone: self.badge_tombstone.as_ref(),
+            reward_claim: self.reward_claim.as_mut(),
+            reward_claim_bump: bumps.reward_claim,
+            shared: &mut self.shared,
+        }
+    }
+}
+
+impl<'info> VerifyAndStoreTierCompressed<'info> {
+    fn badge_accounts<'a>(
+        &'a mut self,
+        bumps: &VerifyAndStoreTierCompressedBumps,
+    ) -> BadgeAccounts<'a, 'info> {
+        BadgeAccounts {
+            payer: &self.user,
+            owner: self.user.to_account_info(),
+            tier_badge: &mut self.tier_badge,
+            tier_badge_bump: bumps.tier_badge,
+            nullifier_record: self.nullifier_record.as_mut(),
+            nullifier_record_bump: bumps.nullifier_record,
+            wallet_marker: self.wallet_marker.as_ref(),
+            request_receipt: self.request_receipt.as_mut(),
+            request_receipt_bump: bumps.request_receipt,
+            instructions_sysvar: self.instructions_sysvar.as_ref(),
+            badge_tombstone: self.badge_tombstone.as_ref(),
+            reward_claim: self.reward_claim.as_mut(),
+            reward_claim_bump: bumps.reward_claim,
+            shared: &mut self.shared,
+        }
+    }
+}
+
+impl<'info> VerifyProofStage2<'info> {
+    fn badge_accounts<'a>(
+        &'a mut self,
+        bumps: &VerifyProofStage2Bumps,
+    ) -> BadgeAccounts<'a, 'info> {
+        BadgeAccounts {
+            payer: &self.user,
+            owner: self.user.to_account_info(),
+            tier_badge: &mut self.tier_badge,
+            tier_badge_bump: bumps.tier_badge,
+            nullifier_record: self.nullifier_record.as_mut(),
+            nullifier_record_bump: bumps.nullifier_record,
+            wallet_marker: self.wallet_marker.as_ref(),
+            request_receipt: self.request_receipt.as_mut(),
+            request_receipt_bump: bumps.request_receipt,
+            instructions_sysvar: self.instructions_sysvar.as_ref(),
+            badge_tombstone: self.badge_tombstone.as_ref(),
+            reward_claim: self.reward_claim.as_mut(),
+            reward_claim_bump: bumps.reward_claim,
+            shared: &mut self.shared,
+        }
+    }
+}
+
+impl<'info> VerifyDelegated<'info> {
+    /// The owner's badge, paid for by the custodian. The instructions
+    /// sysvar carries the owner's authorization rather than a prover
+    /// attestation, so it isn't passed on: delegated badges are never
+    /// prover-attested
+    fn badge_accounts<'a>(&'a mut self, bumps: &VerifyDelegatedBumps) -> BadgeAccounts<'a, 'info> {
+        BadgeAccounts {
+            payer: &self.custodian,
+            owner: self.owner.to_account_info(),
+            tier_badge: &mut self.tier_badge,
+            tier_badge_bump: bumps.tier_badge,
+            nullifier_record: self.nullifier_record.as_mut(),
+            nullifier_record_bump: bumps.nullifier_record,
+            wallet_marker: self.wallet_marker.as_ref(),
+            request_receipt: self.request_receipt.as_mut(),
+            request_receipt_bump: bumps.request_receipt,
+            instructions_sysvar: None,
+            badge_tombstone: self.badge_tombstone.as_ref(),
+            reward_claim: self.reward_claim.as_mut(),
+            reward_claim_bump: bumps.reward_claim,
+            shared: &mut self.shared,
+        }
+    }
+}
+
+/// Verify a proof and create or refresh the caller's TierBadge. Shared by
+/// every instruction built on `VerifyAndStoreTier`. `create` restricts the
+/// call to creating a badge; otherwise it may only refresh one when the
+/// config requires explicit creates.
+fn verify_and_store(
+    accounts: &mut VerifyAndStoreTier,
+    bumps: &VerifyAndStoreTierBumps,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    create: bool,
+) -> Result<()> {
+    let vk_id = verify_proof(
+        &accounts.shared.config,
+        proof_a,
+        proof_b,
+        proof_c,
+        public_inputs,
+    )?;
+    store_verified(
+        accounts.badge_accounts(bumps),
+        public_inputs,
+        vk_id,
+        client_request_id,
+        account_type,
+        namespace,
+        create,
+        attestation::SIG_SCHEME_ED25519,
+    )
+}
+
+/// Create or refresh the owner's TierBadge from public inputs already
+/// verified under key `vk_id` (steps 2-8 of `verify_and_store`).
+/// `sig_scheme` records how the owner authorized the verification.
+fn store_verified(
+    mut accounts: BadgeAccounts,
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    vk_id: u8,
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    create: bool,
+    sig_scheme: u8,
+) -> Result<()> {
+    let owner = accounts.owner.key();
+    let config = &accounts.shared.config;
+    let clock = Clock::get()?;
+
+    // 2. Decode public signals
+    let tier_lower = u64::from_be_bytes(public_inputs[0][24..32].try_into().unwrap());
+    let tier_upper = u64::from_be_bytes(public_inputs[1][24..32].try_into().unwrap());
+    let nullifier = public_inputs[2];
+    let timestamp = i64::from_be_bytes(public_inputs[3][24..32].try_into().unwrap());
+
+    // 3. Validate tier bounds match a known tier
+    let tier: u8 = config
+        .classify_tier(tier_lower, tier_upper)
+        .ok_or(ProofOfLoveError::InvalidTier)?
+        .into();
+
+    // 3a. Deployments serving a band of tiers turn away those above it
+    require!(
+        tier <= config.max_accepted_tier,
+        ProofOfLoveError::TierAboveMaximum
+    );
+
+    // 4. Validate proof freshness
+    let now = clock.unix_timestamp;
+    require!(
+        now - timestamp <= config.max_proof_age_seconds,
+        ProofOfLoveError::ProofTooOld
+    );
+
+    // 4a. The holder kind must be one the deployment accepts
+    require!(
+        config.allowed_account_types & account_type.mask() != 0,
+        ProofOfLoveError::AccountTypeNotAllowed
+    );
+
+    // 4b. Optionally refuse transactions that also call denied programs
+    if config.restrict_bundles {
+        let sysvar = accounts
+            .shared
+            .bundle_sysvar
+            .as_ref()
+            .ok_or(ProofOfLoveError::DisallowedInstructionBundle)?;
+        bundle::require_allowed_bundle(sysvar, &config.denied_programs)?;
+    }
+
+    // 4c. Optionally require a sufficiently old wallet marker
+    if config.min_wallet_age_slots > 0 {
+        let marker = accounts
+            .wallet_marker
+            .as_ref()
+            .ok_or(ProofOfLoveError::WalletTooNew)?;
+        require!(
+            clock.slot
+                >= marker
+                    .created_slot
+                    .saturating_add(config.min_wallet_age_slots),
+            ProofOfLoveError::WalletTooNew
+        );
+    }
+
+    // 4d. Optionally require a funded wallet, as a cheap anti-spam gate.
+    // This is the owner's balance, not a custodian's, and Anchor has
+    // already taken the rent for any badge, nullifier record or request
+    // receipt this verification creates, so an owner paying their own way
+    // is credited it back
+    if config.min_wallet_lamports > 0 {
+        let mut balance = accounts.owner.lamports();
+        if accounts.payer.key() == owner {
+            let rent = Rent::get()?;
+            let created = [
+                (accounts.tier_badge.owner == Pubkey::default())
+                    .then(|| accounts.tier_badge.to_account_info()),
+                accounts
+                    .nullifier_record
+                    .as_ref()
+                    .filter(|record| record.owner == Pubkey::default())
+                    .map(|record| record.to_account_info()),
+                accounts
+                    .request_receipt
+                    .as_ref()
+                    .filter(|receipt| receipt.owner == Pubkey::default())
+                    .map(|receipt| receipt.to_account_info()),
+            ];
+            for info in created.into_iter().flatten() {
+                balance = balance.saturating_add(rent.minimum_balance(info.data_len()));
+            }
+        }
+        require!(
+            balance >= config.min_wallet_lamports,
+            ProofOfLoveError::WalletUnderfunded
+        );
+    }
+
+    // 4e. Reject a retry of a request that was already processed
+    if let Some(request_id) = client_request_id {
+        let receipt = accounts
+            .request_receipt
+            .as_mut()
+            .ok_or(ProofOfLoveError::MissingRequestReceipt)?;
+        require!(
+            receipt.owner == Pubkey::default()
+                || clock.slot >= receipt.slot.saturating_add(REQUEST_ID_WINDOW_SLOTS),
+            ProofOfLoveError::DuplicateRequest
+        );
+        receipt.owner = accounts.payer.key();
+        receipt.request_id = request_id;
+        receipt.slot = clock.slot;
+        receipt.bump = accounts.request_receipt_bump.unwrap_or_default();
+    }
+
+    // 5. Claim the nullifier, or confirm this wallet already holds it. In
+    // relaxed mode the record is optional and skipping it skips the claim
+    let registered = match accounts.nullifier_record.as_mut() {
+        Some(record) => {
+            if record.owner == Pubkey::default() {
+                record.nullifier = nullifier;
+                record.owner = owner;
+                record.bump = accounts.nullifier_record_bump.unwrap_or_default();
+                record.payer = accounts.payer.key();
+            }
+            require!(
+                record.owner == owner,
+                ProofOfLoveError::NullifierAlreadyUsed
+            );
+            true
+        }
+        None => {
+            require!(
+                !config.require_nullifier_uniqueness,
+                ProofOfLoveError::NullifierRecordRequired
+            );
+            false
+        }
+    };
+
+    // 6. On refresh, the wallet must keep the nullifier it first claimed,
+    // unless the authority attested its rotation to this one, and the
+    // clock can't be behind the badge's creation
+    let badge = &mut accounts.tier_badge;
+    let is_refresh = badge.owner != Pubkey::default();
+    if let Some(rotation) = accounts.shared.identity_rotation.as_ref() {
+        require!(
+            is_refresh && rotation.allows(&owner, &badge.nullifier, &nullifier),
+            ProofOfLoveError::InvalidIdentityRotation
+        );
+
+        // Release the badge from the old nullifier's registry entry, and
+        // retire the entry once no other namespace's badge is registered
+        // against it. A badge verified without one in relaxed mode has none
+        // to retire
+        match accounts.shared.previous_nullifier_record.as_mut() {
+            Some(previous) => {
+                require!(
+                    previous.nullifier == badge.nullifier && previous.owner == owner,
+                    ProofOfLoveError::InvalidIdentityRotation
+                );
+                let orphaned = if badge.nullifier_enforced {
+                    previous.release_badge()
+                } else {
+                    previous.badges == 0
+                };
+                if orphaned {
+                    previous.close(accounts.payer.to_account_info())?;
+                }
+            }
+            None => require!(
+                !badge.nullifier_enforced,
+                ProofOfLoveError::InvalidIdentityRotation
+            ),
+        }
+
+        emit!(IdentityRotated {
+            owner,
+            old_nullifier: badge.nullifier,
+            new_nullifier: nullifier,
+        });
+        badge.nullifier = nullifier;
+        badge.nullifier_enforced = false;
+    }
+    require!(
+        !is_refresh || badge.nullifier == nullifier,
+        ProofOfLoveError::NullifierChanged
+    );
+    if is_refresh {
+        badge.require_clock_after_creation(clock.unix_timestamp)?;
+    }
+
+    // 6a. Keep creates and refreshes apart when the caller or config asks
+    if create {
+        require!(!is_refresh, ProofOfLoveError::BadgeAlreadyExists);
+    } else {
+        require!(
+            is_refresh || !config.require_explicit_create,
+            ProofOfLoveError::BadgeNotCreated
+        );
+    }
+
+    // 6b. A burned identity stays burned when the config says so
+    if !is_refresh && config.tombstone_blocks_recreate {
+        let tombstone = accounts
+            .badge_tombstone
+            .as_ref()
+            .ok_or(ProofOfLoveError::BadgeBurned)?;
+        require!(tombstone.data_is_empty(), ProofOfLoveError::BadgeBurned);
+    }
+
+    // 6c. A badge created through a relayer is only refreshed through it
+    // when the config says so
+    let relayer = accounts
+        .shared
+        .relayer
+        .as_ref()
+        .map(|relayer| relayer.key())
+        .unwrap_or_default();
+    if is_refresh && config.require_origin_relayer {
+        require!(
+            badge.origin_relayer == Pubkey::default() || relayer == badge.origin_relayer,
+            ProofOfLoveError::RelayerMismatch
+        );
+    }
+
+    // 6d. Bound how often a badge can be refreshed
+    if is_refresh && config.max_refreshes_per_window > 0 {
+        require!(
+            badge.record_refresh(
+                clock.unix_timestamp,
+                config.refresh_window_seconds,
+                config.max_refreshes_per_window
+            ),
+            ProofOfLoveError::RefreshLimitReached
+        );
+    }
+
+    // 6e. An instructions sysvar means the caller claims a prover
+    // attestation, which must then check out
+    let prover_attested = match &accounts.instructions_sysvar {
+        Some(sysvar) => {
+            require!(
+                config.prover_key != Pubkey::default(),
+                ProofOfLoveError::ProverSignatureInvalid
+            );
+            attestation::require_attestation(
+                sysvar,
+                &config.prover_key,
+                &attestation::attestation_message(public_inputs),
+            )?;
+            true
+        }
+        None => false,
+    };
+
+    // 7. Write the TierBadge PDA. The badge always belongs to the signer,
+    // or to the wallet whose signature authorized a delegated verification:
+    // a refreshed badge must already be theirs, and `owner` is never taken
+    // from anywhere else.
+    require!(
+        !is_refresh || badge.owner == owner,
+        ProofOfLoveError::OwnerSignerMismatch
+    );
+    let previous_expires_at = badge.expires_at;
+    badge.owner = owner;
+    badge.record_tier(tier);
+    badge.tier_lower_bound = tier_lower;
+    badge.tier_upper_bound = tier_upper;
+    badge.nullifier = nullifier;
+    badge.verified_at = timestamp;
+    badge.expires_at = config.badge_expiry(timestamp, clock.unix_timestamp);
+    badge.bump = accounts.tier_badge_bump;
+    badge.prover_attested = prover_attested;
+    badge.account_type = account_type;
+    badge.namespace = namespace.unwrap_or_default();
+    badge.currency_code = config.currency_code;
+    badge.sig_scheme = sig_scheme;
+    badge.vk_id = vk_id;
+    badge.verification_epoch = config.verification_epoch;
+    // A refresh can't change the nullifier, so a registration from an
+    // earlier verification still holds and is only counted once
+    if registered && !badge.nullifier_enforced {
+        if let Some(record) = accounts.nullifier_record.as_mut() {
+            record.register_badge();
+        }
+        badge.nullifier_enforced = true;
+    }
+    if !is_refresh {
+        badge.trusted_after = clock
+            .unix_timestamp
+            .saturating_add(config.probation_seconds);
+        badge.origin_relayer = relayer;
+        badge.refresh_window_start = clock.unix_timestamp;
+        badge.refresh_count = 0;
+        badge.created_at = clock.unix_timestamp;
+        badge.program_version = PROGRAM_VERSION;
+    }
+
+    emit!(TierVerified {
+        owner,
+        tier,
+        account_type,
+        verified_at: badge.verified_at,
+        expires_at: badge.expires_at,
+        tier_badge: badge.key(),
+        currency_code: badge.currency_code,
+        label: badge.label,
+    });
+
+    msg!(
+        "Proof of Love: {} verified as Tier {} (bounds: {} - {})",
+        owner,
+        tier,
+        tier_lower,
+        tier_upper
+    );
+
+    // 7a. Pay a new badge's rent back from the treasury for subsidized
+    // tiers. The treasury keeps its own rent-exempt reserve and never pays
+    // out more than the per-epoch cap
+    let min_tier = config.subsidy_min_tier;
+    if !is_refresh && min_tier != 0 && tier >= min_tier {
+        if let Some(treasury) = accounts.shared.treasury.as_mut() {
+            let rent = Rent::get()?;
+            let subsidy = rent.minimum_balance(8 + TierBadge::INIT_SPACE);
+            let reserve = rent.minimum_balance(8 + Treasury::INIT_SPACE);
+            require!(
+                treasury.get_lamports() >= reserve.saturating_add(subsidy)
+                    && treasury.record_subsidy(
+                        clock.epoch,
+                        subsidy,
+                        config.subsidy_epoch_cap_lamports
+                    ),
+                ProofOfLoveError::TreasuryInsufficient
+            );
+            treasury.sub_lamports(subsidy)?;
+            accounts.owner.add_lamports(subsidy)?;
+
+            msg!(
+                "Proof of Love: treasury paid {} lamports of badge rent",
+                subsidy
+            );
+        }
+    }
+
+    // 7b. Keep the badge listed under the day it now expires, when the
+    // caller passes the buckets. A refresh that moves a listed badge's
+    // expiry to another day must pass the old day's bucket too
+    relist_expiry(
+        accounts.tier_badge,
+        is_refresh.then_some(previous_expires_at),
+        accounts
+            .shared
+            .previous_expiry_bucket
+            .as_deref_mut()
+            .map(|bucket| &mut **bucket),
+        accounts
+            .shared
+            .expiry_bucket
+            .as_deref_mut()
+            .map(|bucket| &mut **bucket),
+    )?;
+
+    // 7c. Leave a parseable tier memo for compliance tooling, when the
+    // config enables it and the caller passes the Memo program
+    if let Some(memo_program) = accounts
+        .shared
+        .memo_program
+        .as_ref()
+        .filter(|_| accounts.shared.config.emit_tier_memo)
+    {
+        memo::build_memo(
+            CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+            format!("POL:T{}", tier).as_bytes(),
+        )?;
+    }
+
+    // 7d. Pay an identity's first verification its reward from the reward
+    // vault, keeping the vault's rent-exempt reserve. The claim is keyed by
+    // nullifier, so recreating the badge never pays again
+    let reward = accounts.shared.config.first_verify_reward_lamports;
+    if !is_refresh && reward > 0 {
+        if let (Some(vault), Some(claim)) = (
+            accounts.shared.reward_vault.as_mut(),
+            accounts.reward_claim.as_mut(),
+        ) {
+            if claim.recipient == Pubkey::default() {
+                let reserve = Rent::get()?.minimum_balance(8 + RewardVault::INIT_SPACE);
+                require!(
+                    vault.get_lamports() >= reserve.saturating_add(reward),
+                    ProofOfLoveError::RewardVaultEmpty
+                );
+                vault.sub_lamports(reward)?;
+                accounts.owner.add_lamports(reward)?;
+                vault.paid_out_lamports = vault.paid_out_lamports.saturating_add(reward);
+                claim.recipient = owner;
+                claim.amount = reward;
+                claim.claimed_at = clock.unix_timestamp;
+                claim.bump = accounts.reward_claim_bump.unwrap_or_default();
+
+                msg!("Proof of Love: reward vault paid {} lamports", reward);
+            }
+        }
+    }
+
+    // 7e. Append the verification to the owner's history log, when the
+    // config enables it and the caller passes the log
+    if let Some(log) = accounts
+        .shared
+        .verification_log
+        .as_mut()
+        .filter(|_| accounts.shared.config.log_verifications)
+    {
+        require_keys_eq!(log.owner, owner, ProofOfLoveError::InvalidVerificationLog);
+        let leaf = VerificationLog::leaf(tier, timestamp, &nullifier);
+        let index = log
+            .append(leaf)
+            .ok_or(ProofOfLoveError::VerificationLogFull)?;
+        emit!(VerificationLogged {
+            verification_log: log.key(),
+            owner,
+            index,
+            leaf,
+            root: log.root,
+        });
+    }
+
+    // 8. Notify the integrator hook, if any. The badge is serialized first
+    // so the hook reads what was just written; a hook error rolls the
+    // whole verification back
+    let hook_program = accounts.shared.config.hook_program;
+    if hook_program != Pubkey::default() {
+        let hook = accounts
+            .shared
+            .hook_program
+            .as_ref()
+            .filter(|hook| hook.key() == hook_program)
+            .ok_or(ProofOfLoveError::HookProgramMismatch)?;
+        accounts.tier_badge.exit(&crate::ID)?;
+        hook::call_hook(hook, &accounts.tier_badge.to_account_info(), tier)?;
+    }
+
+    Ok(())
+}
+
+fn process_verify_compressed(
+    ctx: Context<VerifyAndStoreTierCompressed>,
+    proof_a: &[u8; 32],
+    proof_b: &[u8; 64],
+    proof_c: &[u8; 32],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+) -> Result<()> {
+    let public_inputs = decode_layout(layout_version, public_inputs)?;
+    let (proof_a, proof_b, proof_c) = encoding::decompress_proof(proof_a, proof_b, proof_c)
+        .ok_or(ProofOfLoveError::ProofDecompressionFailed)?;
+
+    let vk_id = verify_proof(
+        &ctx.accounts.shared.config,
+        &proof_a,
+        &proof_b,
+        &proof_c,
+        &public_inputs,
+    )?;
+    store_verified(
+        ctx.accounts.badge_accounts(&ctx.bumps),
+        &public_inputs,
+        vk_id,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+        attestation::SIG_SCHEME_ED25519,
+    )
+}
+
+fn process_verify_stage1(
+    ctx: Context<VerifyProofStage1>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    layout_version: u8,
+) -> Result<()> {
+    let public_inputs = decode_layout(layout_version, public_inputs)?;
+    let vk_id = verify_proof(
+        &ctx.accounts.config,
+        proof_a,
+        proof_b,
+        proof_c,
+        &public_inputs,
+    )?;
+
+    let pending = &mut ctx.accounts.pending_verification;
+    pending.public_inputs = public_inputs;
+    pending.vk_id = vk_id;
+    pending.slot = Clock::get()?.slot;
+    pending.bump = ctx.bumps.pending_verification;
+
+    Ok(())
+}
+
+fn process_verify_stage2(
+    ctx: Context<VerifyProofStage2>,
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+) -> Result<()> {
+    let accounts = ctx.accounts;
+    require!(
+        accounts.pending_verification.is_fresh(Clock::get()?.slot),
+        ProofOfLoveError::StaleVerification
+    );
+    let public_inputs = accounts.pending_verification.public_inputs;
+    let vk_id = accounts.pending_verification.vk_id;
+
+    // The config may have changed since stage 1, e.g. to pause
+    precheck(&accounts.shared.config, &public_inputs)?;
+
+    store_verified(
+        accounts.badge_accounts(&ctx.bumps),
+        &public_inputs,
+        vk_id,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+        attestation::SIG_SCHEME_ED25519,
+    )
+}
+
+fn process_verify_delegated(
+    ctx: Context<VerifyDelegated>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+    sig_scheme: u8,
+) -> Result<()> {
+    let public_inputs = decode_layout(layout_version, public_inputs)?;
+    let accounts = ctx.accounts;
+    let owner = accounts.owner.key();
+    let message = attestation::delegation_message(
+        &accounts.custodian.key(),
+        &namespace.unwrap_or_default(),
+        &public_inputs,
+    );
+    match sig_scheme {
+        attestation::SIG_SCHEME_ED25519 => {
+            attestation::require_delegation(&accounts.instructions_sysvar, &owner, &message)?
+        }
+        attestation::SIG_SCHEME_SECP256K1 => attestation::require_secp256k1_delegation(
+            &accounts.instructions_sysvar,
+            &owner,
+            &message,
+        )?,
+        _ => return err!(ProofOfLoveError::UnsupportedSigScheme),
+    }
+
+    let vk_id = verify_proof(
+        &accounts.shared.config,
+        proof_a,
+        proof_b,
+        proof_c,
+        &public_inputs,
+    )?;
+    store_verified(
+        accounts.badge_accounts(&ctx.bumps),
+        &public_inputs,
+        vk_id,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+        sig_scheme,
+    )
+}
+
+fn process_verify_with_referral(
+    ctx: Context<VerifyWithReferral>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+    referrer: Pubkey,
+    referrer_namespace: Option<[u8; 8]>,
+) -> Result<()> {
+    let referee = ctx.accounts.verify.user.key();
+    require!(referrer != referee, ProofOfLoveError::SelfReferral);
+
+    verify_and_store(
+        &mut ctx.accounts.verify,
+        &ctx.bumps.verify,
+        proof_a,
+        proof_b,
+        proof_c,
+        &decode_layout(layout_version, public_inputs)?,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+    )?;
+
+    // The seeds pin the account to the referrer's badge PDA in
+    // `referrer_namespace`; it must also hold a live badge from there
+    let now = Clock::get()?.unix_timestamp;
+    let referrer_info = ctx.accounts.referrer_badge.to_account_info();
+    require!(
+        *referrer_info.owner == crate::ID,
+        ProofOfLoveError::ReferrerWithoutBadge
+    );
+    let referrer_badge = TierBadge::try_deserialize(&mut &referrer_info.try_borrow_data()?[..])
+        .map_err(|_| ProofOfLoveError::ReferrerWithoutBadge)?;
+    require!(
+        referrer_badge.owner == referrer
+            && referrer_badge.namespace == referrer_namespace.unwrap_or(DEFAULT_NAMESPACE)
+            && now <= referrer_badge.expires_at,
+        ProofOfLoveError::ReferrerWithoutBadge
+    );
+
+    let referral = &mut ctx.accounts.referral;
+    referral.referee = referee;
+    referral.referrer = referrer;
+    referral.registered_at = now;
+    referral.bump = ctx.bumps.referral;
+
+    emit!(ReferralRegistered { referee, referrer });
+    msg!("Proof of Love: {} referred by {}", referee, referrer);
+
+    Ok(())
+}
+
+fn process_verify_and_set_voting_power(
+    ctx: Context<VerifyAndSetVotingPower>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+) -> Result<()> {
+    verify_and_store(
+        &mut ctx.accounts.verify,
+        &ctx.bumps.verify,
+        proof_a,
+        proof_b,
+        proof_c,
+        &decode_layout(layout_version, public_inputs)?,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+    )?;
+
+    let badge = &ctx.accounts.verify.tier_badge;
+    let power = &mut ctx.accounts.voting_power;
+    power.badge = badge.key();
+    power.owner = badge.owner;
+    power.tier = badge.tier;
+    power.weight = ctx.accounts.verify.shared.config.tier_weight(badge.tier);
+    power.expires_at = badge.expires_at;
+    power.bump = ctx.bumps.voting_power;
+
+    msg!(
+        "Proof of Love: {} voting power set to {} at Tier {}",
+        power.owner,
+        power.weight,
+        power.tier
+    );
+
+    Ok(())
+}
+
+/// Apply `update` to the VotingPower at `account` if `badge` set it. An
+/// identity that never set a weight, or whose weight has since moved to a
+/// badge in another namespace, is left alone.
+fn update_voting_power(
+    account: &AccountInfo,
+    badge: &Pubkey,
+    update: impl FnOnce(&mut VotingPower),
+) -> Result<()> {
+    if *account.owner != crate::ID {
+        return Ok(());
+    }
+    let mut power = VotingPower::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    if power.badge != *badge {
+        return Ok(());
+    }
+    update(&mut power);
+    power.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+}
+
+/// Space of a TierBadge account, after checking that `badge` was passed
+/// writable. Verifying instructions use it as the `space` of their
+/// `init_if_needed` badge: Anchor evaluates that before creating the
+/// account, whereas a `mut` or `constraint` check only runs afterwards, by
+/// which point a read-only badge has already failed in the system program.
+fn writable_badge_space(badge: &AccountInfo) -> Result<usize> {
+    require!(badge.is_writable, ProofOfLoveError::BadgeNotWritable);
+    Ok(8 + TierBadge::INIT_SPACE)
+}
+
+/// Keep a badge's ExpiryBucket listing in step with its expiry, which was
+/// `previous_expires_at` before this instruction (`None` for a new badge).
+/// `previous`, the old day's bucket, drops the badge and `bucket`, the new
+/// day's, lists it. A listed badge whose expiry moved to another day must
+/// pass `previous`, or it would stay listed under the wrong day.
+fn relist_expiry(
+    badge: &mut Account<TierBadge>,
+    previous_expires_at: Option<i64>,
+    previous: Option<&mut ExpiryBucket>,
+    bucket: Option<&mut ExpiryBucket>,
+) -> Result<()> {
+    let badge_key = badge.key();
+    match (previous, previous_expires_at) {
+        (Some(previous), Some(previous_expires_at)) => {
+            require!(
+                previous.day == ExpiryBucket::day_of(previous_expires_at),
+                ProofOfLoveError::InvalidExpiryBucket
+            );
+            previous.remove(&badge_key);
+            badge.expiry_listed = false;
+        }
+        (Some(_), None) => return err!(ProofOfLoveError::InvalidExpiryBucket),
+        (None, Some(previous_expires_at)) => require!(
+            !badge.expiry_listed
+                || ExpiryBucket::day_of(previous_expires_at)
+                    == ExpiryBucket::day_of(badge.expires_at),
+            ProofOfLoveError::ExpiryBucketRequired
+        ),
+        (None, None) => {}
+    }
+    if let Some(bucket) = bucket {
+        require!(
+            bucket.day == ExpiryBucket::day_of(badge.expires_at),
+            ProofOfLoveError::InvalidExpiryBucket
+        );
+        require!(bucket.insert(badge_key), ProofOfLoveError::ExpiryBucketFull);
+        badge.expiry_listed = true;
+    }
+    Ok(())
+}
+
+/// Drop a closing badge from the ExpiryBucket for its expiry day, which
+/// must be passed if the badge is listed there.
+fn unlist_expiry(
+    badge: &TierBadge,
+    badge_key: &Pubkey,
+    bucket: Option<&mut ExpiryBucket>,
+) -> Result<()> {
+    match bucket {
+        Some(bucket) => {
+            require!(
+                bucket.day == ExpiryBucket::day_of(badge.expires_at),
+                ProofOfLoveError::InvalidExpiryBucket
+            );
+            bucket.remove(badge_key);
+        }
+        None => require!(!badge.expiry_listed, ProofOfLoveError::ExpiryBucketRequired),
+    }
+    Ok(())
+}
+
+/// Stop counting a closing badge against its NullifierRecord, if the badge
+/// was registered there. Records that don't exist or belong to another
+/// wallet are left alone.
+fn release_nullifier(account: &AccountInfo, badge: &TierBadge) -> Result<()> {
+    if !badge.nullifier_enforced || *account.owner != crate::ID {
+        return Ok(());
+    }
+    let mut record = NullifierRecord::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    if record.owner != badge.owner {
+        return Ok(());
+    }
+    record.release_badge();
+    record.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+}
+
+fn process_verify_and_join(
+    ctx: Context<VerifyAndJoin>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+) -> Result<()> {
+    require!(
+        ctx.accounts.membership.member == Pubkey::default(),
+        ProofOfLoveError::AlreadyMember
+    );
+
+    verify_and_store(
+        &mut ctx.accounts.verify,
+        &ctx.bumps.verify,
+        proof_a,
+        proof_b,
+        proof_c,
+        &decode_layout(layout_version, public_inputs)?,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+    )?;
+
+    let tier = ctx.accounts.verify.tier_badge.tier;
+    let gate = &mut ctx.accounts.dao_gate;
+    require!(tier >= gate.min_tier, ProofOfLoveError::TierBelowDaoMinimum);
+    gate.member_count = gate.member_count.saturating_add(1);
+
+    let member = ctx.accounts.verify.user.key();
+    let membership = &mut ctx.accounts.membership;
+    membership.dao = gate.dao;
+    membership.member = member;
+    membership.tier = tier;
+    membership.joined_at = Clock::get()?.unix_timestamp;
+    membership.bump = ctx.bumps.membership;
+
+    emit!(MemberJoined {
+        dao: gate.dao,
+        member,
+        tier,
+    });
+    msg!(
+        "Proof of Love: {} joined DAO {} at Tier {}",
+        member,
+        gate.dao,
+        tier
+    );
+
+    Ok(())
+}
+
+fn process_verify_range(
+    ctx: Context<VerifyRange>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+
+    // Check the range before paying for the pairing check. Bounds wider
+    // than 64 bits would slip past the circuit's comparators
+    let (lower, upper) = match (
+        encoding::decode_u64_signal(&public_inputs[0]),
+        encoding::decode_u64_signal(&public_inputs[1]),
+    ) {
+        (Some(lower), Some(upper)) if state::range_is_valid(lower, upper) => (lower, upper),
+        _ => return err!(ProofOfLoveError::InvalidRange),
+    };
+
+    verify_proof(config, proof_a, proof_b, proof_c, public_inputs)?;
+
+    let clock = Clock::get()?;
+    let nullifier = public_inputs[2];
+    let timestamp = i64::from_be_bytes(public_inputs[3][24..32].try_into().unwrap());
+    require!(
+        clock.unix_timestamp - timestamp <= config.max_proof_age_seconds,
+        ProofOfLoveError::ProofTooOld
+    );
+
+    let owner = ctx.accounts.user.key();
+    let record = &mut ctx.accounts.nullifier_record;
+    if record.owner == Pubkey::default() {
+        record.nullifier = nullifier;
+        record.owner = owner;
+        record.bump = ctx.bumps.nullifier_record;
+        record.payer = owner;
+    }
+    require!(
+        record.owner == owner,
+        ProofOfLoveError::NullifierAlreadyUsed
+    );
+
+    let badge = &mut ctx.accounts.range_badge;
+    let is_refresh = badge.owner != Pubkey::default();
+    require!(
+        !is_refresh || badge.nullifier == nullifier,
+        ProofOfLoveError::NullifierChanged
+    );
+    if !is_refresh {
+        record.register_badge();
+    }
+    badge.owner = owner;
+    badge.lower_bound = lower;
+    badge.upper_bound = upper;
+    badge.nullifier = nullifier;
+    badge.verified_at = timestamp;
+    badge.expires_at = config.badge_expiry(timestamp, clock.unix_timestamp);
+    badge.bump = ctx.bumps.range_badge;
+
+    emit!(RangeVerified {
+        owner,
+        lower_bound: lower,
+        upper_bound: upper,
+        expires_at: badge.expires_at,
+        range_badge: badge.key(),
+    });
+    msg!(
+        "Proof of Love: {} verified in range {} - {}",
+        owner,
+        lower,
+        upper
+    );
+
+    Ok(())
+}
+
+fn process_verify_and_mint(
+    ctx: Context<VerifyAndMint>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+) -> Result<()> {
+    verify_and_store(
+        &mut ctx.accounts.verify,
+        &ctx.bumps.verify,
+        proof_a,
+        proof_b,
+        proof_c,
+        &decode_layout(layout_version, public_inputs)?,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+    )?;
+
+    let badge = &ctx.accounts.verify.tier_badge;
+    let (authority, authority_bump) =
+        Pubkey::find_program_address(&[b"tier_mint_authority", &[badge.tier]], &crate::ID);
+    require!(
+        ctx.accounts.mint.mint_authority == COption::Some(authority)
+            && ctx.accounts.mint_authority.key() == authority,
+        ProofOfLoveError::MintTierMismatch
+    );
+
+    let receipt = &mut ctx.accounts.mint_receipt;
+    receipt.badge = badge.key();
+    receipt.mint = ctx.accounts.mint.key();
+    receipt.tier = badge.tier;
+    receipt.minted_at = Clock::get()?.unix_timestamp;
+    receipt.bump = ctx.bumps.mint_receipt;
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            &[&[b"tier_mint_authority", &[badge.tier], &[authority_bump]]],
+        ),
+        1,
+    )?;
+
+    msg!(
+        "Proof of Love: minted Tier {} token {} to {}",
+        badge.tier,
+        receipt.mint,
+        ctx.accounts.token_account.key()
+    );
+
+    Ok(())
+}
+
+fn process_verify_and_stake(
+    ctx: Context<VerifyAndStake>,
+    proof_a: &[u8; 64],
+    proof_b: &[u8; 128],
+    proof_c: &[u8; 64],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+    amount: u64,
+) -> Result<()> {
+    let min_tier = ctx.accounts.stake_pool.min_tier;
+
+    verify_and_store(
+        &mut ctx.accounts.verify,
+        &ctx.bumps.verify,
+        proof_a,
+        proof_b,
+        proof_c,
+        &decode_layout(layout_version, public_inputs)?,
+        client_request_id,
+        account_type,
+        namespace,
+        false,
+    )?;
+
+    let badge = &ctx.accounts.verify.tier_badge;
+    require!(
+        badge.tier >= min_tier,
+        ProofOfLoveError::TierBelowPoolMinimum
+    );
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.source.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.verify.user.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let receipt = &mut ctx.accounts.stake_receipt;
+    receipt.badge = badge.key();
+    receipt.vault = ctx.accounts.vault.key();
+    receipt.min_tier = min_tier;
+    receipt.amount = receipt.amount.saturating_add(amount);
+    receipt.staked_at = Clock::get()?.unix_timestamp;
+    receipt.bump = ctx.bumps.stake_receipt;
+
+    msg!(
+        "Proof of Love: staked {} into {} at Tier {} (min {})",
+        amount,
+        receipt.vault,
+        badge.tier,
+        min_tier
+    );
+
+    Ok(())
+}
+
+/// Accounts every tier verification takes alongside its own, none of whose
+/// constraints depend on the payer, the badge or the instruction arguments.
+/// Nested as `shared` by each verifying instruction.
+#[derive(Accounts)]
+pub struct VerifyShared<'info> {
+    /// Required only to refresh onto a new nullifier: the authority's
+    /// attestation of the rotation
+    #[account(
+        seeds = [b"identity_rotation", identity_rotation.old_nullifier.as_ref()],
+        bump = identity_rotation.bump,
+    )]
+    pub identity_rotation: Option<Box<Account<'info, IdentityRotation>>>,
+
+    /// The retired nullifier's registry entry, closed by a rotation
+    #[account(
+        mut,
+        seeds = [b"nullifier", previous_nullifier_record.nullifier.as_ref()],
+        bump = previous_nullifier_record.bump,
+    )]
+    pub previous_nullifier_record: Option<Box<Account<'info, NullifierRecord>>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: the instructions sysvar; required only when
+    /// `config.restrict_bundles` is set
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub bundle_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: must be `config.hook_program`, checked in the handler.
+    /// Required only when the config sets a hook
+    pub hook_program: Option<UncheckedAccount<'info>>,
+
+    /// Required only for a subsidized tier's first verification; without it
+    /// the payer covers the badge rent as usual
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Option<Box<Account<'info, Treasury>>>,
+
+    /// Relayer submitting on the owner's behalf. Recorded as a new badge's
+    /// `origin_relayer`, and required on its refreshes when
+    /// `config.require_origin_relayer` is set
+    pub relayer: Option<Signer<'info>>,
+
+    /// ExpiryBucket for the day the badge will expire, to list it there
+    #[account(
+        mut,
+        seeds = [b"expiry_bucket".as_ref(), &expiry_bucket.day.to_le_bytes()],
+        bump = expiry_bucket.bump,
+    )]
+    pub expiry_bucket: Option<Box<Account<'info, ExpiryBucket>>>,
+
+    /// ExpiryBucket for the day a refreshed badge expired on before,
+    /// required when the badge is listed there and the refresh moves it to
+    /// another day
+    #[account(
+        mut,
+        seeds = [b"expiry_bucket".as_ref(), &previous_expiry_bucket.day.to_le_bytes()],
+        bump = previous_expiry_bucket.bump,
+    )]
+    pub previous_expiry_bucket: Option<Box<Account<'info, ExpiryBucket>>>,
+
+    /// Required only for the memo written when `config.emit_tier_memo` is
+    /// set
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// Required, with `reward_claim`, for a first verification to collect
+    /// `config.first_verify_reward_lamports`
+    #[account(mut, seeds = [b"reward_vault"], bump = reward_vault.bump)]
+    pub reward_vault: Option<Box<Account<'info, RewardVault>>>,
+
+    /// Required only for the history leaf appended when
+    /// `config.log_verifications` is set
+    #[account(
+        mut,
+        seeds = [b"verification_log", verification_log.owner.as_ref()],
+        bump = verification_log.bump,
+    )]
+    pub verification_log: Option<Box<Account<'info, VerificationLog>>>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+)]
+pub struct VerifyAndStoreTier<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Checked writable up front, see `writable_badge_space`
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = writable_badge_space(tier_badge)?,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(namespace.as_ref().unwrap_or(&DEFAULT_NAMESPACE)),
+        ],
+        bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// Required unless `config.require_nullifier_uniqueness` is off
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [
+            b"nullifier",
+            encoding::nullifier_input(layout_version, &public_inputs).as_ref(),
+        ],
+        bump,
+    )]
+    pub nullifier_record: Option<Box<Account<'info, NullifierRecord>>>,
+
+    /// Required only when `config.min_wallet_age_slots` is set
+    #[account(
+        seeds = [b"wallet_marker", user.key().as_ref()],
+        bump = wallet_marker.bump,
+    )]
+    pub wallet_marker: Option<Account<'info, WalletMarker>>,
+
+    /// Required only when `client_request_id` is passed
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RequestReceipt::INIT_SPACE,
+        seeds = [
+            b"request",
+            user.key().as_ref(),
+            client_request_id.unwrap_or_default().as_ref(),
+        ],
+        bump,
+    )]
+    pub request_receipt: Option<Account<'info, RequestReceipt>>,
+
+    /// CHECK: the instructions sysvar; pass it only with a prover
+    /// attestation in the preceding Ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: the identity's BadgeTombstone PDA, which must not exist.
+    /// Required only for first verifications when
+    /// `config.tombstone_blocks_recreate` is set
+    #[account(
+        seeds = [b"tombstone", encoding::nullifier_input(layout_version, &public_inputs).as_ref()],
+        bump,
+    )]
+    pub badge_tombstone: Option<UncheckedAccount<'info>>,
+
+    /// The identity's reward claim, created on its first verification
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardClaim::INIT_SPACE,
+        seeds = [b"reward_claim", encoding::nullifier_input(layout_version, &public_inputs).as_ref()],
+        bump,
+    )]
+    pub reward_claim: Option<Box<Account<'info, RewardClaim>>>,
+
+    pub shared: VerifyShared<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// The accounts of `VerifyAndStoreTier`, for the compressed instruction
+/// layout (its shorter proof changes where the seed arguments sit).
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 32],
+    proof_b: [u8; 64],
+    proof_c: [u8; 32],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+)]
+pub struct VerifyAndStoreTierCompressed<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Checked writable up front, see `writable_badge_space`
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = writable_badge_space(tier_badge)?,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(namespace.as_ref().unwrap_or(&DEFAULT_NAMESPACE)),
+        ],
+        bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// Required unless `config.require_nullifier_uniqueness` is off
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [
+            b"nullifier",
+            encoding::nullifier_input(layout_version, &public_inputs).as_ref(),
+        ],
+        bump,
+    )]
+    pub nullifier_record: Option<Box<Account<'info, NullifierRecord>>>,
+
+    /// Required only when `config.min_wallet_age_slots` is set
+    #[account(
+        seeds = [b"wallet_marker", user.key().as_ref()],
+        bump = wallet_marker.bump,
+    )]
+    pub wallet_marker: Option<Account<'info, WalletMarker>>,
+
+    /// Required only when `client_request_id` is passed
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RequestReceipt::INIT_SPACE,
+        seeds = [
+            b"request",
+            user.key().as_ref(),
+            client_request_id.unwrap_or_default().as_ref(),
+        ],
+        bump,
+    )]
+    pub request_receipt: Option<Account<'info, RequestReceipt>>,
+
+    /// CHECK: the instructions sysvar; pass it only with a prover
+    /// attestation in the preceding Ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: the identity's BadgeTombstone PDA, which must not exist.
+    /// Required only for first verifications when
+    /// `config.tombstone_blocks_recreate` is set
+    #[account(
+        seeds = [b"tombstone", encoding::nullifier_input(layout_version, &public_inputs).as_ref()],
+        bump,
+    )]
+    pub badge_tombstone: Option<UncheckedAccount<'info>>,
+
+    /// The identity's reward claim, created on its first verification
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardClaim::INIT_SPACE,
+        seeds = [b"reward_claim", encoding::nullifier_input(layout_version, &public_inputs).as_ref()],
+        bump,
+    )]
+    pub reward_claim: Option<Box<Account<'info, RewardClaim>>>,
+
+    pub shared: VerifyShared<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// The accounts of `VerifyAndStoreTier` for a custodian verifying into
+/// `owner`'s badge: the custodian signs and pays, and the badge and wallet
+/// marker are `owner`'s.
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+)]
+pub struct VerifyDelegated<'info> {
+    #[account(mut)]
+    pub custodian: Signer<'info>,
+
+    /// CHECK: the badge owner, who authorizes the verification through
+    /// the precompile instruction checked in the handler. Writable to
+    /// collect any rent subsidy and first-verification reward
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Checked writable up front, see `writable_badge_space`
+    #[account(
+        init_if_needed,
+        payer = custodian,
+        space = writable_badge_space(tier_badge)?,
+        seeds = [
+            b"tier_badge",
+            owner.key().as_ref(),
+            namespace_seed(namespace.as_ref().unwrap_or(&DEFAULT_NAMESPACE)),
+        ],
+        bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// Required unless `config.require_nullifier_uniqueness` is off
+    #[account(
+        init_if_needed,
+        payer = custodian,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [
+            b"nullifier",
+            encoding::nullifier_input(layout_version, &public_inputs).as_ref(),
+        ],
+        bump,
+    )]
+    pub nullifier_record: Option<Box<Account<'info, NullifierRecord>>>,
+
+    /// Required only when `config.min_wallet_age_slots` is set
+    #[account(
+        seeds = [b"wallet_marker", owner.key().as_ref()],
+        bump = wallet_marker.bump,
+    )]
+    pub wallet_marker: Option<Account<'info, WalletMarker>>,
+
+    /// Required only when `client_request_id` is passed
+    #[account(
+        init_if_needed,
+        payer = custodian,
+        space = 8 + RequestReceipt::INIT_SPACE,
+        seeds = [
+            b"request",
+            custodian.key().as_ref(),
+            client_request_id.unwrap_or_default().as_ref(),
+        ],
+        bump,
+    )]
+    pub request_receipt: Option<Account<'info, RequestReceipt>>,
+
+    /// CHECK: the instructions sysvar, holding the owner's authorization
+    /// in the preceding Ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: the identity's BadgeTombstone PDA, which must not exist.
+    /// Required only for first verifications when
+    /// `config.tombstone_blocks_recreate` is set
+    #[account(
+        seeds = [b"tombstone", encoding::nullifier_input(layout_version, &public_inputs).as_ref()],
+        bump,
+    )]
+    pub badge_tombstone: Option<UncheckedAccount<'info>>,
+
+    /// The identity's reward claim, created on its first verification
+    #[account(
+        init_if_needed,
+        payer = custodian,
+        space = 8 + RewardClaim::INIT_SPACE,
+        seeds = [b"reward_claim", encoding::nullifier_input(layout_version, &public_inputs).as_ref()],
+        bump,
+    )]
+    pub reward_claim: Option<Box<Account<'info, RewardClaim>>>,
+
+    pub shared: VerifyShared<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyProofStage1<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + PendingVerification::INIT_SPACE,
+        seeds = [b"pending_verification", user.key().as_ref()],
+        bump,
+    )]
+    pub pending_verification: Box<Account<'info, PendingVerification>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// The accounts of `VerifyAndStoreTier`, with the nullifier read from the
+/// staged proof rather than the instruction arguments.
+#[derive(Accounts)]
+#[instruction(
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+)]
+pub struct VerifyProofStage2<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// The proof staged by `verify_proof_stage1`, closed to `user`
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"pending_verification", user.key().as_ref()],
+        bump = pending_verification.bump,
+    )]
+    pub pending_verification: Box<Account<'info, PendingVerification>>,
+
+    /// Checked writable up front, see `writable_badge_space`
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = writable_badge_space(tier_badge)?,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(namespace.as_ref().unwrap_or(&DEFAULT_NAMESPACE)),
+        ],
+        bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// Required unless `config.require_nullifier_uniqueness` is off
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [b"nullifier", pending_verification.public_inputs[2].as_ref()],
+        bump,
+    )]
+    pub nullifier_record: Option<Box<Account<'info, NullifierRecord>>>,
+
+    /// Required only when `config.min_wallet_age_slots` is set
+    #[account(
+        seeds = [b"wallet_marker", user.key().as_ref()],
+        bump = wallet_marker.bump,
+    )]
+    pub wallet_marker: Option<Account<'info, WalletMarker>>,
+
+    /// Required only when `client_request_id` is passed
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RequestReceipt::INIT_SPACE,
+        seeds = [
+            b"request",
+            user.key().as_ref(),
+            client_request_id.unwrap_or_default().as_ref(),
+        ],
+        bump,
+    )]
+    pub request_receipt: Option<Account<'info, RequestReceipt>>,
+
+    /// CHECK: the instructions sysvar; pass it only with a prover
+    /// attestation in the preceding Ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: the identity's BadgeTombstone PDA, which must not exist.
+    /// Required only for first verifications when
+    /// `config.tombstone_blocks_recreate` is set
+    #[account(
+        seeds = [b"tombstone", pending_verification.public_inputs[2].as_ref()],
+        bump,
+    )]
+    pub badge_tombstone: Option<UncheckedAccount<'info>>,
+
+    /// The identity's reward claim, created on its first verification
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardClaim::INIT_SPACE,
+        seeds = [b"reward_claim", pending_verification.public_inputs[2].as_ref()],
+        bump,
+    )]
+    pub reward_claim: Option<Box<Account<'info, RewardClaim>>>,
+
+    pub shared: VerifyShared<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+    referrer: Pubkey,
+    referrer_namespace: Option<[u8; 8]>,
+)]
+pub struct VerifyWithReferral<'info> {
+    pub verify: VerifyAndStoreTier<'info>,
+
+    /// CHECK: the referrer's TierBadge PDA in `referrer_namespace`; decoded
+    /// and checked in the handler so a missing badge gets a clear error
+    #[account(
+        seeds = [
+            b"tier_badge",
+            referrer.as_ref(),
+            namespace_seed(referrer_namespace.as_ref().unwrap_or(&DEFAULT_NAMESPACE)),
+        ],
+        bump,
+    )]
+    pub referrer_badge: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = verify.user,
+        space = 8 + Referral::INIT_SPACE,
+        seeds = [b"referral", verify.user.key().as_ref()],
+        bump,
+    )]
+    pub referral: Account<'info, Referral>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+)]
+pub struct VerifyAndMint<'info> {
+    pub verify: VerifyAndStoreTier<'info>,
+
+    #[account(mut)]
+    pub mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: checked against the verified tier's mint authority PDA
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = verify.user,
+    )]
+    pub token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = verify.user,
+        space = 8 + MintReceipt::INIT_SPACE,
+        seeds = [
+            b"mint_receipt",
+            encoding::nullifier_input(layout_version, &public_inputs).as_ref(),
+            mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub mint_receipt: Account<'info, MintReceipt>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateStakePool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub authority: Signer<'info>,
+
+    #[account(token::authority = authority)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + StakePool::INIT_SPACE,
+        seeds = [b"stake_pool", vault.key().as_ref()],
+        bump,
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+)]
+pub struct VerifyAndStake<'info> {
+    pub verify: VerifyAndStoreTier<'info>,
+
+    #[account(mut, token::authority = verify.user)]
+    pub source: Box<Account<'info, TokenAccount>>,
+
+    #[account(seeds = [b"stake_pool", vault.key().as_ref()], bump = stake_pool.bump)]
+    pub stake_pool: Box<Account<'info, StakePool>>,
+
+    #[account(mut, token::mint = source.mint)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = verify.user,
+        space = 8 + StakeReceipt::INIT_SPACE,
+        seeds = [
+            b"stake_receipt",
+            verify.tier_badge.key().as_ref(),
+            vault.key().as_ref(),
+            &[stake_pool.min_tier],
+        ],
+        bump,
+    )]
+    pub stake_receipt: Account<'info, StakeReceipt>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    client_request_id: Option<[u8; 16]>,
+    account_type: AccountType,
+    namespace: Option<[u8; 8]>,
+    layout_version: u8,
+)]
+pub struct VerifyAndSetVotingPower<'info> {
+    pub verify: VerifyAndStoreTier<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = verify.user,
+        space = 8 + VotingPower::INIT_SPACE,
+        seeds = [
+            b"voting_power",
+            encoding::nullifier_input(layout_version, &public_inputs).as_ref(),
+        ],
+        bump,
+    )]
+    pub voting_power: Box<Account<'info, VotingPower>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateDaoGate<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub dao: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DaoGate::INIT_SPACE,
+        seeds = [b"dao_gate", dao.key().as_ref()],
+        bump,
+    )]
+    pub dao_gate: Account<'info, DaoGate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAndJoin<'info> {
+    pub verify: VerifyAndStoreTier<'info>,
+
+    #[account(mut, seeds = [b"dao_gate", dao_gate.dao.as_ref()], bump = dao_gate.bump)]
+    pub dao_gate: Box<Account<'info, DaoGate>>,
+
+    /// Created on the first join; an existing membership fails the call
+    /// with `AlreadyMember`
+    #[account(
+        init_if_needed,
+        payer = verify.user,
+        space = 8 + Membership::INIT_SPACE,
+        seeds = [b"membership", dao_gate.dao.as_ref(), verify.user.key().as_ref()],
+        bump,
+    )]
+    pub membership: Box<Account<'info, Membership>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+)]
+pub struct VerifyRange<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RangeBadge::INIT_SPACE,
+        seeds = [b"range_badge", user.key().as_ref()],
+        bump,
+    )]
+    pub range_badge: Account<'info, RangeBadge>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [b"nullifier", public_inputs[2].as_ref()],
+        bump,
+    )]
+    pub nullifier_record: Box<Account<'info, NullifierRecord>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeExpiredTier<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut @ ProofOfLoveError::BadgeNotWritable,
+        close = user,
+        seeds = [
+            b"tier_badge",
+            tier_badge.owner.as_ref(),
+            namespace_seed(&tier_badge.namespace),
+        ],
+        bump = tier_badge.bump,
+        constraint = tier_badge.owner == user.key(),
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// ExpiryBucket listing the badge, to remove it from
+    #[account(
+        mut,
+        seeds = [b"expiry_bucket".as_ref(), &expiry_bucket.day.to_le_bytes()],
+        bump = expiry_bucket.bump,
+    )]
+    pub expiry_bucket: Option<Box<Account<'info, ExpiryBucket>>>,
+
+    /// CHECK: the identity's VotingPower PDA, zeroed if this badge set it;
+    /// it need not exist
+    #[account(mut, seeds = [b"voting_power", tier_badge.nullifier.as_ref()], bump)]
+    pub voting_power: UncheckedAccount<'info>,
+
+    /// CHECK: the badge's NullifierRecord, which stops counting the badge if
+    /// it was registered there; it need not exist
+    #[account(mut, seeds = [b"nullifier", tier_badge.nullifier.as_ref()], bump)]
+    pub nullifier_record: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BurnBadge<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut @ ProofOfLoveError::BadgeNotWritable,
+        close = user,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(&tier_badge.namespace),
+        ],
+        bump = tier_badge.bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BadgeTombstone::INIT_SPACE,
+        seeds = [b"tombstone", tier_badge.nullifier.as_ref()],
+        bump,
+    )]
+    pub badge_tombstone: Account<'info, BadgeTombstone>,
+
+    /// ExpiryBucket listing the badge, to remove it from
+    #[account(
+        mut,
+        seeds = [b"expiry_bucket".as_ref(), &expiry_bucket.day.to_le_bytes()],
+        bump = expiry_bucket.bump,
+    )]
+    pub expiry_bucket: Option<Box<Account<'info, ExpiryBucket>>>,
+
+    /// CHECK: the identity's VotingPower PDA, zeroed if this badge set it;
+    /// it need not exist
+    #[account(mut, seeds = [b"voting_power", tier_badge.nullifier.as_ref()], bump)]
+    pub voting_power: UncheckedAccount<'info>,
+
+    /// CHECK: the badge's NullifierRecord, which stops counting the badge if
+    /// it was registered there; it need not exist
+    #[account(mut, seeds = [b"nullifier", tier_badge.nullifier.as_ref()], bump)]
+    pub nullifier_record: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DowngradeBadge<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut @ ProofOfLoveError::BadgeNotWritable,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(&tier_badge.namespace),
+        ],
+        bump = tier_badge.bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// CHECK: the identity's VotingPower PDA, recomputed if this badge set
+    /// it; it need not exist
+    #[account(mut, seeds = [b"voting_power", tier_badge.nullifier.as_ref()], bump)]
+    pub voting_power: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLabel<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut @ ProofOfLoveError::BadgeNotWritable,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(&tier_badge.namespace),
+        ],
+        bump = tier_badge.bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::ProofOfLove>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ ProofOfLoveError::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub pauser: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = pauser @ ProofOfLoveError::NotPauser,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct RequireTrustedBadge<'info> {
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// The badge nullifier's registry record, to cross-check the badge
+    /// against
+    #[account(
+        seeds = [b"nullifier", nullifier_record.nullifier.as_ref()],
+        bump = nullifier_record.bump,
+    )]
+    pub nullifier_record: Option<Account<'info, NullifierRecord>>,
+}
+
+#[derive(Accounts)]
+pub struct BadgeCommitment<'info> {
+    pub tier_badge: Account<'info, TierBadge>,
+}
+
+#[derive(Accounts)]
+pub struct RequireCurrentEpoch<'info> {
+    pub tier_badge: Account<'info, TierBadge>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// The badge nullifier's registry record, to cross-check the badge
+    /// against
+    #[account(
+        seeds = [b"nullifier", nullifier_record.nullifier.as_ref()],
+        bump = nullifier_record.bump,
+    )]
+    pub nullifier_record: Option<Account<'info, NullifierRecord>>,
+}
+
+#[derive(Accounts)]
+pub struct GateCheck<'info> {
+    pub tier_badge: Account<'info, TierBadge>,
+
+    /// The badge nullifier's registry record, to cross-check the badge
+    /// against
+    #[account(
+        seeds = [b"nullifier", nullifier_record.nullifier.as_ref()],
+        bump = nullifier_record.bump,
+    )]
+    pub nullifier_record: Option<Account<'info, NullifierRecord>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[cfg(feature = "test-vk")]
+#[derive(Accounts)]
+pub struct VerifyWithTestKey<'info> {
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetConfig<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+#[instruction(old_nullifier: [u8; 32])]
+pub struct AttestIdentityRotation<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + IdentityRotation::INIT_SPACE,
+        seeds = [b"identity_rotation", old_nullifier.as_ref()],
+        bump,
+    )]
+    pub identity_rotation: Account<'info, IdentityRotation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BackfillNullifier<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        mut @ ProofOfLoveError::BadgeNotWritable,
+        seeds = [
+            b"tier_badge",
+            tier_badge.owner.as_ref(),
+            namespace_seed(&tier_badge.namespace),
+        ],
+        bump = tier_badge.bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [b"nullifier", tier_badge.nullifier.as_ref()],
+        bump,
+    )]
+    pub nullifier_record: Box<Account<'info, NullifierRecord>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateBadge<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: a TierBadge in any layout, which can't be loaded as one until
+    /// migrated; the handler checks its discriminator and length
+    #[account(mut, owner = crate::ID)]
+    pub tier_badge: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepOrphanedNullifiers<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct FundTreasury<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury"],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewardVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + RewardVault::INIT_SPACE,
+        seeds = [b"reward_vault"],
+        bump,
+    )]
+    pub reward_vault: Account<'info, RewardVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: [u8; 8])]
+pub struct CreateCoupon<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Coupon::INIT_SPACE,
+        seeds = [b"coupon", id.as_ref()],
+        bump,
+    )]
+    pub coupon: Account<'info, Coupon>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemCoupon<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut @ ProofOfLoveError::BadgeNotWritable,
+        seeds = [
+            b"tier_badge",
+            user.key().as_ref(),
+            namespace_seed(&tier_badge.namespace),
+        ],
+        bump = tier_badge.bump,
+    )]
+    pub tier_badge: Account<'info, TierBadge>,
+
+    #[account(mut, seeds = [b"coupon", coupon.id.as_ref()], bump = coupon.bump)]
+    pub coupon: Account<'info, Coupon>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// ExpiryBucket for the day the extended badge will expire, to list it
+    /// there
+    #[account(
+        mut,
+        seeds = [b"expiry_bucket".as_ref(), &expiry_bucket.day.to_le_bytes()],
+        bump = expiry_bucket.bump,
+    )]
+    pub expiry_bucket: Option<Box<Account<'info, ExpiryBucket>>>,
+
+    /// ExpiryBucket for the day the badge expired on before, required when
+    /// the badge is listed there and the extension moves it to another day
+    #[account(
+        mut,
+        seeds = [b"expiry_bucket".as_ref(), &previous_expiry_bucket.day.to_le_bytes()],
+        bump = previous_expiry_bucket.bump,
+    )]
+    pub previous_expiry_bucket: Option<Box<Account<'info, ExpiryBucket>>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProofOfLoveError::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    /// Pass exactly one of `treasury` and `reward_vault`
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Option<Box<Account<'info, Treasury>>>,
+
+    #[account(mut, seeds = [b"reward_vault"], bump = reward_vault.bump)]
+    pub reward_vault: Option<Box<Account<'info, RewardVault>>>,
+
+    /// CHECK: only receives lamports; must be the authority or
+    /// `config.withdrawal_recipient`
+    #[account(
+        mut,
+        constraint = config.may_receive_withdrawals(&recipient.key())
+            @ ProofOfLoveError::Unauthorized,
+    )]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(day: i64)]
+pub struct CreateExpiryBucket<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ExpiryBucket::INIT_SPACE,
+        seeds = [b"expiry_bucket".as_ref(), &day.to_le_bytes()],
+        bump,
+    )]
+    pub expiry_bucket: Account<'info, ExpiryBucket>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateVerificationLog<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + VerificationLog::INIT_SPACE,
+        seeds = [b"verification_log", owner.key().as_ref()],
+        bump,
+    )]
+    pub verification_log: Box<Account<'info, VerificationLog>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ListExpiringBadges<'info> {
+    #[account(
+        seeds = [b"expiry_bucket".as_ref(), &expiry_bucket.day.to_le_bytes()],
+        bump = expiry_bucket.bump,
+    )]
+    pub expiry_bucket: Account<'info, ExpiryBucket>,
+}
+
+#[derive(Accounts)]
+pub struct CreateWalletMarker<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + WalletMarker::INIT_SPACE,
+        seeds = [b"wallet_marker", user.key().as_ref()],
+        bump,
+    )]
+    pub wallet_marker: Account<'info, WalletMarker>,
+
+    pub system_program: Program<'info, System>,
+}
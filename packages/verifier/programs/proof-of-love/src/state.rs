@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::verifying_key::NR_PUBLIC_INPUTS;
 
 /// PDA that stores a user's verified wealth tier.
 /// Seeds: [b"tier_badge", user_pubkey]
@@ -11,10 +14,10 @@ pub struct TierBadge {
     /// Verified tier (1-7: Seed, Sprout, Tree, Mountain, Ocean, Moon, Sun)
     pub tier: u8,
 
-    /// Lower bound of the tier range in USD cents
+    /// Lower bound of the tier range in cents of `currency_code`
     pub tier_lower_bound: u64,
 
-    /// Upper bound of the tier range in USD cents
+    /// Upper bound of the tier range in cents of `currency_code`
     pub tier_upper_bound: u64,
 
     /// Poseidon nullifier hash — prevents multi-account abuse
@@ -28,4 +31,2368 @@ pub struct TierBadge {
 
     /// Bump seed for PDA derivation
     pub bump: u8,
-}
\ No newline at end of file
+    /// Whether the config's prover key signed this proof's public signals
+    pub prover_attested: bool,
+    /// Kind of holder the badge was verified for
+    pub account_type: AccountType,
+    /// Unix timestamp from which consumers should fully trust the badge:
+    /// creation time plus the config's probation window. Refreshes keep it.
+    pub trusted_after: i64,
+    /// App namespace the badge was verified under; all zeros for the
+    /// wallet's default badge
+    pub namespace: [u8; 8],
+    /// Relayer that co-signed the badge's creation, or the default key if
+    /// the owner submitted it alone
+    pub origin_relayer: Pubkey,
+    /// Start of the current refresh-limit window
+    pub refresh_window_start: i64,
+    /// Refreshes counted in the window starting at `refresh_window_start`
+    pub refresh_count: u32,
+    /// Unix timestamp, by the on-chain clock, when the badge was created
+    pub created_at: i64,
+    /// Highest tier the badge has ever been verified at. Never decreases,
+    /// even when a later proof or a downgrade lowers `tier`
+    pub tier_high_water: u8,
+    /// ISO 4217 code of the currency the bounds are denominated in, taken
+    /// from the config at verification
+    pub currency_code: [u8; 3],
+    /// How the owner authorized the latest verification, see
+    /// `attestation::SIG_SCHEME_ED25519` and `SIG_SCHEME_SECP256K1`
+    pub sig_scheme: u8,
+    /// Display label chosen by the owner with `set_label`: UTF-8, padded
+    /// with zeros. All zeros when no label is set. Kept across refreshes
+    pub label: [u8; MAX_LABEL_LEN],
+    /// Id of the verifying key that accepted the latest proof, see
+    /// `Config.vk_windows`
+    pub vk_id: u8,
+    /// `PROGRAM_VERSION` of the release that created the badge. Kept across
+    /// refreshes, like `created_at`
+    pub program_version: u16,
+    /// `Config.verification_epoch` at the latest verification
+    pub verification_epoch: u64,
+    /// Whether the nullifier is registered to the owner in the nullifier
+    /// registry. Set by any verification that passes the NullifierRecord;
+    /// false on legacy badges created before enforcement, until
+    /// `backfill_nullifier` runs, and on badges verified without a record
+    /// while `Config.require_nullifier_uniqueness` is off
+    pub nullifier_enforced: bool,
+    /// Whether the badge is listed in the ExpiryBucket for the day of
+    /// `expires_at`, which must then be passed wherever that listing would
+    /// go stale
+    pub expiry_listed: bool,
+}
+
+/// Length of a TierBadge in the first release's layout, discriminator
+/// included: the fields up to `bump`. Later fields were only ever appended,
+/// so every older layout is a prefix of the current one and
+/// `migrate_badge` can grow it in place.
+pub const LEGACY_TIER_BADGE_LEN: usize = 8 + 98;
+
+/// Namespace of a wallet's default badge, whose address predates namespaces
+pub const DEFAULT_NAMESPACE: [u8; 8] = [0; 8];
+
+/// The badge PDA seed for `namespace`. The default namespace contributes no
+/// seed bytes, so those badges keep their original
+/// `[b"tier_badge", owner]` address.
+pub fn namespace_seed(namespace: &[u8; 8]) -> &[u8] {
+    if *namespace == DEFAULT_NAMESPACE {
+        &[]
+    } else {
+        namespace
+    }
+}
+
+/// `GateResult::reason` when the badge passed
+pub const GATE_PASSED: u8 = 0;
+/// The badge's tier is below the gate's `min_tier`
+pub const GATE_TIER_TOO_LOW: u8 = 1;
+/// The badge has expired
+pub const GATE_EXPIRED: u8 = 2;
+/// The gate requires trusted badges and this one is still in probation
+pub const GATE_IN_PROBATION: u8 = 3;
+/// The badge expires before the gate's remaining-validity horizon
+pub const GATE_EXPIRES_TOO_SOON: u8 = 4;
+
+/// Outcome of `gate_check`: whether the badge passed, and if not, the
+/// first `GATE_*` condition it failed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GateResult {
+    pub passed: bool,
+    pub reason: u8,
+}
+
+/// Outcome of a revocation (via return data): the rent lamports returned
+/// to the caller and the number of badges closed, so cleanup bots can
+/// account for each call without diffing balances
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RevocationReport {
+    pub lamports_reclaimed: u64,
+    pub badges_closed: u32,
+}
+
+impl TierBadge {
+    /// Set the fields a badge grown from an older layout read as zeros to
+    /// what the verification that created it would have written:
+    /// `tier_high_water` starts at the current tier, and the bounds are in
+    /// `DEFAULT_CURRENCY_CODE`. Other appended fields are correct as zeros.
+    pub fn fill_migrated_defaults(&mut self) {
+        self.tier_high_water = self.tier_high_water.max(self.tier);
+        if self.currency_code == [0; 3] {
+            self.currency_code = DEFAULT_CURRENCY_CODE;
+        }
+    }
+
+    /// Check the badge at `now` against a composite gate: at least
+    /// `min_tier`, unexpired, out of probation when `require_trusted`, and
+    /// valid for at least `min_remaining_seconds` more. Conditions are
+    /// checked in that order and the first failure is reported.
+    ///
+    /// With `use_high_water`, the tier check uses the badge's high-water
+    /// mark instead of its current tier.
+    pub fn gate(
+        &self,
+        now: i64,
+        min_tier: u8,
+        use_high_water: bool,
+        require_trusted: bool,
+        min_remaining_seconds: i64,
+    ) -> GateResult {
+        let tier = if use_high_water {
+            self.tier_high_water
+        } else {
+            self.tier
+        };
+        let reason = if tier < min_tier {
+            GATE_TIER_TOO_LOW
+        } else if now > self.expires_at {
+            GATE_EXPIRED
+        } else if require_trusted && now < self.trusted_after {
+            GATE_IN_PROBATION
+        } else if self.expires_at - now < min_remaining_seconds {
+            GATE_EXPIRES_TOO_SOON
+        } else {
+            GATE_PASSED
+        };
+        GateResult {
+            passed: reason == GATE_PASSED,
+            reason,
+        }
+    }
+
+    /// Record a verified `tier`, raising the high-water mark if it's a new
+    /// high.
+    pub fn record_tier(&mut self, tier: u8) {
+        self.tier = tier;
+        self.tier_high_water = self.tier_high_water.max(tier);
+    }
+
+    /// Gate for consumers that want to skip badges still in probation.
+    pub fn require_trusted(&self, now: i64) -> Result<()> {
+        require!(
+            now >= self.trusted_after,
+            crate::errors::ProofOfLoveError::BadgeInProbation
+        );
+        Ok(())
+    }
+
+    /// Gate for consumers that only trust badges created by release
+    /// `min_version` or later.
+    pub fn require_program_version(&self, min_version: u16) -> Result<()> {
+        require!(
+            self.program_version >= min_version,
+            crate::errors::ProofOfLoveError::ProgramVersionTooOld
+        );
+        Ok(())
+    }
+
+    /// Gate for consumers that honor re-verification campaigns: under a
+    /// config in strict epoch mode, badges last verified before the
+    /// current epoch are rejected.
+    pub fn require_current_epoch(&self, config: &Config) -> Result<()> {
+        require!(
+            !config.strict_epoch || self.verification_epoch >= config.verification_epoch,
+            crate::errors::ProofOfLoveError::BadgeEpochExpired
+        );
+        Ok(())
+    }
+
+    /// Gate for consumers that don't accept badges whose nullifier was
+    /// never registered, see `nullifier_enforced`.
+    pub fn require_nullifier_enforced(&self) -> Result<()> {
+        require!(
+            self.nullifier_enforced,
+            crate::errors::ProofOfLoveError::NullifierNotEnforced
+        );
+        Ok(())
+    }
+
+    /// Defense-in-depth check that the badge and its nullifier's registry
+    /// record agree on the nullifier and its owner.
+    pub fn require_in_sync(&self, record: &NullifierRecord) -> Result<()> {
+        require!(
+            record.nullifier == self.nullifier && record.owner == self.owner,
+            crate::errors::ProofOfLoveError::NullifierDesync
+        );
+        Ok(())
+    }
+
+    /// Sanity check that the clock hasn't gone back past the badge's
+    /// creation, which absent a bug can't happen.
+    pub fn require_clock_after_creation(&self, now: i64) -> Result<()> {
+        require!(
+            now >= self.created_at,
+            crate::errors::ProofOfLoveError::ClockRegressed
+        );
+        Ok(())
+    }
+
+    /// Count a refresh at `now` against the limit of `max` per
+    /// `window_seconds`, starting a fresh window once the current one has
+    /// passed. Returns false, counting nothing, if the limit is reached.
+    pub fn record_refresh(&mut self, now: i64, window_seconds: i64, max: u32) -> bool {
+        if now >= self.refresh_window_start.saturating_add(window_seconds) {
+            self.refresh_window_start = now;
+            self.refresh_count = 0;
+        }
+        if self.refresh_count >= max {
+            return false;
+        }
+        self.refresh_count += 1;
+        true
+    }
+}
+
+/// Permanent record that an identity's badge was deliberately burned. Never
+/// closed, so it outlives the badge as an audit trail.
+/// Seeds: [b"tombstone", nullifier]
+#[account]
+#[derive(InitSpace)]
+pub struct BadgeTombstone {
+    /// The wallet that owned the burned badge
+    pub owner: Pubkey,
+
+    /// Tier the badge held when it was burned
+    pub tier: u8,
+
+    /// Unix timestamp of the burn
+    pub burned_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// A raw proven wealth range, for consumers that gate on finer bounds than
+/// the tier table. Written by `verify_range`.
+/// Seeds: [b"range_badge", owner]
+#[account]
+#[derive(InitSpace)]
+pub struct RangeBadge {
+    /// The wallet that owns this range badge
+    pub owner: Pubkey,
+
+    /// Proven lower bound in USD cents (inclusive)
+    pub lower_bound: u64,
+
+    /// Proven upper bound in USD cents (exclusive)
+    pub upper_bound: u64,
+
+    /// Poseidon nullifier hash, shared with the wallet's tier badge
+    pub nullifier: [u8; 32],
+
+    /// Unix timestamp when the proof was generated
+    pub verified_at: i64,
+
+    /// Unix timestamp when this badge expires
+    pub expires_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// Largest upper bound `verify_range` accepts: the circuit's MAX_BALANCE of
+/// 10^16 cents, far above any real balance and well inside the 64-bit
+/// comparators
+pub const MAX_RANGE_UPPER_BOUND: u64 = 10_000_000_000_000_000;
+
+/// Whether `[lower, upper)` is a range `verify_range` will store: non-empty
+/// and no wider than `MAX_RANGE_UPPER_BOUND`.
+pub fn range_is_valid(lower: u64, upper: u64) -> bool {
+    lower < upper && upper <= MAX_RANGE_UPPER_BOUND
+}
+
+/// Kind of holder a badge represents, supplied at verification time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum AccountType {
+    Individual,
+    Institution,
+    Contract,
+}
+
+impl AccountType {
+    /// All account types, for building masks
+    pub const ALL: [AccountType; 3] = [
+        AccountType::Individual,
+        AccountType::Institution,
+        AccountType::Contract,
+    ];
+
+    /// This type's bit in `Config::allowed_account_types`
+    pub const fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Registry entry binding a nullifier to the one wallet allowed to use it.
+/// Seeds: [b"nullifier", nullifier]
+#[account]
+#[derive(InitSpace)]
+pub struct NullifierRecord {
+    /// The Poseidon nullifier this record reserves
+    pub nullifier: [u8; 32],
+
+    /// The wallet that claimed the nullifier
+    pub owner: Pubkey,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+
+    /// The wallet that paid the record's rent, refunded when it is swept
+    pub payer: Pubkey,
+
+    /// Badges registered against this record that are still open: TierBadges
+    /// in any namespace with `nullifier_enforced` set, and the owner's
+    /// RangeBadge. The record is orphaned once this drops to zero
+    pub badges: u32,
+}
+
+impl NullifierRecord {
+    /// Count a badge newly registered against this record.
+    pub fn register_badge(&mut self) {
+        self.badges = self.badges.saturating_add(1);
+    }
+
+    /// Stop counting a badge that was closed or moved to another nullifier.
+    /// Returns whether the record is now orphaned.
+    pub fn release_badge(&mut self) -> bool {
+        self.badges = self.badges.saturating_sub(1);
+        self.badges == 0
+    }
+}
+
+/// Proof that an identity has already minted a given tier token. Keyed by
+/// nullifier rather than badge, so namespaced or recreated badges can't
+/// mint it again.
+/// Seeds: [b"mint_receipt", nullifier, mint]
+#[account]
+#[derive(InitSpace)]
+pub struct MintReceipt {
+    /// The badge the token was minted against
+    pub badge: Pubkey,
+
+    /// The tier token mint
+    pub mint: Pubkey,
+
+    /// Tier of the badge at mint time
+    pub tier: u8,
+
+    /// Unix timestamp of the mint
+    pub minted_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// A vault's admission rule for `verify_and_stake`, set by the vault's
+/// token authority. Seeds: [b"stake_pool", vault]
+#[account]
+#[derive(InitSpace)]
+pub struct StakePool {
+    /// Token account stakes are transferred into
+    pub vault: Pubkey,
+
+    /// Lowest tier a staking wallet may verify at
+    pub min_tier: u8,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// Running total a badge has staked into a vault through `verify_and_stake`,
+/// under the minimum tier the vault's StakePool set at the time.
+/// Seeds: [b"stake_receipt", tier_badge, vault, min_tier]
+#[account]
+#[derive(InitSpace)]
+pub struct StakeReceipt {
+    /// The badge the stake was verified against
+    pub badge: Pubkey,
+
+    /// Token account the stake was transferred into
+    pub vault: Pubkey,
+
+    /// Tier the badge had to meet for the stake
+    pub min_tier: u8,
+
+    /// Total amount staked, in the vault mint's base units
+    pub amount: u64,
+
+    /// Unix timestamp of the latest stake
+    pub staked_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// One-time, immutable link from a badge owner to the wallet that referred
+/// them. Seeds: [b"referral", referee]
+#[account]
+#[derive(InitSpace)]
+pub struct Referral {
+    /// The newly verified wallet
+    pub referee: Pubkey,
+
+    /// The wallet credited with the referral
+    pub referrer: Pubkey,
+
+    /// Unix timestamp of the registration
+    pub registered_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// The authority's attestation that `owner` legitimately rotated their
+/// identity commitment, so a refresh of their badge may move from
+/// `old_nullifier` to `new_nullifier`. Never closed, as an audit trail.
+/// Seeds: [b"identity_rotation", old_nullifier]
+#[account]
+#[derive(InitSpace)]
+pub struct IdentityRotation {
+    /// The nullifier being retired
+    pub old_nullifier: [u8; 32],
+
+    /// The nullifier replacing it
+    pub new_nullifier: [u8; 32],
+
+    /// The wallet whose badge may rotate
+    pub owner: Pubkey,
+
+    /// Unix timestamp of the attestation
+    pub attested_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl IdentityRotation {
+    /// Whether this attestation lets `owner`'s badge move from `old` to
+    /// `new`.
+    pub fn allows(&self, owner: &Pubkey, old: &[u8; 32], new: &[u8; 32]) -> bool {
+        self.owner == *owner && self.old_nullifier == *old && self.new_nullifier == *new
+    }
+}
+
+/// A DAO's admission rule for `verify_and_join`, set by the DAO's own key.
+/// Seeds: [b"dao_gate", dao]
+#[account]
+#[derive(InitSpace)]
+pub struct DaoGate {
+    /// The DAO's key: a wallet, or a PDA of the DAO's program
+    pub dao: Pubkey,
+
+    /// Lowest tier a joining wallet may verify at
+    pub min_tier: u8,
+
+    /// Members admitted so far
+    pub member_count: u64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// A wallet's membership of a gated DAO, recorded once by `verify_and_join`.
+/// Seeds: [b"membership", dao, member]
+#[account]
+#[derive(InitSpace)]
+pub struct Membership {
+    /// The DAO joined
+    pub dao: Pubkey,
+
+    /// The wallet that joined
+    pub member: Pubkey,
+
+    /// Tier the wallet verified at when it joined
+    pub tier: u8,
+
+    /// Unix timestamp of the join
+    pub joined_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// An identity's governance weight, written by `verify_and_set_voting_power`
+/// from `Config.tier_weights`. Keyed by nullifier, so an identity holds one
+/// weight however many namespaces it has badges in; the weight follows the
+/// badge that last set it. Recomputed on every such verification and on a
+/// downgrade of that badge, and zeroed when it is revoked or burned.
+/// Seeds: [b"voting_power", nullifier]
+#[account]
+#[derive(InitSpace)]
+pub struct VotingPower {
+    /// The badge the weight derives from, the last to set it
+    pub badge: Pubkey,
+
+    /// The badge's owner, who casts the votes
+    pub owner: Pubkey,
+
+    /// Tier the weight was computed for; 0 once zeroed
+    pub tier: u8,
+
+    /// Votes granted at `tier`
+    pub weight: u64,
+
+    /// The badge's expiry when the weight was computed; the weight lapses
+    /// with it
+    pub expires_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl VotingPower {
+    /// The weight governance should count at `now`: zero once the badge
+    /// the weight was computed from has expired.
+    pub fn weight_at(&self, now: i64) -> u64 {
+        if now <= self.expires_at {
+            self.weight
+        } else {
+            0
+        }
+    }
+
+    /// Drop the weight, for a badge that no longer exists.
+    pub fn zero(&mut self) {
+        self.tier = 0;
+        self.weight = 0;
+    }
+}
+
+/// Lamports the authority sets aside to pay badge rent for tiers at or
+/// above `Config.subsidy_min_tier`. Seeds: [b"treasury"]
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    /// Epoch that `subsidized_lamports` counts against
+    pub epoch: u64,
+
+    /// Lamports paid out in subsidies during `epoch`
+    pub subsidized_lamports: u64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl Treasury {
+    /// Count `amount` against the per-epoch `cap`, starting a fresh count
+    /// when `epoch` has moved on. Returns false, counting nothing, if the
+    /// subsidy would exceed the cap.
+    pub fn record_subsidy(&mut self, epoch: u64, amount: u64, cap: u64) -> bool {
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.subsidized_lamports = 0;
+        }
+        match self.subsidized_lamports.checked_add(amount) {
+            Some(total) if total <= cap => {
+                self.subsidized_lamports = total;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// What is left of the per-epoch `cap` in `epoch`: subsidies the config
+    /// still promises before the epoch ends.
+    pub fn remaining_cap(&self, epoch: u64, cap: u64) -> u64 {
+        let paid = if epoch == self.epoch {
+            self.subsidized_lamports
+        } else {
+            0
+        };
+        cap.saturating_sub(paid)
+    }
+}
+
+/// Lamports the authority sets aside for first-verification rewards, see
+/// `Config.first_verify_reward_lamports`. Seeds: [b"reward_vault"]
+#[account]
+#[derive(InitSpace)]
+pub struct RewardVault {
+    /// Lamports paid out in rewards so far
+    pub paid_out_lamports: u64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// Records that an identity was paid its first-verification reward. Keyed
+/// by nullifier rather than badge, and never closed, so revoking, burning
+/// or sweeping and verifying again, or verifying under another namespace,
+/// doesn't pay twice. Seeds: [b"reward_claim", nullifier]
+#[account]
+#[derive(InitSpace)]
+pub struct RewardClaim {
+    /// The wallet the reward was paid to; the default key until paid
+    pub recipient: Pubkey,
+
+    /// Lamports paid
+    pub amount: u64,
+
+    /// Unix timestamp of the payout
+    pub claimed_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// A coupon issued by the authority that extends a badge's validity
+/// without a new proof. Redeemed with `redeem_coupon` until its uses run
+/// out or it expires. Seeds: [b"coupon", id]
+#[account]
+#[derive(InitSpace)]
+pub struct Coupon {
+    /// Authority-chosen id of the coupon
+    pub id: [u8; 8],
+
+    /// Seconds each redemption adds to a badge's `expires_at`
+    pub extension_seconds: i64,
+
+    /// Redemptions left
+    pub remaining_uses: u32,
+
+    /// Unix timestamp after which the coupon can't be redeemed
+    pub expires_at: i64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl Coupon {
+    /// Use the coupon once at `now` on a badge expiring at
+    /// `badge_expires_at`, returning the badge's new expiry.
+    pub fn redeem(&mut self, badge_expires_at: i64, now: i64) -> Result<i64> {
+        require!(
+            now <= self.expires_at,
+            crate::errors::ProofOfLoveError::CouponExpired
+        );
+        require!(
+            self.remaining_uses > 0,
+            crate::errors::ProofOfLoveError::CouponExhausted
+        );
+        self.remaining_uses -= 1;
+        Ok(badge_expires_at.saturating_add(self.extension_seconds))
+    }
+}
+
+/// Marks a `client_request_id` as processed so client retries are deduped.
+/// Seeds: [b"request", owner, client_request_id]
+#[account]
+#[derive(InitSpace)]
+pub struct RequestReceipt {
+    /// The wallet that submitted the request
+    pub owner: Pubkey,
+
+    /// Client-chosen id of the request
+    pub request_id: [u8; 16],
+
+    /// Slot at which the request was processed
+    pub slot: u64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// How long a `client_request_id` stays reserved — about a minute, roughly
+/// the lifetime of the blockhash a retried transaction would reuse
+pub const REQUEST_ID_WINDOW_SLOTS: u64 = 150;
+
+/// A proof verified by `verify_proof_stage1`, waiting for
+/// `verify_proof_stage2` to store the badge. Closed by stage 2.
+/// Seeds: [b"pending_verification", user]
+#[account]
+#[derive(InitSpace)]
+pub struct PendingVerification {
+    /// Verified public inputs, in canonical order
+    pub public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+
+    /// Id of the verifying key that accepted the proof
+    pub vk_id: u8,
+
+    /// Slot at which the proof was verified
+    pub slot: u64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl PendingVerification {
+    /// Whether stage 2 at `slot` may still use the record
+    pub fn is_fresh(&self, slot: u64) -> bool {
+        slot <= self.slot.saturating_add(PENDING_VERIFICATION_SLOTS)
+    }
+}
+
+/// How long a staged verification stays usable — a few seconds, enough for
+/// a client to land stage 2 right behind stage 1
+pub const PENDING_VERIFICATION_SLOTS: u64 = 16;
+
+/// Number of wealth tiers in the tier table
+pub const TIER_COUNT: usize = 7;
+
+/// 30 days in seconds
+pub const DEFAULT_BADGE_VALIDITY_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// 10 minutes in seconds — max age for a proof timestamp
+pub const DEFAULT_MAX_PROOF_AGE_SECONDS: i64 = 10 * 60;
+
+/// 365 days in seconds — default cap on the badge validity window
+pub const DEFAULT_MAX_VALIDITY_SECONDS: i64 = 365 * 24 * 60 * 60;
+
+/// 1 day in seconds — default length of the refresh-limit window
+pub const DEFAULT_REFRESH_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+/// The circuit's `MAX_BALANCE`, the ceiling top-tier proofs use — default
+/// tripwire for tier bounds in the wrong unit. Top-tier proofs are exempt,
+/// since that tier has no real ceiling.
+pub const DEFAULT_SANE_BOUNDS_MAX: u64 = MAX_RANGE_UPPER_BOUND;
+
+/// Launch voting weights, indexed by tier - 1: one vote per tier number
+pub const DEFAULT_TIER_WEIGHTS: [u64; TIER_COUNT] = [1, 2, 3, 4, 5, 6, 7];
+
+/// Currency of the launch tier table
+pub const DEFAULT_CURRENCY_CODE: [u8; 3] = *b"USD";
+
+/// Whether `code` looks like an ISO 4217 code: three ASCII uppercase
+/// letters. Whether the code is actually assigned isn't checked.
+pub fn is_valid_currency_code(code: &[u8; 3]) -> bool {
+    code.iter().all(u8::is_ascii_uppercase)
+}
+
+/// Longest badge label, in bytes
+pub const MAX_LABEL_LEN: usize = 32;
+
+/// Validate a label for `set_label` and pad it to the badge field. Labels
+/// must be UTF-8 of at most `MAX_LABEL_LEN` bytes with no control
+/// characters, NUL included, so the padding can't be mistaken for text.
+/// An empty label clears it.
+pub fn encode_label(label: &[u8]) -> Option<[u8; MAX_LABEL_LEN]> {
+    if label.len() > MAX_LABEL_LEN {
+        return None;
+    }
+    let text = core::str::from_utf8(label).ok()?;
+    if text.chars().any(char::is_control) {
+        return None;
+    }
+    let mut padded = [0u8; MAX_LABEL_LEN];
+    padded[..label.len()].copy_from_slice(label);
+    Some(padded)
+}
+
+/// Size of the config's deny-list of bundled programs
+pub const MAX_DENIED_PROGRAMS: usize = 4;
+
+/// Size of the config's deny-list of nullifier prefixes
+pub const MAX_DENIED_NULLIFIER_PREFIXES: usize = 4;
+
+/// Longest nullifier prefix the config can deny, in bytes
+pub const MAX_NULLIFIER_PREFIX_LEN: usize = 8;
+
+/// Most verifying keys the program can compile in: the current key, id 0,
+/// and previous keys kept for a migration, ids 1 and up
+pub const MAX_VERIFYING_KEYS: usize = 3;
+
+/// Proof timestamps a verifying key accepts, both ends inclusive. A window
+/// that ends before it starts accepts nothing.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct KeyWindow {
+    pub valid_from: i64,
+    pub valid_until: i64,
+}
+
+impl KeyWindow {
+    pub const ALWAYS: KeyWindow = KeyWindow {
+        valid_from: i64::MIN,
+        valid_until: i64::MAX,
+    };
+    pub const NEVER: KeyWindow = KeyWindow {
+        valid_from: i64::MAX,
+        valid_until: i64::MIN,
+    };
+
+    pub fn contains(&self, timestamp: i64) -> bool {
+        self.valid_from <= timestamp && timestamp <= self.valid_until
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.valid_from > self.valid_until
+    }
+
+    /// Whether every timestamp this window accepts, `other` accepts too
+    pub fn within(&self, other: &KeyWindow) -> bool {
+        self.is_empty()
+            || (other.valid_from <= self.valid_from && self.valid_until <= other.valid_until)
+    }
+}
+
+/// Launch windows: the current key accepts every proof, and the slots for
+/// previous keys accept none
+pub const DEFAULT_VK_WINDOWS: [KeyWindow; MAX_VERIFYING_KEYS] =
+    [KeyWindow::ALWAYS, KeyWindow::NEVER, KeyWindow::NEVER];
+
+/// The first `len` bytes of `bytes`, matched against the start of a
+/// nullifier. A zero `len` marks an unused slot and matches nothing.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct NullifierPrefix {
+    pub len: u8,
+    pub bytes: [u8; MAX_NULLIFIER_PREFIX_LEN],
+}
+
+impl NullifierPrefix {
+    pub fn matches(&self, nullifier: &[u8; 32]) -> bool {
+        let len = self.len as usize;
+        len != 0 && len <= MAX_NULLIFIER_PREFIX_LEN && nullifier.starts_with(&self.bytes[..len])
+    }
+}
+
+/// Whether a tier table ascends contiguously from zero, each tier starting
+/// where the previous one ends.
+pub fn tiers_contiguous(tiers: &[TierBounds; TIER_COUNT]) -> bool {
+    tiers[0].lower == 0
+        && tiers.iter().all(|t| t.lower < t.upper)
+        && tiers.windows(2).all(|w| w[0].upper == w[1].lower)
+}
+
+/// Bounds of a single tier in USD cents: [lower, upper). The top tier is
+/// unbounded: its `upper` is only the smallest ceiling a proof may use, so
+/// wealth anywhere above `lower` still classifies (see `classify_tier`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct TierBounds {
+    pub lower: u64,
+    pub upper: u64,
+}
+
+/// The launch tier table, indexed by tier - 1
+#[rustfmt::skip]
+pub const DEFAULT_TIERS: [TierBounds; TIER_COUNT] = [
+    TierBounds { lower: 0, upper: 100_000 },                      // Seed: < $1K
+    TierBounds { lower: 100_000, upper: 1_000_000 },              // Sprout: $1K - $10K
+    TierBounds { lower: 1_000_000, upper: 5_000_000 },            // Tree: $10K - $50K
+    TierBounds { lower: 5_000_000, upper: 25_000_000 },           // Mountain: $50K - $250K
+    TierBounds { lower: 25_000_000, upper: 100_000_000 },         // Ocean: $250K - $1M
+    TierBounds { lower: 100_000_000, upper: 500_000_000 },        // Moon: $1M - $5M
+    TierBounds { lower: 500_000_000, upper: 10_000_000_000_000 }, // Sun: $5M+
+];
+
+/// A wealth tier, for Rust integrators that would rather match on tiers
+/// than on raw numbers. Converts to and from the tier number (1-7) stored
+/// in `TierBadge::tier`.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum Tier {
+    Seed,
+    Sprout,
+    Tree,
+    Mountain,
+    Ocean,
+    Moon,
+    Sun,
+}
+
+impl Tier {
+    /// All tiers, lowest first
+    pub const ALL: [Tier; TIER_COUNT] = [
+        Tier::Seed,
+        Tier::Sprout,
+        Tier::Tree,
+        Tier::Mountain,
+        Tier::Ocean,
+        Tier::Moon,
+        Tier::Sun,
+    ];
+}
+
+impl TryFrom<u8> for Tier {
+    type Error = crate::errors::ProofOfLoveError;
+
+    /// Fails with `InvalidTier` outside 1-7.
+    fn try_from(tier: u8) -> std::result::Result<Self, Self::Error> {
+        tier.checked_sub(1)
+            .and_then(|i| Tier::ALL.get(i as usize).copied())
+            .ok_or(crate::errors::ProofOfLoveError::InvalidTier)
+    }
+}
+
+impl From<Tier> for u8 {
+    fn from(tier: Tier) -> u8 {
+        tier as u8 + 1
+    }
+}
+
+/// Length of a tier display name, zero-padded
+pub const TIER_NAME_LEN: usize = 16;
+
+/// Tier display names, indexed by tier - 1
+pub const TIER_NAMES: [[u8; TIER_NAME_LEN]; TIER_COUNT] = [
+    tier_name(b"Seed"),
+    tier_name(b"Sprout"),
+    tier_name(b"Tree"),
+    tier_name(b"Mountain"),
+    tier_name(b"Ocean"),
+    tier_name(b"Moon"),
+    tier_name(b"Sun"),
+];
+
+const fn tier_name(name: &[u8]) -> [u8; TIER_NAME_LEN] {
+    let mut padded = [0u8; TIER_NAME_LEN];
+    let mut i = 0;
+    while i < name.len() {
+        padded[i] = name[i];
+        i += 1;
+    }
+    padded
+}
+
+/// One rung of the tier ladder, as returned by `list_tiers`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TierDefinition {
+    /// The tier
+    pub tier: Tier,
+    /// UTF-8 display name, zero-padded
+    pub name_bytes: [u8; TIER_NAME_LEN],
+    /// Lower bound in USD cents (inclusive)
+    pub lower: u64,
+    /// Upper bound in USD cents (exclusive)
+    pub upper: u64,
+}
+
+/// Global program configuration, owned by `authority`.
+/// Seeds: [b"config"]
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    /// Key allowed to change the configuration
+    pub authority: Pubkey,
+
+    /// When true, no new badges can be verified
+    pub paused: bool,
+
+    /// How long a badge stays valid after its proof timestamp
+    pub badge_validity_seconds: i64,
+
+    /// Max age of a proof timestamp at submission
+    pub max_proof_age_seconds: i64,
+
+    /// Tier bounds, indexed by tier - 1
+    pub tiers: [TierBounds; TIER_COUNT],
+
+    /// Delay before a sensitive change queued by `update_config` can be applied
+    pub timelock_slots: u64,
+
+    /// Min age of the wallet's `WalletMarker` in slots (0 disables the gate)
+    pub min_wallet_age_slots: u64,
+
+    /// Slot at which the authority last recorded a verifying key rotation
+    pub vk_rotation_slot: u64,
+
+    /// Slots after `vk_rotation_slot` during which proofs are rejected
+    pub post_rotation_cooldown_slots: u64,
+
+    /// Set once by `freeze_tier_table`; the tier table can never change again
+    pub tier_table_frozen: bool,
+
+    /// Key whose Ed25519 signature marks a proof as prover-attested (default: none)
+    pub prover_key: Pubkey,
+
+    /// Upper bound on `badge_validity_seconds`, guarding against fat-fingered updates
+    pub max_validity_seconds: i64,
+
+    /// When set, `verify_and_store_tier` only refreshes badges and first
+    /// verifications must go through `create_badge`
+    pub require_explicit_create: bool,
+
+    /// Key allowed to pause or unpause through `set_paused`, and nothing else
+    pub pauser: Pubkey,
+
+    /// Bitmask of `AccountType`s badges may be verified as, see
+    /// `AccountType::mask`
+    pub allowed_account_types: u8,
+
+    /// Program called with `on_tier_verified` after every successful
+    /// verification (see `hook`), or the default key for none
+    pub hook_program: Pubkey,
+
+    /// How long a newly created badge stays in probation, see
+    /// `TierBadge::trusted_after`
+    pub probation_seconds: i64,
+
+    /// When set, verification fails if the transaction also calls a
+    /// program in `denied_programs`
+    pub restrict_bundles: bool,
+
+    /// Programs that may not appear alongside a verification when
+    /// `restrict_bundles` is set. Unused slots hold the default key
+    pub denied_programs: [Pubkey; MAX_DENIED_PROGRAMS],
+
+    /// Minimum lamports the verifying wallet must hold, or zero for no gate
+    pub min_wallet_lamports: u64,
+
+    /// Lowest tier whose badge rent the treasury pays (0 disables subsidies)
+    pub subsidy_min_tier: u8,
+
+    /// Most lamports the treasury pays out in subsidies per epoch
+    pub subsidy_epoch_cap_lamports: u64,
+
+    /// When set, a badge address with a `BadgeTombstone` can't be created
+    /// again
+    pub tombstone_blocks_recreate: bool,
+
+    /// When set, refreshes of a badge created through a relayer must be
+    /// co-signed by that same relayer
+    pub require_origin_relayer: bool,
+
+    /// Most refreshes a badge may have per `refresh_window_seconds`, or
+    /// zero for no limit
+    pub max_refreshes_per_window: u32,
+
+    /// Length of the window `max_refreshes_per_window` counts over
+    pub refresh_window_seconds: i64,
+
+    /// Nullifiers starting with any of these prefixes can't verify, to
+    /// block a class of nullifiers during an incident
+    pub denied_nullifier_prefixes: [NullifierPrefix; MAX_DENIED_NULLIFIER_PREFIXES],
+
+    /// When set, badges expire `badge_validity_seconds` after the
+    /// submission lands rather than after the proof's own timestamp
+    pub anchor_expiry_to_submission: bool,
+
+    /// ISO 4217 code of the currency the tier table is denominated in, in
+    /// cents (or the currency's minor unit)
+    pub currency_code: [u8; 3],
+
+    /// When set, a verification that passes the Memo program also writes
+    /// a `POL:T<tier>` memo, for compliance tooling that parses memos
+    pub emit_tier_memo: bool,
+
+    /// Lamports the RewardVault pays an identity on its first verification
+    /// (0 disables rewards)
+    pub first_verify_reward_lamports: u64,
+
+    /// Proof timestamps each verifying key accepts, indexed by key id: the
+    /// compiled `VERIFYING_KEY` and then `PREVIOUS_VERIFYING_KEYS`. Slots
+    /// past the compiled keys are ignored
+    pub vk_windows: [KeyWindow; MAX_VERIFYING_KEYS],
+
+    /// Wallet besides the authority that `withdraw_treasury` may pay; the
+    /// default key when there is none
+    pub withdrawal_recipient: Pubkey,
+
+    /// Highest tier verifications are accepted for, so a deployment can
+    /// serve a band of tiers. `TIER_COUNT` accepts every tier
+    pub max_accepted_tier: u8,
+
+    /// Current verification epoch. Every verification stamps it on the
+    /// badge; bumping it marks every earlier badge for re-verification
+    pub verification_epoch: u64,
+
+    /// Whether `require_current_epoch` rejects badges from earlier epochs
+    pub strict_epoch: bool,
+
+    /// Largest upper bound a proof may carry, in cents of `currency_code`.
+    /// Anything above it is taken for a unit mismatch (e.g. bounds scaled
+    /// for another currency's subunit) and rejected
+    pub sane_bounds_max: u64,
+
+    /// When set, a verification that passes the wallet's VerificationLog
+    /// also appends a leaf for it to the log
+    pub log_verifications: bool,
+
+    /// Voting weight `verify_and_set_voting_power` grants per tier, indexed
+    /// by tier - 1. Never decreasing, so a higher tier never votes less
+    pub tier_weights: [u64; TIER_COUNT],
+
+    /// When cleared, tier verifications may omit the NullifierRecord and
+    /// skip nullifier registration, for deployments that use badges for
+    /// personalization only. Badges record whether they were registered
+    /// in `nullifier_enforced`
+    pub require_nullifier_uniqueness: bool,
+
+    /// Sensitive change waiting for its timelock to elapse
+    pub pending_change: Option<PendingConfigChange>,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// Layout version of `ConfigSnapshot`
+pub const CONFIG_SNAPSHOT_VERSION: u8 = 28;
+
+/// The active config parameters returned by `get_config`.
+///
+/// Decoupled from the `Config` account layout so clients keep a stable
+/// interface. Field order never changes; new fields are appended and bump
+/// `version`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConfigSnapshot {
+    pub version: u8,
+    pub paused: bool,
+    pub badge_validity_seconds: i64,
+    pub max_proof_age_seconds: i64,
+    pub tiers: [TierBounds; TIER_COUNT],
+    pub timelock_slots: u64,
+    pub min_wallet_age_slots: u64,
+    pub vk_rotation_slot: u64,
+    pub post_rotation_cooldown_slots: u64,
+    /// Added in version 2
+    pub tier_table_frozen: bool,
+    /// Added in version 3
+    pub prover_key: Pubkey,
+    /// Added in version 4
+    pub max_validity_seconds: i64,
+    /// Added in version 5
+    pub require_explicit_create: bool,
+    /// Added in version 6
+    pub pauser: Pubkey,
+    /// Added in version 7
+    pub allowed_account_types: u8,
+    /// Added in version 8
+    pub hook_program: Pubkey,
+    /// Added in version 9
+    pub probation_seconds: i64,
+    /// Added in version 10
+    pub restrict_bundles: bool,
+    /// Added in version 10
+    pub denied_programs: [Pubkey; MAX_DENIED_PROGRAMS],
+    /// Added in version 11
+    pub min_wallet_lamports: u64,
+    /// Added in version 12
+    pub subsidy_min_tier: u8,
+    /// Added in version 12
+    pub subsidy_epoch_cap_lamports: u64,
+    /// Added in version 13
+    pub tombstone_blocks_recreate: bool,
+    /// Added in version 14
+    pub require_origin_relayer: bool,
+    /// Added in version 15
+    pub max_refreshes_per_window: u32,
+    /// Added in version 15
+    pub refresh_window_seconds: i64,
+    /// Added in version 16
+    pub anchor_expiry_to_submission: bool,
+    /// Added in version 17
+    pub denied_nullifier_prefixes: [NullifierPrefix; MAX_DENIED_NULLIFIER_PREFIXES],
+    /// Added in version 18
+    pub currency_code: [u8; 3],
+    /// Added in version 19
+    pub emit_tier_memo: bool,
+    /// Added in version 20
+    pub first_verify_reward_lamports: u64,
+    /// Added in version 21
+    pub vk_windows: [KeyWindow; MAX_VERIFYING_KEYS],
+    /// Added in version 22
+    pub withdrawal_recipient: Pubkey,
+    /// Added in version 23
+    pub max_accepted_tier: u8,
+    /// Added in version 24
+    pub verification_epoch: u64,
+    /// Added in version 24
+    pub strict_epoch: bool,
+    /// Added in version 25
+    pub sane_bounds_max: u64,
+    /// Added in version 26
+    pub log_verifications: bool,
+    /// Added in version 27
+    pub tier_weights: [u64; TIER_COUNT],
+    /// Added in version 28
+    pub require_nullifier_uniqueness: bool,
+}
+
+/// Fields accepted by `update_config`. `None` leaves a field unchanged.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct ConfigUpdate {
+    pub paused: Option<bool>,
+    pub badge_validity_seconds: Option<i64>,
+    pub max_proof_age_seconds: Option<i64>,
+    pub tiers: Option<[TierBounds; TIER_COUNT]>,
+    pub timelock_slots: Option<u64>,
+    pub min_wallet_age_slots: Option<u64>,
+    pub post_rotation_cooldown_slots: Option<u64>,
+    pub prover_key: Option<Pubkey>,
+    pub max_validity_seconds: Option<i64>,
+    pub require_explicit_create: Option<bool>,
+    pub pauser: Option<Pubkey>,
+    pub allowed_account_types: Option<u8>,
+    pub hook_program: Option<Pubkey>,
+    pub probation_seconds: Option<i64>,
+    pub restrict_bundles: Option<bool>,
+    pub denied_programs: Option<[Pubkey; MAX_DENIED_PROGRAMS]>,
+    pub min_wallet_lamports: Option<u64>,
+    pub subsidy_min_tier: Option<u8>,
+    pub subsidy_epoch_cap_lamports: Option<u64>,
+    pub tombstone_blocks_recreate: Option<bool>,
+    pub require_origin_relayer: Option<bool>,
+    pub max_refreshes_per_window: Option<u32>,
+    pub refresh_window_seconds: Option<i64>,
+    pub anchor_expiry_to_submission: Option<bool>,
+    pub denied_nullifier_prefixes: Option<[NullifierPrefix; MAX_DENIED_NULLIFIER_PREFIXES]>,
+    pub currency_code: Option<[u8; 3]>,
+    pub emit_tier_memo: Option<bool>,
+    pub first_verify_reward_lamports: Option<u64>,
+    pub vk_windows: Option<[KeyWindow; MAX_VERIFYING_KEYS]>,
+    pub withdrawal_recipient: Option<Pubkey>,
+    pub max_accepted_tier: Option<u8>,
+    pub verification_epoch: Option<u64>,
+    pub strict_epoch: Option<bool>,
+    pub sane_bounds_max: Option<u64>,
+    pub log_verifications: Option<bool>,
+    pub tier_weights: Option<[u64; TIER_COUNT]>,
+    pub require_nullifier_uniqueness: Option<bool>,
+}
+
+impl ConfigUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == ConfigUpdate::default()
+    }
+
+    /// Durations must be positive (probation non-negative), the account
+    /// type mask non-empty and known, the hook not this program, the
+    /// currency code three uppercase letters, and the tier table ascending
+    /// and contiguous from zero.
+    pub fn is_valid(&self) -> bool {
+        let positive = |v: Option<i64>| !matches!(v, Some(v) if v <= 0);
+        if !positive(self.badge_validity_seconds)
+            || !positive(self.max_proof_age_seconds)
+            || !positive(self.max_validity_seconds)
+            || !positive(self.refresh_window_seconds)
+            || matches!(self.probation_seconds, Some(seconds) if seconds < 0)
+        {
+            return false;
+        }
+        let known = AccountType::ALL.iter().fold(0, |m, t| m | t.mask());
+        if matches!(self.allowed_account_types, Some(mask) if mask == 0 || mask & !known != 0) {
+            return false;
+        }
+        // A self-hook would be the one reentrant CPI the runtime allows
+        if self.hook_program == Some(crate::ID) {
+            return false;
+        }
+        if matches!(self.subsidy_min_tier, Some(tier) if tier as usize > TIER_COUNT) {
+            return false;
+        }
+        if matches!(self.max_accepted_tier, Some(tier) if tier == 0 || tier as usize > TIER_COUNT) {
+            return false;
+        }
+        if self.sane_bounds_max == Some(0) {
+            return false;
+        }
+        if matches!(self.tier_weights, Some(weights) if weights.windows(2).any(|w| w[0] > w[1])) {
+            return false;
+        }
+        if matches!(self.denied_nullifier_prefixes, Some(prefixes)
+            if prefixes.iter().any(|p| p.len as usize > MAX_NULLIFIER_PREFIX_LEN))
+        {
+            return false;
+        }
+        if matches!(self.currency_code, Some(code) if !is_valid_currency_code(&code)) {
+            return false;
+        }
+        match &self.tiers {
+            Some(tiers) => tiers_contiguous(tiers),
+            None => true,
+        }
+    }
+}
+
+/// A timelocked config change, applied by `apply_config` once
+/// `effective_slot` is reached.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, InitSpace)]
+pub struct PendingConfigChange {
+    /// The deferred fields of the update
+    pub update: ConfigUpdate,
+
+    /// First slot at which the change may be applied
+    pub effective_slot: u64,
+}
+
+impl Config {
+    /// The active tier table with tier numbers and display names.
+    pub fn tier_definitions(&self) -> Vec<TierDefinition> {
+        self.tiers
+            .iter()
+            .zip(TIER_NAMES)
+            .zip(Tier::ALL)
+            .map(|((bounds, name_bytes), tier)| TierDefinition {
+                tier,
+                name_bytes,
+                lower: bounds.lower,
+                upper: bounds.upper,
+            })
+            .collect()
+    }
+
+    /// Snapshot of the active parameters, excluding any pending change.
+    pub fn snapshot(&self) -> ConfigSnapshot {
+        ConfigSnapshot {
+            version: CONFIG_SNAPSHOT_VERSION,
+            paused: self.paused,
+            badge_validity_seconds: self.badge_validity_seconds,
+            max_proof_age_seconds: self.max_proof_age_seconds,
+            tiers: self.tiers,
+            timelock_slots: self.timelock_slots,
+            min_wallet_age_slots: self.min_wallet_age_slots,
+            vk_rotation_slot: self.vk_rotation_slot,
+            post_rotation_cooldown_slots: self.post_rotation_cooldown_slots,
+            tier_table_frozen: self.tier_table_frozen,
+            prover_key: self.prover_key,
+            max_validity_seconds: self.max_validity_seconds,
+            require_explicit_create: self.require_explicit_create,
+            pauser: self.pauser,
+            allowed_account_types: self.allowed_account_types,
+            hook_program: self.hook_program,
+            probation_seconds: self.probation_seconds,
+            restrict_bundles: self.restrict_bundles,
+            denied_programs: self.denied_programs,
+            min_wallet_lamports: self.min_wallet_lamports,
+            subsidy_min_tier: self.subsidy_min_tier,
+            subsidy_epoch_cap_lamports: self.subsidy_epoch_cap_lamports,
+            tombstone_blocks_recreate: self.tombstone_blocks_recreate,
+            require_origin_relayer: self.require_origin_relayer,
+            max_refreshes_per_window: self.max_refreshes_per_window,
+            refresh_window_seconds: self.refresh_window_seconds,
+            anchor_expiry_to_submission: self.anchor_expiry_to_submission,
+            denied_nullifier_prefixes: self.denied_nullifier_prefixes,
+            currency_code: self.currency_code,
+            emit_tier_memo: self.emit_tier_memo,
+            first_verify_reward_lamports: self.first_verify_reward_lamports,
+            vk_windows: self.vk_windows,
+            withdrawal_recipient: self.withdrawal_recipient,
+            max_accepted_tier: self.max_accepted_tier,
+            verification_epoch: self.verification_epoch,
+            strict_epoch: self.strict_epoch,
+            sane_bounds_max: self.sane_bounds_max,
+            log_verifications: self.log_verifications,
+            tier_weights: self.tier_weights,
+            require_nullifier_uniqueness: self.require_nullifier_uniqueness,
+        }
+    }
+
+    /// Whether `withdraw_treasury` may pay `recipient`: the authority, or
+    /// the configured withdrawal recipient if one is set.
+    pub fn may_receive_withdrawals(&self, recipient: &Pubkey) -> bool {
+        *recipient == self.authority
+            || (self.withdrawal_recipient != Pubkey::default()
+                && *recipient == self.withdrawal_recipient)
+    }
+
+    /// Whether `nullifier` starts with a denied prefix.
+    pub fn nullifier_denied(&self, nullifier: &[u8; 32]) -> bool {
+        self.denied_nullifier_prefixes
+            .iter()
+            .any(|prefix| prefix.matches(nullifier))
+    }
+
+    /// Voting weight of a badge at `tier` (1-7), or 0 for anything else.
+    pub fn tier_weight(&self, tier: u8) -> u64 {
+        match tier {
+            1..=7 => self.tier_weights[(tier - 1) as usize],
+            _ => 0,
+        }
+    }
+
+    /// Expiry of a badge verified at `now` from a proof generated at
+    /// `proof_timestamp`.
+    pub fn badge_expiry(&self, proof_timestamp: i64, now: i64) -> i64 {
+        let anchor = if self.anchor_expiry_to_submission {
+            now
+        } else {
+            proof_timestamp
+        };
+        anchor + self.badge_validity_seconds
+    }
+
+    /// Map proven bounds to a tier, if they match a tier exactly.
+    /// The top tier has no real ceiling, so it matches any proven `upper` at
+    /// or above the table's: a proof that wealth is below some larger cap
+    /// still shows it is at least `lower`.
+    pub fn classify_tier(&self, lower: u64, upper: u64) -> Option<Tier> {
+        let top = TIER_COUNT - 1;
+        self.tiers
+            .iter()
+            .enumerate()
+            .position(|(i, t)| {
+                let ceiling_matches = if i == top {
+                    upper >= t.upper
+                } else {
+                    upper == t.upper
+                };
+                t.lower == lower && ceiling_matches
+            })
+            .map(|i| Tier::ALL[i])
+    }
+
+    /// Whether the badge validity stays within the validity cap once
+    /// `update` is applied.
+    pub fn validity_within_cap(&self, update: &ConfigUpdate) -> bool {
+        update
+            .badge_validity_seconds
+            .unwrap_or(self.badge_validity_seconds)
+            <= update
+                .max_validity_seconds
+                .unwrap_or(self.max_validity_seconds)
+    }
+
+    /// Apply the parts of an update that take effect immediately and return
+    /// the sensitive remainder, which must wait out the timelock.
+    ///
+    /// Pausing and tightening (shorter proof age, longer timelock, longer
+    /// min wallet age or post-rotation cooldown, lower validity cap) never
+    /// weaken security, so they bypass the timelock.
+    /// Validity windows, the tier table and any loosening, unpausing
+    /// included, are deferred.
+    pub fn stage_update(&mut self, update: &ConfigUpdate) -> ConfigUpdate {
+        let mut deferred = update.clone();
+
+        // Pausing is immediate so incidents can be contained; unpausing
+        // waits
+        if let Some(paused) = update.paused.filter(|&paused| paused >= self.paused) {
+            self.paused = paused;
+            deferred.paused = None;
+        }
+        if let Some(age) = update
+            .max_proof_age_seconds
+            .filter(|&age| age <= self.max_proof_age_seconds)
+        {
+            self.max_proof_age_seconds = age;
+            deferred.max_proof_age_seconds = None;
+        }
+        if let Some(slots) = update
+            .timelock_slots
+            .filter(|&slots| slots >= self.timelock_slots)
+        {
+            self.timelock_slots = slots;
+            deferred.timelock_slots = None;
+        }
+        if let Some(slots) = update
+            .min_wallet_age_slots
+            .filter(|&slots| slots >= self.min_wallet_age_slots)
+        {
+            self.min_wallet_age_slots = slots;
+            deferred.min_wallet_age_slots = None;
+        }
+
+        if let Some(slots) = update
+            .post_rotation_cooldown_slots
+            .filter(|&slots| slots >= self.post_rotation_cooldown_slots)
+        {
+            self.post_rotation_cooldown_slots = slots;
+            deferred.post_rotation_cooldown_slots = None;
+        }
+
+        if let Some(seconds) = update
+            .max_validity_seconds
+            .filter(|&seconds| seconds <= self.max_validity_seconds)
+        {
+            self.max_validity_seconds = seconds;
+            deferred.max_validity_seconds = None;
+        }
+
+        if let Some(strict) = update
+            .require_explicit_create
+            .filter(|&strict| strict >= self.require_explicit_create)
+        {
+            self.require_explicit_create = strict;
+            deferred.require_explicit_create = None;
+        }
+
+        if let Some(seconds) = update
+            .probation_seconds
+            .filter(|&seconds| seconds >= self.probation_seconds)
+        {
+            self.probation_seconds = seconds;
+            deferred.probation_seconds = None;
+        }
+
+        if let Some(restrict) = update
+            .restrict_bundles
+            .filter(|&restrict| restrict >= self.restrict_bundles)
+        {
+            self.restrict_bundles = restrict;
+            deferred.restrict_bundles = None;
+        }
+
+        if let Some(lamports) = update
+            .min_wallet_lamports
+            .filter(|&lamports| lamports >= self.min_wallet_lamports)
+        {
+            self.min_wallet_lamports = lamports;
+            deferred.min_wallet_lamports = None;
+        }
+
+        if let Some(cap) = update
+            .subsidy_epoch_cap_lamports
+            .filter(|&cap| cap <= self.subsidy_epoch_cap_lamports)
+        {
+            self.subsidy_epoch_cap_lamports = cap;
+            deferred.subsidy_epoch_cap_lamports = None;
+        }
+
+        if let Some(lamports) = update
+            .first_verify_reward_lamports
+            .filter(|&lamports| lamports <= self.first_verify_reward_lamports)
+        {
+            self.first_verify_reward_lamports = lamports;
+            deferred.first_verify_reward_lamports = None;
+        }
+
+        if let Some(block) = update
+            .tombstone_blocks_recreate
+            .filter(|&block| block >= self.tombstone_blocks_recreate)
+        {
+            self.tombstone_blocks_recreate = block;
+            deferred.tombstone_blocks_recreate = None;
+        }
+
+        if let Some(require) = update
+            .require_origin_relayer
+            .filter(|&require| require >= self.require_origin_relayer)
+        {
+            self.require_origin_relayer = require;
+            deferred.require_origin_relayer = None;
+        }
+
+        // Zero means unlimited, so any limit tightens an unlimited config
+        if let Some(max) = update.max_refreshes_per_window.filter(|&max| {
+            max != 0 && (self.max_refreshes_per_window == 0 || max <= self.max_refreshes_per_window)
+        }) {
+            self.max_refreshes_per_window = max;
+            deferred.max_refreshes_per_window = None;
+        }
+
+        if let Some(seconds) = update
+            .refresh_window_seconds
+            .filter(|&seconds| seconds >= self.refresh_window_seconds)
+        {
+            self.refresh_window_seconds = seconds;
+            deferred.refresh_window_seconds = None;
+        }
+
+        // Anchoring to submission lengthens badges, so only turning it off
+        // is immediate
+        if let Some(anchor) = update
+            .anchor_expiry_to_submission
+            .filter(|&anchor| anchor <= self.anchor_expiry_to_submission)
+        {
+            self.anchor_expiry_to_submission = anchor;
+            deferred.anchor_expiry_to_submission = None;
+        }
+
+        // Denying more nullifiers is immediate so incidents can be
+        // contained; dropping a denied prefix waits
+        if let Some(prefixes) = update.denied_nullifier_prefixes.filter(|prefixes| {
+            self.denied_nullifier_prefixes
+                .iter()
+                .filter(|denied| denied.len != 0)
+                .all(|denied| prefixes.contains(denied))
+        }) {
+            self.denied_nullifier_prefixes = prefixes;
+            deferred.denied_nullifier_prefixes = None;
+        }
+
+        // Narrowing key windows is immediate so a compromised key can be
+        // cut off; accepting more proofs under any key waits
+        if let Some(windows) = update.vk_windows.filter(|windows| {
+            windows
+                .iter()
+                .zip(&self.vk_windows)
+                .all(|(new, current)| new.within(current))
+        }) {
+            self.vk_windows = windows;
+            deferred.vk_windows = None;
+        }
+
+        // Advancing the epoch is the re-verification lever, so it takes
+        // effect at once; going back to an earlier epoch waits
+        if let Some(epoch) = update
+            .verification_epoch
+            .filter(|&epoch| epoch >= self.verification_epoch)
+        {
+            self.verification_epoch = epoch;
+            deferred.verification_epoch = None;
+        }
+
+        if let Some(strict) = update
+            .strict_epoch
+            .filter(|&strict| strict >= self.strict_epoch)
+        {
+            self.strict_epoch = strict;
+            deferred.strict_epoch = None;
+        }
+
+        if let Some(max) = update
+            .sane_bounds_max
+            .filter(|&max| max <= self.sane_bounds_max)
+        {
+            self.sane_bounds_max = max;
+            deferred.sane_bounds_max = None;
+        }
+
+        if let Some(require) = update
+            .require_nullifier_uniqueness
+            .filter(|&require| require >= self.require_nullifier_uniqueness)
+        {
+            self.require_nullifier_uniqueness = require;
+            deferred.require_nullifier_uniqueness = None;
+        }
+
+        deferred
+    }
+
+    /// Apply every field set in `change`.
+    pub fn apply_change(&mut self, change: &ConfigUpdate) {
+        if let Some(paused) = change.paused {
+            self.paused = paused;
+        }
+        if let Some(seconds) = change.badge_validity_seconds {
+            self.badge_validity_seconds = seconds;
+        }
+        if let Some(age) = change.max_proof_age_seconds {
+            self.max_proof_age_seconds = age;
+        }
+        if let Some(tiers) = change.tiers {
+            self.tiers = tiers;
+        }
+        if let Some(slots) = change.timelock_slots {
+            self.timelock_slots = slots;
+        }
+        if let Some(slots) = change.min_wallet_age_slots {
+            self.min_wallet_age_slots = slots;
+        }
+        if let Some(slots) = change.post_rotation_cooldown_slots {
+            self.post_rotation_cooldown_slots = slots;
+        }
+        if let Some(key) = change.prover_key {
+            self.prover_key = key;
+        }
+        if let Some(seconds) = change.max_validity_seconds {
+            self.max_validity_seconds = seconds;
+        }
+        if let Some(strict) = change.require_explicit_create {
+            self.require_explicit_create = strict;
+        }
+        if let Some(key) = change.pauser {
+            self.pauser = key;
+        }
+        if let Some(mask) = change.allowed_account_types {
+            self.allowed_account_types = mask;
+        }
+        if let Some(key) = change.hook_program {
+            self.hook_program = key;
+        }
+        if let Some(seconds) = change.probation_seconds {
+            self.probation_seconds = seconds;
+        }
+        if let Some(restrict) = change.restrict_bundles {
+            self.restrict_bundles = restrict;
+        }
+        if let Some(programs) = change.denied_programs {
+            self.denied_programs = programs;
+        }
+        if let Some(lamports) = change.min_wallet_lamports {
+            self.min_wallet_lamports = lamports;
+        }
+        if let Some(tier) = change.subsidy_min_tier {
+            self.subsidy_min_tier = tier;
+        }
+        if let Some(cap) = change.subsidy_epoch_cap_lamports {
+            self.subsidy_epoch_cap_lamports = cap;
+        }
+        if let Some(block) = change.tombstone_blocks_recreate {
+            self.tombstone_blocks_recreate = block;
+        }
+        if let Some(require) = change.require_origin_relayer {
+            self.require_origin_relayer = require;
+        }
+        if let Some(max) = change.max_refreshes_per_window {
+            self.max_refreshes_per_window = max;
+        }
+        if let Some(seconds) = change.refresh_window_seconds {
+            self.refresh_window_seconds = seconds;
+        }
+        if let Some(anchor) = change.anchor_expiry_to_submission {
+            self.anchor_expiry_to_submission = anchor;
+        }
+        if let Some(prefixes) = change.denied_nullifier_prefixes {
+            self.denied_nullifier_prefixes = prefixes;
+        }
+        if let Some(code) = change.currency_code {
+            self.currency_code = code;
+        }
+        if let Some(emit) = change.emit_tier_memo {
+            self.emit_tier_memo = emit;
+        }
+        if let Some(lamports) = change.first_verify_reward_lamports {
+            self.first_verify_reward_lamports = lamports;
+        }
+        if let Some(windows) = change.vk_windows {
+            self.vk_windows = windows;
+        }
+        if let Some(recipient) = change.withdrawal_recipient {
+            self.withdrawal_recipient = recipient;
+        }
+        if let Some(tier) = change.max_accepted_tier {
+            self.max_accepted_tier = tier;
+        }
+        if let Some(epoch) = change.verification_epoch {
+            self.verification_epoch = epoch;
+        }
+        if let Some(strict) = change.strict_epoch {
+            self.strict_epoch = strict;
+        }
+        if let Some(max) = change.sane_bounds_max {
+            self.sane_bounds_max = max;
+        }
+        if let Some(log) = change.log_verifications {
+            self.log_verifications = log;
+        }
+        if let Some(weights) = change.tier_weights {
+            self.tier_weights = weights;
+        }
+        if let Some(require) = change.require_nullifier_uniqueness {
+            self.require_nullifier_uniqueness = require;
+        }
+    }
+}
+
+/// Attests that a wallet was active at `created_slot`. Solana doesn't expose
+/// account age, so deployments that gate on wallet age require this marker
+/// to be at least `Config.min_wallet_age_slots` old.
+/// Seeds: [b"wallet_marker", owner]
+#[account]
+#[derive(InitSpace)]
+pub struct WalletMarker {
+    /// The wallet this marker belongs to
+    pub owner: Pubkey,
+
+    /// Slot at which the marker was created
+    pub created_slot: u64,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+/// Length of the expiry window one ExpiryBucket covers: a UTC day
+pub const EXPIRY_BUCKET_SECONDS: i64 = 24 * 60 * 60;
+
+/// Most badges one ExpiryBucket can list
+pub const MAX_EXPIRY_BUCKET_ENTRIES: usize = 64;
+
+/// Most badges `list_expiring_badges` returns at once; return data is
+/// capped at 1024 bytes
+pub const EXPIRY_BUCKET_PAGE_SIZE: usize = 30;
+
+/// Index of the badges expiring on one day, so renewal reminders can be
+/// found without scanning. Only badges verified with the bucket passed are
+/// listed.
+/// Seeds: [b"expiry_bucket", day (i64 LE)]
+#[account]
+#[derive(InitSpace)]
+pub struct ExpiryBucket {
+    /// Days since the Unix epoch
+    pub day: i64,
+
+    /// TierBadge addresses expiring that day
+    #[max_len(MAX_EXPIRY_BUCKET_ENTRIES)]
+    pub badges: Vec<Pubkey>,
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl ExpiryBucket {
+    /// The bucket day for an expiry timestamp
+    pub fn day_of(expires_at: i64) -> i64 {
+        expires_at.div_euclid(EXPIRY_BUCKET_SECONDS)
+    }
+
+    /// List `badge` unless it already is. Returns false if the bucket is
+    /// full.
+    pub fn insert(&mut self, badge: Pubkey) -> bool {
+        if self.badges.contains(&badge) {
+            return true;
+        }
+        if self.badges.len() >= MAX_EXPIRY_BUCKET_ENTRIES {
+            return false;
+        }
+        self.badges.push(badge);
+        true
+    }
+
+    /// Stop listing `badge`, if it is listed.
+    pub fn remove(&mut self, badge: &Pubkey) {
+        self.badges.retain(|listed| listed != badge);
+    }
+}
+
+/// Depth of a VerificationLog's merkle tree, which holds up to
+/// 2^depth leaves
+pub const VERIFICATION_LOG_DEPTH: usize = 16;
+
+/// Append-only history of a wallet's verifications, kept as an incremental
+/// merkle tree so the account stays small however long the history grows.
+/// Only the root and the frontier (the left siblings the next append needs)
+/// are stored; indexers rebuild the leaves from `VerificationLogged`
+/// events. History only: gating always reads the live TierBadge.
+/// Seeds: [b"verification_log", owner]
+#[account]
+#[derive(InitSpace)]
+pub struct VerificationLog {
+    /// The wallet whose verifications are logged
+    pub owner: Pubkey,
+
+    /// Leaves appended so far, which is also the next leaf's index
+    pub leaf_count: u64,
+
+    /// Root over `leaf_count` leaves, with empty leaves all zeros
+    pub root: [u8; 32],
+
+    /// `frontier[level]` is the root of the latest complete left subtree
+    /// at that level
+    pub frontier: [[u8; 32]; VERIFICATION_LOG_DEPTH],
+
+    /// Bump seed for PDA derivation
+    pub bump: u8,
+}
+
+impl VerificationLog {
+    /// The leaf logged for one verification:
+    /// `sha256(tier | timestamp (i64 BE) | sha256(nullifier))`
+    pub fn leaf(tier: u8, timestamp: i64, nullifier: &[u8; 32]) -> [u8; 32] {
+        let nullifier_hash = hashv(&[nullifier]).to_bytes();
+        hashv(&[&[tier], &timestamp.to_be_bytes(), &nullifier_hash]).to_bytes()
+    }
+
+    /// Parent of two nodes: `sha256(left | right)`
+    pub fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        hashv(&[left, right]).to_bytes()
+    }
+
+    /// Append `leaf` and update the root. Returns the leaf's index, or
+    /// None if the tree is full.
+    pub fn append(&mut self, leaf: [u8; 32]) -> Option<u64> {
+        let index = self.leaf_count;
+        if index >= 1 << VERIFICATION_LOG_DEPTH {
+            return None;
+        }
+
+        let mut node = leaf;
+        let mut empty = [0u8; 32];
+        let mut stored = false;
+        for level in 0..VERIFICATION_LOG_DEPTH {
+            if index >> level & 1 == 0 {
+                // The first left node on the path completes no subtree yet,
+                // but is the left sibling of the appends that follow
+                if !stored {
+                    self.frontier[level] = node;
+                    stored = true;
+                }
+                node = Self::parent(&node, &empty);
+            } else {
+                node = Self::parent(&self.frontier[level], &node);
+            }
+            empty = Self::parent(&empty, &empty);
+        }
+
+        self.root = node;
+        self.leaf_count = index + 1;
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_tiers_to_and_from_numbers() {
+        for (tier, number) in Tier::ALL.into_iter().zip(1u8..) {
+            assert_eq!(u8::from(tier), number);
+            assert_eq!(Tier::try_from(number).ok(), Some(tier));
+        }
+        assert_eq!(u8::from(Tier::Sun), 7);
+    }
+
+    /// A badge created at `created_at`
+    fn badge_created_at(created_at: i64) -> TierBadge {
+        TierBadge {
+            owner: Pubkey::default(),
+            tier: 1,
+            tier_lower_bound: 0,
+            tier_upper_bound: 100_000,
+            nullifier: [0; 32],
+            verified_at: 0,
+            expires_at: 0,
+            bump: 0,
+            prover_attested: false,
+            account_type: AccountType::Individual,
+            trusted_after: 0,
+            namespace: [0; 8],
+            origin_relayer: Pubkey::default(),
+            refresh_window_start: created_at,
+            refresh_count: 0,
+            created_at,
+            tier_high_water: 1,
+            currency_code: DEFAULT_CURRENCY_CODE,
+            sig_scheme: 0,
+            label: [0; MAX_LABEL_LEN],
+            vk_id: 0,
+            program_version: 0,
+            verification_epoch: 0,
+            nullifier_enforced: false,
+            expiry_listed: false,
+        }
+    }
+
+    #[test]
+    fn grows_legacy_badges_to_the_current_layout() {
+        let badge = TierBadge {
+            tier: 6,
+            nullifier: [7; 32],
+            expires_at: 2_000,
+            bump: 254,
+            ..badge_created_at(0)
+        };
+        let mut data = Vec::new();
+        badge.try_serialize(&mut data).unwrap();
+
+        // The first release wrote the fields up to `bump`; the rest read as
+        // zeros once the account is grown
+        data.truncate(LEGACY_TIER_BADGE_LEN);
+        assert_eq!(data[LEGACY_TIER_BADGE_LEN - 1], 254);
+        data.resize(8 + TierBadge::INIT_SPACE, 0);
+        let mut migrated = TierBadge::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(migrated.tier_high_water, 0);
+        assert_eq!(migrated.currency_code, [0; 3]);
+
+        migrated.fill_migrated_defaults();
+        assert_eq!((migrated.tier, migrated.tier_high_water), (6, 6));
+        assert_eq!(migrated.currency_code, DEFAULT_CURRENCY_CODE);
+        assert_eq!(migrated.nullifier, [7; 32]);
+        assert_eq!(migrated.expires_at, 2_000);
+        assert!(!migrated.nullifier_enforced);
+    }
+
+    #[test]
+    fn limits_refreshes_per_window() {
+        let mut badge = badge_created_at(1_000);
+
+        assert!(badge.record_refresh(1_000, 60, 2));
+        assert!(badge.record_refresh(1_059, 60, 2));
+        assert!(!badge.record_refresh(1_059, 60, 2));
+        assert_eq!(badge.refresh_count, 2);
+
+        // The window rolls over at its end, resetting the count
+        assert!(badge.record_refresh(1_060, 60, 2));
+        assert_eq!(
+            (badge.refresh_window_start, badge.refresh_count),
+            (1_060, 1)
+        );
+    }
+
+    #[test]
+    fn matches_nullifier_prefixes() {
+        let prefix = NullifierPrefix {
+            len: 2,
+            bytes: [0xab, 0xcd, 0xff, 0, 0, 0, 0, 0],
+        };
+        let mut nullifier = [0u8; 32];
+        nullifier[..3].copy_from_slice(&[0xab, 0xcd, 0x01]);
+        assert!(prefix.matches(&nullifier));
+
+        nullifier[1] = 0xce;
+        assert!(!prefix.matches(&nullifier));
+
+        // Unused slots match nothing
+        assert!(!NullifierPrefix::default().matches(&[0u8; 32]));
+    }
+
+    #[test]
+    fn buckets_expiries_by_day() {
+        assert_eq!(ExpiryBucket::day_of(0), 0);
+        assert_eq!(ExpiryBucket::day_of(EXPIRY_BUCKET_SECONDS - 1), 0);
+        assert_eq!(ExpiryBucket::day_of(EXPIRY_BUCKET_SECONDS), 1);
+        assert_eq!(ExpiryBucket::day_of(-1), -1);
+    }
+
+    #[test]
+    fn lists_badges_once_up_to_capacity() {
+        let mut bucket = ExpiryBucket {
+            day: 0,
+            badges: Vec::new(),
+            bump: 0,
+        };
+        let badge = Pubkey::new_unique();
+        assert!(bucket.insert(badge));
+        assert!(bucket.insert(badge));
+        assert_eq!(bucket.badges, vec![badge]);
+
+        while bucket.badges.len() < MAX_EXPIRY_BUCKET_ENTRIES {
+            bucket.badges.push(Pubkey::new_unique());
+        }
+        assert!(!bucket.insert(Pubkey::new_unique()));
+
+        bucket.remove(&badge);
+        assert!(!bucket.badges.contains(&badge));
+        assert!(bucket.insert(Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn reports_the_first_failed_gate_condition() {
+        let mut badge = badge_created_at(0);
+        badge.tier = 3;
+        badge.expires_at = 1_000;
+        badge.trusted_after = 100;
+        let gate = |now, min_tier, require_trusted, min_remaining| {
+            badge
+                .gate(now, min_tier, false, require_trusted, min_remaining)
+                .reason
+        };
+
+        assert_eq!(gate(500, 3, true, 500), GATE_PASSED);
+        assert!(badge.gate(500, 3, false, true, 500).passed);
+        assert_eq!(gate(500, 4, true, 0), GATE_TIER_TOO_LOW);
+        assert_eq!(gate(1_001, 3, false, 0), GATE_EXPIRED);
+        assert_eq!(gate(50, 3, true, 0), GATE_IN_PROBATION);
+        assert_eq!(gate(50, 3, false, 0), GATE_PASSED);
+        assert_eq!(gate(500, 3, true, 501), GATE_EXPIRES_TOO_SOON);
+        assert!(!badge.gate(500, 3, false, true, 501).passed);
+    }
+
+    #[test]
+    fn keeps_the_tier_high_water_mark() {
+        let mut badge = badge_created_at(0);
+        badge.expires_at = 1_000;
+        badge.record_tier(5);
+        badge.record_tier(2);
+        assert_eq!((badge.tier, badge.tier_high_water), (2, 5));
+        badge.record_tier(6);
+        assert_eq!((badge.tier, badge.tier_high_water), (6, 6));
+
+        // A downgrade lowers the tier alone
+        badge.tier = 3;
+        assert_eq!(badge.gate(0, 6, false, false, 0).reason, GATE_TIER_TOO_LOW);
+        assert!(badge.gate(0, 6, true, false, 0).passed);
+    }
+
+    #[test]
+    fn guards_against_clock_regression() {
+        let badge = badge_created_at(1_000);
+        assert!(badge.require_clock_after_creation(1_000).is_ok());
+        assert!(badge.require_clock_after_creation(2_000).is_ok());
+        assert_eq!(
+            badge.require_clock_after_creation(999),
+            Err(crate::errors::ProofOfLoveError::ClockRegressed.into())
+        );
+    }
+
+    #[test]
+    fn gates_on_program_version() {
+        let mut badge = badge_created_at(1_000);
+        badge.program_version = 2;
+        assert!(badge.require_program_version(0).is_ok());
+        assert!(badge.require_program_version(2).is_ok());
+        assert_eq!(
+            badge.require_program_version(3),
+            Err(crate::errors::ProofOfLoveError::ProgramVersionTooOld.into())
+        );
+    }
+
+    #[test]
+    fn expires_staged_verifications() {
+        let pending = PendingVerification {
+            public_inputs: [[0; 32]; NR_PUBLIC_INPUTS],
+            vk_id: 0,
+            slot: 1_000,
+            bump: 0,
+        };
+        assert!(pending.is_fresh(1_000));
+        assert!(pending.is_fresh(1_000 + PENDING_VERIFICATION_SLOTS));
+        assert!(!pending.is_fresh(1_001 + PENDING_VERIFICATION_SLOTS));
+    }
+
+    #[test]
+    fn redeems_coupons_until_exhausted_or_expired() {
+        let mut coupon = Coupon {
+            id: *b"welcome!",
+            extension_seconds: 100,
+            remaining_uses: 2,
+            expires_at: 1_000,
+            bump: 0,
+        };
+        assert_eq!(coupon.redeem(500, 900), Ok(600));
+        assert_eq!(coupon.redeem(600, 1_000), Ok(700));
+        assert_eq!(
+            coupon.redeem(700, 1_000),
+            Err(crate::errors::ProofOfLoveError::CouponExhausted.into())
+        );
+
+        coupon.remaining_uses = 1;
+        assert_eq!(
+            coupon.redeem(700, 1_001),
+            Err(crate::errors::ProofOfLoveError::CouponExpired.into())
+        );
+        assert_eq!(coupon.remaining_uses, 1);
+    }
+
+    #[test]
+    fn counts_badges_registered_against_a_nullifier() {
+        let mut record = NullifierRecord {
+            nullifier: [7; 32],
+            owner: Pubkey::new_unique(),
+            bump: 0,
+            payer: Pubkey::new_unique(),
+            badges: 0,
+        };
+        record.register_badge();
+        record.register_badge();
+        assert!(!record.release_badge());
+        assert!(record.release_badge());
+        assert!(record.release_badge());
+        assert_eq!(record.badges, 0);
+    }
+
+    #[test]
+    fn detects_nullifier_desync() {
+        let mut badge = badge_created_at(1_000);
+        badge.owner = Pubkey::new_unique();
+        badge.nullifier = [7; 32];
+        let record = NullifierRecord {
+            nullifier: badge.nullifier,
+            owner: badge.owner,
+            bump: 0,
+            payer: badge.owner,
+            badges: 1,
+        };
+        assert!(badge.require_in_sync(&record).is_ok());
+
+        let desync = Err(crate::errors::ProofOfLoveError::NullifierDesync.into());
+        let other_nullifier = NullifierRecord {
+            nullifier: [8; 32],
+            ..record.clone()
+        };
+        assert_eq!(badge.require_in_sync(&other_nullifier), desync);
+        let other_owner = NullifierRecord {
+            owner: Pubkey::new_unique(),
+            ..record
+        };
+        assert_eq!(badge.require_in_sync(&other_owner), desync);
+    }
+
+    #[test]
+    fn appends_verification_log_leaves() {
+        // Root of the full tree over `leaves`, padded with zero leaves
+        fn naive_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+            let mut level = leaves.to_vec();
+            level.resize(1 << VERIFICATION_LOG_DEPTH, [0; 32]);
+            while level.len() > 1 {
+                level = level
+                    .chunks(2)
+                    .map(|pair| VerificationLog::parent(&pair[0], &pair[1]))
+                    .collect();
+            }
+            level[0]
+        }
+
+        let mut log = VerificationLog {
+            owner: Pubkey::new_unique(),
+            leaf_count: 0,
+            root: [0; 32],
+            frontier: [[0; 32]; VERIFICATION_LOG_DEPTH],
+            bump: 0,
+        };
+        let mut leaves = Vec::new();
+        for i in 0..9u8 {
+            let leaf = VerificationLog::leaf(i % 7 + 1, 1_700_000_000 + i as i64, &[i; 32]);
+            assert_eq!(log.append(leaf), Some(i as u64));
+            leaves.push(leaf);
+            assert_eq!(log.root, naive_root(&leaves));
+        }
+        assert_eq!(log.leaf_count, 9);
+
+        log.leaf_count = 1 << VERIFICATION_LOG_DEPTH;
+        assert_eq!(log.append([1; 32]), None);
+    }
+
+    #[test]
+    fn gates_on_nullifier_enforcement() {
+        let mut badge = badge_created_at(1_000);
+        assert_eq!(
+            badge.require_nullifier_enforced(),
+            Err(crate::errors::ProofOfLoveError::NullifierNotEnforced.into())
+        );
+        badge.nullifier_enforced = true;
+        assert!(badge.require_nullifier_enforced().is_ok());
+    }
+
+    #[test]
+    fn validates_currency_codes() {
+        assert!(is_valid_currency_code(&DEFAULT_CURRENCY_CODE));
+        assert!(is_valid_currency_code(b"EUR"));
+        for code in [b"usd", b"US1", b"U D", b"\xc3\x9cS"] {
+            assert!(!is_valid_currency_code(code), "{code:?}");
+        }
+        let update = |code: &[u8; 3]| ConfigUpdate {
+            currency_code: Some(*code),
+            ..Default::default()
+        };
+        assert!(update(b"JPY").is_valid());
+        assert!(!update(b"jpy").is_valid());
+    }
+
+    #[test]
+    fn encodes_labels() {
+        let label = encode_label("gm 🌱".as_bytes()).unwrap();
+        assert_eq!(&label[..7], "gm 🌱".as_bytes());
+        assert!(label[7..].iter().all(|&b| b == 0));
+        assert_eq!(encode_label(b""), Some([0; MAX_LABEL_LEN]));
+        assert!(encode_label(&[b'a'; MAX_LABEL_LEN]).is_some());
+
+        assert!(encode_label(&[b'a'; MAX_LABEL_LEN + 1]).is_none());
+        for label in [&b"a\0b"[..], b"tab\there", b"\x7f", b"\xff\xfe"] {
+            assert!(encode_label(label).is_none(), "{label:?}");
+        }
+        // C1 controls are multi-byte in UTF-8
+        assert!(encode_label("\u{85}".as_bytes()).is_none());
+    }
+
+    #[test]
+    fn validates_max_accepted_tier() {
+        let update = |tier| ConfigUpdate {
+            max_accepted_tier: Some(tier),
+            ..Default::default()
+        };
+        assert!(update(1).is_valid());
+        assert!(update(TIER_COUNT as u8).is_valid());
+        assert!(!update(0).is_valid());
+        assert!(!update(TIER_COUNT as u8 + 1).is_valid());
+    }
+
+    #[test]
+    fn validates_sane_bounds_max() {
+        let update = |max| ConfigUpdate {
+            sane_bounds_max: Some(max),
+            ..Default::default()
+        };
+        assert!(update(DEFAULT_SANE_BOUNDS_MAX).is_valid());
+        assert!(!update(0).is_valid());
+        // The default leaves room for the whole launch tier table
+        assert!(DEFAULT_TIERS
+            .iter()
+            .all(|tier| tier.upper <= DEFAULT_SANE_BOUNDS_MAX));
+    }
+
+    #[test]
+    fn matches_identity_rotations_exactly() {
+        let owner = Pubkey::new_unique();
+        let rotation = IdentityRotation {
+            old_nullifier: [1; 32],
+            new_nullifier: [2; 32],
+            owner,
+            attested_at: 0,
+            bump: 0,
+        };
+        assert!(rotation.allows(&owner, &[1; 32], &[2; 32]));
+        assert!(!rotation.allows(&Pubkey::new_unique(), &[1; 32], &[2; 32]));
+        assert!(!rotation.allows(&owner, &[3; 32], &[2; 32]));
+        assert!(!rotation.allows(&owner, &[1; 32], &[3; 32]));
+    }
+
+    #[test]
+    fn tracks_the_remaining_subsidy_cap() {
+        let mut treasury = Treasury {
+            epoch: 0,
+            subsidized_lamports: 0,
+            bump: 0,
+        };
+        assert!(treasury.record_subsidy(5, 300, 1_000));
+        assert!(treasury.record_subsidy(5, 700, 1_000));
+        assert!(!treasury.record_subsidy(5, 1, 1_000));
+        assert_eq!(treasury.remaining_cap(5, 1_000), 0);
+        assert_eq!(treasury.remaining_cap(5, 1_500), 500);
+
+        // A new epoch starts with the whole cap available again
+        assert_eq!(treasury.remaining_cap(6, 1_000), 1_000);
+        assert!(treasury.record_subsidy(6, 1_000, 1_000));
+    }
+
+    #[test]
+    fn weighs_votes_by_tier() {
+        let update = |weights| ConfigUpdate {
+            tier_weights: Some(weights),
+            ..Default::default()
+        };
+        assert!(update(DEFAULT_TIER_WEIGHTS).is_valid());
+        assert!(update([0, 0, 1, 1, 10, 10, 100]).is_valid());
+        assert!(!update([1, 2, 3, 4, 5, 7, 6]).is_valid());
+
+        let mut power = VotingPower {
+            badge: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            tier: 6,
+            weight: DEFAULT_TIER_WEIGHTS[5],
+            expires_at: 1_000,
+            bump: 0,
+        };
+        assert_eq!(power.weight_at(1_000), 6);
+        assert_eq!(power.weight_at(1_001), 0);
+        power.zero();
+        assert_eq!(power.weight_at(0), 0);
+    }
+
+    #[test]
+    fn key_windows_overlap_then_close() {
+        // Migration: the old key accepts proofs made until 2_000
+        let old = KeyWindow {
+            valid_from: i64::MIN,
+            valid_until: 2_000,
+        };
+        let new = KeyWindow::ALWAYS;
+        assert!(old.contains(1_999) && new.contains(1_999));
+        assert!(old.contains(2_000));
+        assert!(!old.contains(2_001) && new.contains(2_001));
+        assert!(!KeyWindow::NEVER.contains(0));
+
+        // Only windows that accept no new timestamps are narrowings
+        assert!(old.within(&KeyWindow::ALWAYS));
+        assert!(!KeyWindow::ALWAYS.within(&old));
+        assert!(KeyWindow::NEVER.within(&old));
+        assert!(!old.within(&KeyWindow::NEVER));
+        let reopened = KeyWindow {
+            valid_from: 2_500,
+            valid_until: 3_000,
+        };
+        assert!(!reopened.within(&old));
+    }
+
+    #[test]
+    fn rejects_out_of_range_tier_numbers() {
+        for number in [0, 8, u8::MAX] {
+            assert!(matches!(
+                Tier::try_from(number),
+                Err(crate::errors::ProofOfLoveError::InvalidTier)
+            ));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Verification against a caller-supplied verifying key, so local tests can
+//! iterate on the circuit without regenerating `verifying_key.rs`. Compiled
+//! only with the `test-vk` feature; production builds can only verify
+//! against the pinned key.
+
+use groth16_solana::decompression::{decompress_g1, decompress_g2};
+use groth16_solana::groth16::Groth16Verifyingkey;
+
+use crate::verifying_key::NR_PUBLIC_INPUTS;
+
+/// Length of a compressed verifying key with one IC point per public input
+/// plus the constant term.
+pub const COMPRESSED_VK_LEN: usize = 32 + 3 * 64 + (NR_PUBLIC_INPUTS + 1) * 32;
+
+/// A verifying key decoded from instruction data. `Groth16Verifyingkey`
+/// borrows its IC points, so they live here.
+pub struct OwnedVerifyingKey {
+    alpha_g1: [u8; 64],
+    beta_g2: [u8; 128],
+    gamma_g2: [u8; 128],
+    delta_g2: [u8; 128],
+    ic: Vec<[u8; 64]>,
+}
+
+impl OwnedVerifyingKey {
+    /// Decode a key sent as compressed points, in the order `vk_hash`
+    /// hashes them: `alpha_g1 | beta_g2 | gamma_g2 | delta_g2 | ic[0] | ...`.
+    /// Points use the same compression as `verify_and_store_tier_compressed`.
+    /// Compressed, since the uncompressed key alone would leave no room in
+    /// a transaction for the proof. Returns `None` for the wrong length or
+    /// bytes that don't decode to curve points.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != COMPRESSED_VK_LEN {
+            return None;
+        }
+        let (g1s, ic) = (&bytes[..32], &bytes[32 + 3 * 64..]);
+        let g2 = |i: usize| -> Option<[u8; 128]> {
+            let start = 32 + i * 64;
+            decompress_g2(bytes[start..start + 64].try_into().ok()?).ok()
+        };
+        Some(Self {
+            alpha_g1: decompress_g1(g1s.try_into().ok()?).ok()?,
+            beta_g2: g2(0)?,
+            gamma_g2: g2(1)?,
+            delta_g2: g2(2)?,
+            ic: ic
+                .chunks_exact(32)
+                .map(|point| decompress_g1(point.try_into().ok()?).ok())
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub fn as_groth16(&self) -> Groth16Verifyingkey<'_> {
+        Groth16Verifyingkey {
+            nr_pubinputs: NR_PUBLIC_INPUTS,
+            vk_alpha_g1: self.alpha_g1,
+            vk_beta_g2: self.beta_g2,
+            vk_gamme_g2: self.gamma_g2,
+            vk_delta_g2: self.delta_g2,
+            vk_ic: &self.ic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_wrong_length() {
+        assert!(OwnedVerifyingKey::decode(&[]).is_none());
+        assert!(OwnedVerifyingKey::decode(&[0u8; COMPRESSED_VK_LEN - 32]).is_none());
+        assert!(OwnedVerifyingKey::decode(&[0u8; COMPRESSED_VK_LEN + 32]).is_none());
+    }
+
+    #[test]
+    fn rejects_points_off_the_curve() {
+        assert!(OwnedVerifyingKey::decode(&[0xff; COMPRESSED_VK_LEN]).is_none());
+    }
+}
@@ -1,6 +1,6 @@
-/// AUTO-GENERATED FILE — Do not edit manually.
-/// Generated by: node scripts/parse-vk.js verification_key.json
-/// Source: verification_key.json
+//! AUTO-GENERATED FILE — Do not edit manually.
+//! Generated by: node scripts/parse-vk.js verification_key.json
+//! Source: verification_key.json
 
 use groth16_solana::groth16::Groth16Verifyingkey;
 
@@ -85,3 +85,8 @@ pub const VERIFYING_KEY: Groth16Verifyingkey = Groth16Verifyingkey {
         ],
     ],
 };
+
+/// Verifying keys of previous WealthTier setups, newest first. Key `i`
+/// here has id `i + 1` in `Config.vk_windows`.
+pub const PREVIOUS_VERIFYING_KEYS: &[Groth16Verifyingkey] = &[
+];
@@ -7,7 +7,11 @@
  * with groth16-solana's Groth16Verifyingkey struct.
  *
  * Usage:
- *   node scripts/parse-vk.js <path-to-verification_key.json>
+ *   node scripts/parse-vk.js <path-to-verification_key.json> [<previous-key.json> ...]
+ *
+ * Previous keys, newest first, are emitted as PREVIOUS_VERIFYING_KEYS so
+ * proofs made under them stay valid inside their Config.vk_windows during
+ * a trusted-setup migration.
  *
  * Example:
  *   node scripts/parse-vk.js ../../circuits/build/verification_key.json > programs/proof-of-love/src/verifying_key.rs
@@ -16,13 +20,24 @@
 const fs = require("fs");
 const path = require("path");
 
-const vkPath = process.argv[2];
+const [vkPath, ...previousPaths] = process.argv.slice(2);
 if (!vkPath) {
-  console.error("Usage: node parse-vk.js <path-to-verification_key.json>");
+  console.error(
+    "Usage: node parse-vk.js <path-to-verification_key.json> [<previous-key.json> ...]",
+  );
   process.exit(1);
 }
 
-const vk = JSON.parse(fs.readFileSync(path.resolve(vkPath), "utf-8"));
+// Must match state::MAX_VERIFYING_KEYS
+const MAX_VERIFYING_KEYS = 3;
+if (previousPaths.length >= MAX_VERIFYING_KEYS) {
+  console.error(`At most ${MAX_VERIFYING_KEYS - 1} previous keys fit the config`);
+  process.exit(1);
+}
+
+const readVk = (p) => JSON.parse(fs.readFileSync(path.resolve(p), "utf-8"));
+const vk = readVk(vkPath);
+const previousVks = previousPaths.map(readVk);
 
 /**
  * Convert a decimal string to a 32-byte big-endian array.
@@ -70,18 +85,41 @@ function formatBytes(bytes, indent = "    ") {
   return lines.join("\n");
 }
 
-// Build the output
-const alphaG1 = g1ToBytes(vk.vk_alpha_1);
-const betaG2 = g2ToBytes(vk.vk_beta_2);
-const gammaG2 = g2ToBytes(vk.vk_gamma_2);
-const deltaG2 = g2ToBytes(vk.vk_delta_2);
+/**
+ * Format a verifying key's fields as a Groth16Verifyingkey body, every line
+ * prefixed with `indent`.
+ */
+function formatKey(key, indent) {
+  const fields = [
+    ["vk_alpha_g1", g1ToBytes(key.vk_alpha_1)],
+    ["vk_beta_g2", g2ToBytes(key.vk_beta_2)],
+    ["vk_gamme_g2", g2ToBytes(key.vk_gamma_2)],
+    ["vk_delta_g2", g2ToBytes(key.vk_delta_2)],
+  ];
+  let body = `${indent}nr_pubinputs: NR_PUBLIC_INPUTS,\n`;
+  for (const [name, bytes] of fields) {
+    body += `\n${indent}${name}: [\n${formatBytes(bytes, indent + "    ")}\n${indent}],\n`;
+  }
+  body += `\n${indent}vk_ic: &[\n`;
+  for (const point of key.IC) {
+    body += `${indent}    [\n${formatBytes(g1ToBytes(point), indent + "        ")}\n${indent}    ],\n`;
+  }
+  return body + `${indent}],\n`;
+}
 
-const icPoints = vk.IC.map((point) => g1ToBytes(point));
 const nrPubInputs = vk.IC.length - 1;
+for (const previous of previousVks) {
+  if (previous.IC.length - 1 !== nrPubInputs) {
+    console.error("Previous keys must have the same number of public inputs");
+    process.exit(1);
+  }
+}
+
+const sources = [vkPath, ...previousPaths].map((p) => path.basename(p)).join(" ");
 
-let output = `/// AUTO-GENERATED FILE — Do not edit manually.
-/// Generated by: node scripts/parse-vk.js ${path.basename(vkPath)}
-/// Source: ${path.basename(vkPath)}
+let output = `//! AUTO-GENERATED FILE — Do not edit manually.
+//! Generated by: node scripts/parse-vk.js ${sources}
+//! Source: ${sources}
 
 use groth16_solana::groth16::Groth16Verifyingkey;
 
@@ -91,33 +129,18 @@ pub const NR_PUBLIC_INPUTS: usize = ${nrPubInputs};
 
 /// Verifying key for the WealthTier Groth16 circuit.
 pub const VERIFYING_KEY: Groth16Verifyingkey = Groth16Verifyingkey {
-    nr_pubinputs: NR_PUBLIC_INPUTS,
-
-    vk_alpha_g1: [
-${formatBytes(alphaG1, "        ")}
-    ],
-
-    vk_beta_g2: [
-${formatBytes(betaG2, "        ")}
-    ],
-
-    vk_gamma_g2: [
-${formatBytes(gammaG2, "        ")}
-    ],
-
-    vk_delta_g2: [
-${formatBytes(deltaG2, "        ")}
-    ],
+${formatKey(vk, "    ")}};
 
-    vk_ic: &[
+/// Verifying keys of previous WealthTier setups, newest first. Key \`i\`
+/// here has id \`i + 1\` in \`Config.vk_windows\`.
+pub const PREVIOUS_VERIFYING_KEYS: &[Groth16Verifyingkey] = &[
 `;
 
-for (let i = 0; i < icPoints.length; i++) {
-  output += `        [\n${formatBytes(icPoints[i], "            ")}\n        ],\n`;
+for (const previous of previousVks) {
+  output += `    Groth16Verifyingkey {\n${formatKey(previous, "        ")}    },\n`;
 }
 
-output += `    ],
-};
+output += `];
 `;
 
 process.stdout.write(output);
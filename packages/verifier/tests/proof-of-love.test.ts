@@ -1,10 +1,21 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ComputeBudgetProgram,
+  Ed25519Program,
+  Keypair,
+  PublicKey,
+  Secp256k1Program,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
 import { expect } from "chai";
 import * as path from "path";
 import * as fs from "fs";
-import { createHash } from "crypto";
+import * as os from "os";
+import { createHash, randomBytes } from "crypto";
 
 // ---------------------------------------------------------------------------
 // Configuration — adjust these paths to match your monorepo layout
@@ -33,6 +44,32 @@ const ZKEY_PATH = path.join(
   "wealth_tier_final.zkey",
 );
 
+/** The `account_type` argument for an individual's badge */
+const INDIVIDUAL = { individual: {} };
+
+/** `layout_version` arguments: the circuit's own public input order, and
+ * the same inputs with the nullifier first */
+const LAYOUT_V1 = 1;
+const LAYOUT_V2 = 2;
+
+/** `sig_scheme` arguments of verify_delegated */
+const SIG_SCHEME_ED25519 = 0;
+const SIG_SCHEME_SECP256K1 = 1;
+
+/** The `Tier` enum value for a tier number (1-7), as Anchor decodes it */
+function tierValue(tier: number): object {
+  const variants = [
+    "seed",
+    "sprout",
+    "tree",
+    "mountain",
+    "ocean",
+    "moon",
+    "sun",
+  ];
+  return { [variants[tier - 1]]: {} };
+}
+
 // ---------------------------------------------------------------------------
 // Proof encoding helpers (inline to avoid import issues in Anchor test env)
 // ---------------------------------------------------------------------------
@@ -83,6 +120,67 @@ function encodeProofC(pi_c: string[]): Buffer {
   return buf;
 }
 
+/** Coordinates above (p - 1) / 2 count as negative in point compression */
+const HALF_FIELD = (CURVE_ORDER - 1n) / 2n;
+
+function bytesToBigInt(bytes: Buffer): bigint {
+  return BigInt("0x" + bytes.toString("hex"));
+}
+
+/** 64-byte G1 point → 32-byte x, y's sign in the top bit */
+function compressG1(point: Buffer): Buffer {
+  const compressed = Buffer.from(point.subarray(0, 32));
+  if (bytesToBigInt(point.subarray(32)) > HALF_FIELD) compressed[0] |= 0x80;
+  return compressed;
+}
+
+/** 128-byte G2 point → 64-byte x, y's sign (y.c1 first) in the top bit */
+function compressG2(point: Buffer): Buffer {
+  const compressed = Buffer.from(point.subarray(0, 64));
+  const yC1 = bytesToBigInt(point.subarray(64, 96));
+  const yC0 = bytesToBigInt(point.subarray(96));
+  if ((yC1 !== 0n ? yC1 : yC0) > HALF_FIELD) compressed[0] |= 0x80;
+  return compressed;
+}
+
+/** Derive the NullifierRecord PDA for a 32-byte nullifier */
+function deriveNullifierPDA(
+  nullifier: Buffer,
+  programId: PublicKey,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("nullifier"), nullifier],
+    programId,
+  );
+}
+
+/** Derive an identity's VotingPower PDA from its 32-byte nullifier */
+function deriveVotingPowerPDA(
+  nullifier: Buffer,
+  programId: PublicKey,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("voting_power"), nullifier],
+    programId,
+  );
+}
+
+/** Derive an identity's BadgeTombstone PDA from its 32-byte nullifier */
+function deriveTombstonePDA(
+  nullifier: Buffer,
+  programId: PublicKey,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("tombstone"), nullifier],
+    programId,
+  );
+}
+
+/** Derive the global Config PDA */
+function deriveConfigPDA(programId: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([Buffer.from("config")], programId);
+}
+
 /** Derive the TierBadge PDA */
 function deriveTierBadgePDA(
   userPubkey: PublicKey,
@@ -94,6 +192,166 @@ function deriveTierBadgePDA(
   );
 }
 
+/** Derive the ExpiryBucket PDA for a day since the Unix epoch */
+function deriveExpiryBucketPDA(
+  day: number,
+  programId: PublicKey,
+): [PublicKey, number] {
+  const dayBytes = Buffer.alloc(8);
+  dayBytes.writeBigInt64LE(BigInt(day));
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("expiry_bucket"), dayBytes],
+    programId,
+  );
+}
+
+const BPF_LOADER_UPGRADEABLE = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111",
+);
+
+/** Timelock used for the test deployment's Config */
+const TIMELOCK_SLOTS = 10;
+
+/** Wait until the cluster has advanced by at least `slots` slots */
+async function waitSlots(
+  connection: anchor.web3.Connection,
+  slots: number,
+): Promise<void> {
+  const target = (await connection.getSlot()) + slots;
+  while ((await connection.getSlot()) < target) {
+    await new Promise((resolve) => setTimeout(resolve, 200));
+  }
+}
+
+/** Wait until the cluster's clock is past `timestamp` */
+async function waitPastUnixTime(
+  connection: anchor.web3.Connection,
+  timestamp: number,
+): Promise<void> {
+  const clusterTime = async () =>
+    (await connection.getBlockTime(await connection.getSlot())) ?? 0;
+  while ((await clusterTime()) <= timestamp) {
+    await new Promise((resolve) => setTimeout(resolve, 500));
+  }
+}
+
+const TOKEN_PROGRAM_ID = new PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+);
+const MINT_SIZE = 82;
+const TOKEN_ACCOUNT_SIZE = 165;
+
+/** Derive the mint authority PDA a tier token mint must be controlled by */
+function deriveTierMintAuthorityPDA(
+  tier: number,
+  programId: PublicKey,
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("tier_mint_authority"), Buffer.from([tier])],
+    programId,
+  );
+}
+
+/** Create a 0-decimal SPL mint controlled by `authority` */
+async function createMint(
+  provider: anchor.AnchorProvider,
+  authority: PublicKey,
+): Promise<PublicKey> {
+  const mint = Keypair.generate();
+  const lamports =
+    await provider.connection.getMinimumBalanceForRentExemption(MINT_SIZE);
+  // InitializeMint2: [20, decimals, mint_authority, freeze_authority: None]
+  const data = Buffer.concat([
+    Buffer.from([20, 0]),
+    authority.toBuffer(),
+    Buffer.from([0]),
+  ]);
+  await provider.sendAndConfirm(
+    new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: provider.wallet.publicKey,
+        newAccountPubkey: mint.publicKey,
+        lamports,
+        space: MINT_SIZE,
+        programId: TOKEN_PROGRAM_ID,
+      }),
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+        data,
+      }),
+    ),
+    [mint],
+  );
+  return mint.publicKey;
+}
+
+/** Create a token account for `mint` owned by `owner` */
+async function createTokenAccount(
+  provider: anchor.AnchorProvider,
+  mint: PublicKey,
+  owner: PublicKey,
+): Promise<PublicKey> {
+  const account = Keypair.generate();
+  const lamports =
+    await provider.connection.getMinimumBalanceForRentExemption(
+      TOKEN_ACCOUNT_SIZE,
+    );
+  // InitializeAccount3: [18, owner]
+  const data = Buffer.concat([Buffer.from([18]), owner.toBuffer()]);
+  await provider.sendAndConfirm(
+    new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: provider.wallet.publicKey,
+        newAccountPubkey: account.publicKey,
+        lamports,
+        space: TOKEN_ACCOUNT_SIZE,
+        programId: TOKEN_PROGRAM_ID,
+      }),
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: account.publicKey, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+        ],
+        data,
+      }),
+    ),
+    [account],
+  );
+  return account.publicKey;
+}
+
+/** Mint `amount` base units of `mint` into `account`, as the provider wallet */
+async function mintTokens(
+  provider: anchor.AnchorProvider,
+  mint: PublicKey,
+  account: PublicKey,
+  amount: number,
+): Promise<void> {
+  // MintTo: [7, amount (u64 LE)]
+  const data = Buffer.alloc(9);
+  data[0] = 7;
+  data.writeBigUInt64LE(BigInt(amount), 1);
+  await provider.sendAndConfirm(
+    new Transaction().add(
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: account, isSigner: false, isWritable: true },
+          {
+            pubkey: provider.wallet.publicKey,
+            isSigner: true,
+            isWritable: false,
+          },
+        ],
+        data,
+      }),
+    ),
+  );
+}
+
 // ---------------------------------------------------------------------------
 // Test suite
 // ---------------------------------------------------------------------------
@@ -121,6 +379,188 @@ describe("proof-of-love", () => {
   let publicSignals: string[];
   let tierBadgePDA: PublicKey;
   let tierBadgeBump: number;
+  const [configPDA] = deriveConfigPDA(programId);
+
+  /** A ConfigUpdate that changes nothing — spread and override fields */
+  const noChange = {
+    paused: null,
+    badgeValiditySeconds: null,
+    maxProofAgeSeconds: null,
+    tiers: null,
+    timelockSlots: null,
+    minWalletAgeSlots: null,
+    postRotationCooldownSlots: null,
+    proverKey: null,
+    maxValiditySeconds: null,
+    requireExplicitCreate: null,
+    pauser: null,
+    allowedAccountTypes: null,
+    hookProgram: null,
+    probationSeconds: null,
+    restrictBundles: null,
+    deniedPrograms: null,
+    minWalletLamports: null,
+    subsidyMinTier: null,
+    subsidyEpochCapLamports: null,
+    tombstoneBlocksRecreate: null,
+    requireOriginRelayer: null,
+    maxRefreshesPerWindow: null,
+    refreshWindowSeconds: null,
+    anchorExpiryToSubmission: null,
+    deniedNullifierPrefixes: null,
+    currencyCode: null,
+    emitTierMemo: null,
+    firstVerifyRewardLamports: null,
+    vkWindows: null,
+    withdrawalRecipient: null,
+    maxAcceptedTier: null,
+    verificationEpoch: null,
+    strictEpoch: null,
+    saneBoundsMax: null,
+    logVerifications: null,
+    tierWeights: null,
+    requireNullifierUniqueness: null,
+  };
+
+  const fetchConfig = () => (program.account as any).config.fetch(configPDA);
+
+  /** The VotingPower PDA of an existing badge's identity */
+  const votingPowerOf = async (badge: PublicKey) =>
+    deriveVotingPowerPDA(
+      Buffer.from(
+        (await (program.account as any).tierBadge.fetch(badge)).nullifier,
+      ),
+      programId,
+    )[0];
+
+  /** The NullifierRecord PDA of an existing badge's identity */
+  const nullifierRecordOf = async (badge: PublicKey) =>
+    deriveNullifierPDA(
+      Buffer.from(
+        (await (program.account as any).tierBadge.fetch(badge)).nullifier,
+      ),
+      programId,
+    )[0];
+
+  /** The BadgeTombstone PDA of an existing badge's identity */
+  const tombstoneOf = async (badge: PublicKey) =>
+    deriveTombstonePDA(
+      Buffer.from(
+        (await (program.account as any).tierBadge.fetch(badge)).nullifier,
+      ),
+      programId,
+    )[0];
+
+  /** Change the badge validity window, waiting out the timelock */
+  async function setBadgeValidity(seconds: number): Promise<void> {
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        badgeValiditySeconds: new anchor.BN(seconds),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+  }
+
+  /** Create a keypair funded with 2 SOL */
+  async function fundedKeypair(): Promise<Keypair> {
+    const kp = Keypair.generate();
+    const sig = await provider.connection.requestAirdrop(
+      kp.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL,
+    );
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    return kp;
+  }
+
+  /**
+   * Generate a fresh proof for `wallet` (Moon tier, $2M balances, nullifier
+   * derived from the wallet) and encode it as verify_and_store_tier args.
+   * `inputs` overrides any circuit input; `zkey` the proving key.
+   */
+  async function proveFor(
+    wallet: PublicKey,
+    inputs: Record<string, string> = {},
+    zkey: string = ZKEY_PATH,
+  ) {
+    const nullifier = BigInt(
+      "0x" + createHash("sha256").update(wallet.toBuffer()).digest("hex"),
+    ).toString();
+    const { proof, publicSignals: signals } = await snarkjs.groth16.fullProve(
+      {
+        balance_1: "200000000",
+        balance_2: "200000000",
+        balance_3: "200000000",
+        tier_lower_bound: "100000000",
+        tier_upper_bound: "500000000",
+        nullifier,
+        timestamp: Math.floor(Date.now() / 1000).toString(),
+        ...inputs,
+      },
+      WASM_PATH,
+      zkey,
+    );
+    const encodedInputs = signals.map((s: string) => decimalTo32BytesBE(s));
+    return {
+      publicInputs: encodedInputs as Buffer[],
+      args: [
+        Array.from(encodeProofA(proof.pi_a)),
+        Array.from(encodeProofB(proof.pi_b)),
+        Array.from(encodeProofC(proof.pi_c)),
+        encodedInputs.map((buf: Buffer) => Array.from(buf)),
+        null as number[] | null,
+        INDIVIDUAL as object,
+        null as number[] | null,
+      ] as const,
+    };
+  }
+
+  /** The VerifyShared accounts every verification nests, with overrides */
+  function sharedAccounts(overrides: Record<string, PublicKey | null> = {}) {
+    return {
+      identityRotation: null,
+      previousNullifierRecord: null,
+      config: configPDA,
+      bundleSysvar: null,
+      hookProgram: null,
+      treasury: null,
+      relayer: null,
+      expiryBucket: null,
+      previousExpiryBucket: null,
+      memoProgram: null,
+      rewardVault: null,
+      verificationLog: null,
+      ...overrides,
+    };
+  }
+
+  /**
+   * The verify_and_store_tier accounts for `wallet` and its proof inputs,
+   * with `shared` overriding the nested VerifyShared accounts
+   */
+  function verifyAccounts(
+    wallet: PublicKey,
+    inputs: Buffer[],
+    shared: Record<string, PublicKey | null> = {},
+  ) {
+    return {
+      user: wallet,
+      tierBadge: deriveTierBadgePDA(wallet, programId)[0],
+      nullifierRecord: deriveNullifierPDA(inputs[2], programId)[0],
+      walletMarker: null,
+      requestReceipt: null,
+      instructionsSysvar: null,
+      badgeTombstone: null,
+      rewardClaim: null,
+      shared: sharedAccounts(shared),
+      systemProgram: SystemProgram.programId,
+    };
+  }
 
   // ---------------------------------------------------------------------------
   // Setup: check artifacts exist, load snarkjs, generate a proof
@@ -212,7 +652,24 @@ describe("proof-of-love", () => {
       programId,
     );
     console.log(`    TierBadge PDA: ${tierBadgePDA.toBase58()}`);
-    console.log(`    Bump: ${tierBadgeBump}\n`);
+    console.log(`    Bump: ${tierBadgeBump}`);
+
+    // 7. Initialize the Config PDA (the provider wallet is the upgrade authority)
+    const [programDataPDA] = PublicKey.findProgramAddressSync(
+      [programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE,
+    );
+    await program.methods
+      .initializeConfig(new anchor.BN(TIMELOCK_SLOTS))
+      .accounts({
+        authority: user.publicKey,
+        config: configPDA,
+        program: programId,
+        programData: programDataPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    console.log(`    Config PDA: ${configPDA.toBase58()}\n`);
   });
 
   // ---------------------------------------------------------------------------
@@ -227,10 +684,21 @@ describe("proof-of-love", () => {
     const pubInputsArray = publicInputs.map((buf) => Array.from(buf));
 
     const tx = await program.methods
-      .verifyAndStoreTier(proofAArray, proofBArray, proofCArray, pubInputsArray)
+      .verifyAndStoreTier(
+        proofAArray,
+        proofBArray,
+        proofCArray,
+        pubInputsArray,
+        null,
+        INDIVIDUAL,
+        null,
+        LAYOUT_V1,
+      )
       .accounts({
         user: user.publicKey,
         tierBadge: tierBadgePDA,
+        nullifierRecord: deriveNullifierPDA(publicInputs[2], programId)[0],
+        config: configPDA,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
@@ -301,10 +769,16 @@ describe("proof-of-love", () => {
         Array.from(newProofB),
         Array.from(newProofC),
         newPubInputs.map((buf: Buffer) => Array.from(buf)),
+        null,
+        INDIVIDUAL,
+        null,
+        LAYOUT_V1,
       )
       .accounts({
         user: user.publicKey,
         tierBadge: tierBadgePDA,
+        nullifierRecord: deriveNullifierPDA(newPubInputs[2], programId)[0],
+        config: configPDA,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
@@ -315,11 +789,74 @@ describe("proof-of-love", () => {
     const badge = await (program.account as any).tierBadge.fetch(tierBadgePDA);
     expect(badge.tier).to.equal(6);
     expect(badge.verifiedAt.toNumber()).to.be.gte(timestamp - 5);
+    expect(Buffer.from(badge.nullifier)).to.deep.equal(newPubInputs[2]);
     console.log("    PDA updated with fresh timestamp ✓");
   });
 
   // ---------------------------------------------------------------------------
-  // Test 3: Invalid tier bounds should fail
+  // Test 3: Refresh with a different nullifier should fail
+  // ---------------------------------------------------------------------------
+
+  it("rejects refresh with a changed nullifier", async () => {
+    const timestamp = Math.floor(Date.now() / 1000);
+    const otherNullifier = BigInt(
+      "0x" +
+        createHash("sha256")
+          .update(Buffer.concat([user.publicKey.toBuffer(), Buffer.from("other")]))
+          .digest("hex"),
+    ).toString();
+
+    const { proof, publicSignals: signals } = await snarkjs.groth16.fullProve(
+      {
+        balance_1: "200000000",
+        balance_2: "200000000",
+        balance_3: "200000000",
+        tier_lower_bound: "100000000",
+        tier_upper_bound: "500000000",
+        nullifier: otherNullifier,
+        timestamp: timestamp.toString(),
+      },
+      WASM_PATH,
+      ZKEY_PATH,
+    );
+
+    try {
+      await program.methods
+        .verifyAndStoreTier(
+          Array.from(encodeProofA(proof.pi_a)),
+          Array.from(encodeProofB(proof.pi_b)),
+          Array.from(encodeProofC(proof.pi_c)),
+          signals.map((s: string) => Array.from(decimalTo32BytesBE(s))),
+          null,
+          INDIVIDUAL,
+          null,
+          LAYOUT_V1,
+        )
+        .accounts({
+          user: user.publicKey,
+          tierBadge: tierBadgePDA,
+          nullifierRecord: deriveNullifierPDA(
+            decimalTo32BytesBE(signals[2]),
+            programId,
+          )[0],
+          config: configPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      expect.fail("Expected transaction to fail — nullifier changed");
+    } catch (err: any) {
+      expect(err.message || "").to.include("NullifierChanged");
+      console.log("    Correctly rejected changed nullifier ✓");
+    }
+
+    // The stored badge keeps its original nullifier
+    const badge = await (program.account as any).tierBadge.fetch(tierBadgePDA);
+    expect(Buffer.from(badge.nullifier)).to.deep.equal(publicInputs[2]);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 4: Invalid tier bounds should fail
   // ---------------------------------------------------------------------------
 
   it("rejects proof with invalid tier bounds", async () => {
@@ -340,10 +877,16 @@ describe("proof-of-love", () => {
           Array.from(proofB),
           Array.from(proofC),
           fakePubInputs.map((buf) => Array.from(buf)),
+          null,
+          INDIVIDUAL,
+          null,
+          LAYOUT_V1,
         )
         .accounts({
           user: user.publicKey,
           tierBadge: tierBadgePDA,
+          nullifierRecord: deriveNullifierPDA(fakePubInputs[2], programId)[0],
+          config: configPDA,
           systemProgram: SystemProgram.programId,
         })
         .rpc();
@@ -359,7 +902,7 @@ describe("proof-of-love", () => {
   });
 
   // ---------------------------------------------------------------------------
-  // Test 4: Revoke expired tier should fail when badge is still valid
+  // Test 5: Revoke expired tier should fail when badge is still valid
   // ---------------------------------------------------------------------------
 
   it("rejects revocation of non-expired badge", async () => {
@@ -369,6 +912,9 @@ describe("proof-of-love", () => {
         .accounts({
           user: user.publicKey,
           tierBadge: tierBadgePDA,
+          expiryBucket: null,
+          votingPower: await votingPowerOf(tierBadgePDA),
+          nullifierRecord: await nullifierRecordOf(tierBadgePDA),
         })
         .rpc();
 
@@ -386,7 +932,7 @@ describe("proof-of-love", () => {
   });
 
   // ---------------------------------------------------------------------------
-  // Test 5: Different user gets a different PDA
+  // Test 6: Different user gets a different PDA
   // ---------------------------------------------------------------------------
 
   it("creates separate PDA for a different user", async () => {
@@ -429,10 +975,19 @@ describe("proof-of-love", () => {
         Array.from(encodeProofB(proof.pi_b)),
         Array.from(encodeProofC(proof.pi_c)),
         signals.map((s: string) => Array.from(decimalTo32BytesBE(s))),
+        null,
+        INDIVIDUAL,
+        null,
+        LAYOUT_V1,
       )
       .accounts({
         user: user2.publicKey,
         tierBadge: pda2,
+        nullifierRecord: deriveNullifierPDA(
+          decimalTo32BytesBE(signals[2]),
+          programId,
+        )[0],
+        config: configPDA,
         systemProgram: SystemProgram.programId,
       })
       .signers([user2])
@@ -455,4 +1010,5447 @@ describe("proof-of-love", () => {
     console.log("    User1 badge: Tier 6 (Moon) — unchanged ✓");
     console.log(`    User2 PDA: ${pda2.toBase58()}`);
   });
+
+  // ---------------------------------------------------------------------------
+  // Test 7: Sensitive config changes wait out the timelock
+  // ---------------------------------------------------------------------------
+
+  it("timelocks sensitive config changes", async () => {
+    // Loosening the proof age (10 → 15 minutes) is queued, not applied
+    await program.methods
+      .updateConfig({ ...noChange, maxProofAgeSeconds: new anchor.BN(900) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    let config = await fetchConfig();
+    expect(config.maxProofAgeSeconds.toNumber()).to.equal(600);
+    expect(config.pendingChange.update.maxProofAgeSeconds.toNumber()).to.equal(
+      900,
+    );
+
+    // Applying before the timelock elapses fails
+    try {
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      expect.fail("Expected apply_config to fail — timelock not elapsed");
+    } catch (err: any) {
+      expect(err.message || "").to.include("TimelockNotElapsed");
+    }
+
+    // After the timelock the change can be applied
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    config = await fetchConfig();
+    expect(config.maxProofAgeSeconds.toNumber()).to.equal(900);
+    expect(config.pendingChange).to.be.null;
+    console.log("    Loosened proof age applied after timelock ✓");
+
+    // Tightening it back bypasses the timelock
+    await program.methods
+      .updateConfig({ ...noChange, maxProofAgeSeconds: new anchor.BN(600) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    config = await fetchConfig();
+    expect(config.maxProofAgeSeconds.toNumber()).to.equal(600);
+    expect(config.pendingChange).to.be.null;
+    console.log("    Tightened proof age applied immediately ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 8: Nullifier backfill and sybil protection
+  // ---------------------------------------------------------------------------
+
+  it("backfills nullifiers idempotently and blocks nullifier reuse", async () => {
+    const [nullifierPDA] = deriveNullifierPDA(publicInputs[2], programId);
+
+    // Badges created by the current program already have a record, so the
+    // backfill leaves it untouched
+    await program.methods
+      .backfillNullifier()
+      .accounts({
+        authority: user.publicKey,
+        config: configPDA,
+        tierBadge: tierBadgePDA,
+        nullifierRecord: nullifierPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const record = await (program.account as any).nullifierRecord.fetch(
+      nullifierPDA,
+    );
+    expect(record.owner.toBase58()).to.equal(user.publicKey.toBase58());
+    expect(Buffer.from(record.nullifier)).to.deep.equal(publicInputs[2]);
+    expect(
+      (await (program.account as any).tierBadge.fetch(tierBadgePDA))
+        .nullifierEnforced,
+    ).to.be.true;
+    console.log("    Existing record left untouched ✓");
+
+    // A second wallet proving with the same nullifier is rejected
+    const sybil = Keypair.generate();
+    const airdropSig = await provider.connection.requestAirdrop(
+      sybil.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL,
+    );
+    await provider.connection.confirmTransaction(airdropSig, "confirmed");
+
+    const { proof, publicSignals: signals } = await snarkjs.groth16.fullProve(
+      {
+        balance_1: "200000000",
+        balance_2: "200000000",
+        balance_3: "200000000",
+        tier_lower_bound: "100000000",
+        tier_upper_bound: "500000000",
+        nullifier: publicSignals[2],
+        timestamp: Math.floor(Date.now() / 1000).toString(),
+      },
+      WASM_PATH,
+      ZKEY_PATH,
+    );
+
+    try {
+      await program.methods
+        .verifyAndStoreTier(
+          Array.from(encodeProofA(proof.pi_a)),
+          Array.from(encodeProofB(proof.pi_b)),
+          Array.from(encodeProofC(proof.pi_c)),
+          signals.map((s: string) => Array.from(decimalTo32BytesBE(s))),
+          null,
+          INDIVIDUAL,
+          null,
+          LAYOUT_V1,
+        )
+        .accounts({
+          user: sybil.publicKey,
+          tierBadge: deriveTierBadgePDA(sybil.publicKey, programId)[0],
+          nullifierRecord: nullifierPDA,
+          config: configPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([sybil])
+        .rpc();
+
+      expect.fail("Expected transaction to fail — nullifier already used");
+    } catch (err: any) {
+      expect(err.message || "").to.include("NullifierAlreadyUsed");
+      console.log("    Correctly rejected nullifier reuse ✓");
+    }
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 9: Minimum wallet age gate
+  // ---------------------------------------------------------------------------
+
+  it("requires an old enough wallet marker when the age gate is on", async () => {
+    const MIN_AGE_SLOTS = 5;
+    const wallet = await fundedKeypair();
+    const [markerPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("wallet_marker"), wallet.publicKey.toBuffer()],
+      programId,
+    );
+
+    // Raising the min age tightens the gate, so it applies immediately
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        minWalletAgeSlots: new anchor.BN(MIN_AGE_SLOTS),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    const submit = async (marker: PublicKey | null) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      return program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          walletMarker: marker,
+        })
+        .signers([wallet])
+        .rpc();
+    };
+    const expectTooNew = async (marker: PublicKey | null) => {
+      try {
+        await submit(marker);
+        expect.fail("Expected transaction to fail — wallet too new");
+      } catch (err: any) {
+        expect(err.message || "").to.include("WalletTooNew");
+      }
+    };
+
+    // No marker at all
+    await expectTooNew(null);
+
+    // A marker that was just created
+    await program.methods
+      .createWalletMarker()
+      .accounts({
+        user: wallet.publicKey,
+        walletMarker: markerPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+    await expectTooNew(markerPDA);
+    console.log("    Rejected missing and fresh markers ✓");
+
+    // Once the marker has aged, verification succeeds
+    await waitSlots(provider.connection, MIN_AGE_SLOTS + 1);
+    await submit(markerPDA);
+    console.log("    Accepted aged marker ✓");
+
+    // Lowering the gate loosens it, so it waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, minWalletAgeSlots: new anchor.BN(0) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).minWalletAgeSlots.toNumber()).to.equal(0);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 10: Post-rotation cooldown
+  // ---------------------------------------------------------------------------
+
+  it("rejects proofs during the cooldown after a VK rotation", async () => {
+    const COOLDOWN_SLOTS = 5;
+    const wallet = await fundedKeypair();
+    const submit = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      return program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    };
+
+    // Lengthening the cooldown tightens it, so it applies immediately
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        postRotationCooldownSlots: new anchor.BN(COOLDOWN_SLOTS),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await program.methods
+      .recordVkRotation()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    const rotationSlot = (await fetchConfig()).vkRotationSlot.toNumber();
+    expect(rotationSlot).to.be.greaterThan(0);
+
+    try {
+      await submit();
+      expect.fail("Expected transaction to fail — inside rotation cooldown");
+    } catch (err: any) {
+      expect(err.message || "").to.include("PostRotationCooldown");
+    }
+    console.log("    Rejected proof inside cooldown ✓");
+
+    // One slot past the boundary, proofs are accepted again
+    await waitSlots(provider.connection, COOLDOWN_SLOTS + 1);
+    await submit();
+    console.log("    Accepted proof after cooldown ✓");
+
+    // Only the authority can record a rotation
+    const stranger = await fundedKeypair();
+    try {
+      await program.methods
+        .recordVkRotation()
+        .accounts({ authority: stranger.publicKey, config: configPDA })
+        .signers([stranger])
+        .rpc();
+      expect.fail("Expected transaction to fail — not the authority");
+    } catch (err: any) {
+      expect(err.message || "").to.include("Unauthorized");
+    }
+
+    // Shortening the cooldown loosens it, so it waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, postRotationCooldownSlots: new anchor.BN(0) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).postRotationCooldownSlots.toNumber()).to.equal(
+      0,
+    );
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 11: Verify and mint a tier token
+  // ---------------------------------------------------------------------------
+
+  it("mints a tier token only on successful verification", async () => {
+    const MOON_TIER = 6;
+    const wallet = await fundedKeypair();
+    const [moonAuthority] = deriveTierMintAuthorityPDA(MOON_TIER, programId);
+    const [oceanAuthority] = deriveTierMintAuthorityPDA(
+      MOON_TIER - 1,
+      programId,
+    );
+    const moonMint = await createMint(provider, moonAuthority);
+    const oceanMint = await createMint(provider, oceanAuthority);
+    const moonAccount = await createTokenAccount(
+      provider,
+      moonMint,
+      wallet.publicKey,
+    );
+    const oceanAccount = await createTokenAccount(
+      provider,
+      oceanMint,
+      wallet.publicKey,
+    );
+    const balance = async (account: PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(account)).value.amount;
+
+    const mintAccounts = (
+      inputs: Buffer[],
+      mint: PublicKey,
+      authority: PublicKey,
+      tokenAccount: PublicKey,
+    ) => {
+      const [mintReceipt] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mint_receipt"), inputs[2], mint.toBuffer()],
+        programId,
+      );
+      return {
+        verify: verifyAccounts(wallet.publicKey, inputs),
+        mint,
+        mintAuthority: authority,
+        tokenAccount,
+        mintReceipt,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      };
+    };
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      let message = "";
+      try {
+        await promise;
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include(error);
+    };
+
+    // A tampered proof mints nothing
+    const tampered = await proveFor(wallet.publicKey);
+    const badInputs = tampered.args[3].map((input) => [...input]);
+    badInputs[3][31] ^= 1;
+    await expectError(
+      program.methods
+        .verifyAndMint(
+          tampered.args[0],
+          tampered.args[1],
+          tampered.args[2],
+          badInputs,
+          null,
+          INDIVIDUAL,
+          null,
+          LAYOUT_V1,
+        )
+        .accounts(
+          mintAccounts(
+            tampered.publicInputs,
+            moonMint,
+            moonAuthority,
+            moonAccount,
+          ),
+        )
+        .signers([wallet])
+        .rpc(),
+      "ProofVerificationFailed",
+    );
+    expect(await balance(moonAccount)).to.equal("0");
+
+    // A mint for another tier is rejected
+    let { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    await expectError(
+      program.methods
+        .verifyAndMint(...args, LAYOUT_V1)
+        .accounts(mintAccounts(inputs, oceanMint, oceanAuthority, oceanAccount))
+        .signers([wallet])
+        .rpc(),
+      "MintTierMismatch",
+    );
+    expect(await balance(oceanAccount)).to.equal("0");
+    console.log("    Nothing minted on failed verification ✓");
+
+    // A valid Moon proof mints one Moon token and stores the badge
+    await program.methods
+      .verifyAndMint(...args, LAYOUT_V1)
+      .accounts(mintAccounts(inputs, moonMint, moonAuthority, moonAccount))
+      .signers([wallet])
+      .rpc();
+    expect(await balance(moonAccount)).to.equal("1");
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.tier).to.equal(MOON_TIER);
+    console.log("    Minted tier token ✓");
+
+    // The receipt already exists, so a second mint against the badge fails
+    ({ args, publicInputs: inputs } = await proveFor(wallet.publicKey));
+    await expectError(
+      program.methods
+        .verifyAndMint(...args, LAYOUT_V1)
+        .accounts(mintAccounts(inputs, moonMint, moonAuthority, moonAccount))
+        .signers([wallet])
+        .rpc(),
+      "already in use",
+    );
+    expect(await balance(moonAccount)).to.equal("1");
+    console.log("    Rejected double mint ✓");
+
+    // The receipt is the identity's, so a badge in another namespace can't
+    // mint the token again either
+    const APP = Array.from(Buffer.from("app-mint"));
+    ({ args, publicInputs: inputs } = await proveFor(wallet.publicKey));
+    const accounts = mintAccounts(inputs, moonMint, moonAuthority, moonAccount);
+    accounts.verify.tierBadge = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tier_badge"),
+        wallet.publicKey.toBuffer(),
+        Buffer.from(APP),
+      ],
+      programId,
+    )[0];
+    await expectError(
+      program.methods
+        .verifyAndMint(...args.slice(0, 6), APP, LAYOUT_V1)
+        .accounts(accounts)
+        .signers([wallet])
+        .rpc(),
+      "already in use",
+    );
+    expect(await balance(moonAccount)).to.equal("1");
+    console.log("    Rejected a second mint from another namespace ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 12: Config snapshot view
+  // ---------------------------------------------------------------------------
+
+  it("returns the stored config parameters from get_config", async () => {
+    const snapshot = await program.methods
+      .getConfig()
+      .accounts({ config: configPDA })
+      .view();
+    const config = await fetchConfig();
+
+    expect(snapshot.version).to.equal(28);
+    expect(snapshot.requireNullifierUniqueness).to.equal(true);
+    expect(snapshot.saneBoundsMax.toString()).to.equal("10000000000000000");
+    expect(snapshot.logVerifications).to.equal(false);
+    expect(snapshot.tierWeights.map((w: any) => w.toNumber())).to.deep.equal([
+      1, 2, 3, 4, 5, 6, 7,
+    ]);
+    expect(snapshot.maxAcceptedTier).to.equal(7);
+    expect(snapshot.strictEpoch).to.equal(false);
+    expect(snapshot.withdrawalRecipient.toBase58()).to.equal(
+      PublicKey.default.toBase58(),
+    );
+    const validUntil = (windows: any[]) =>
+      windows.map((w) => w.validUntil.toString());
+    expect(validUntil(snapshot.vkWindows)).to.deep.equal(
+      validUntil(config.vkWindows),
+    );
+    expect(snapshot.firstVerifyRewardLamports.toNumber()).to.equal(0);
+    expect(snapshot.emitTierMemo).to.equal(false);
+    expect(snapshot.currencyCode).to.deep.equal(Array.from(Buffer.from("USD")));
+    expect(snapshot.deniedNullifierPrefixes).to.deep.equal(
+      config.deniedNullifierPrefixes,
+    );
+    expect(snapshot.anchorExpiryToSubmission).to.equal(
+      config.anchorExpiryToSubmission,
+    );
+    expect(snapshot.maxRefreshesPerWindow).to.equal(
+      config.maxRefreshesPerWindow,
+    );
+    expect(snapshot.refreshWindowSeconds.toString()).to.equal(
+      config.refreshWindowSeconds.toString(),
+    );
+    expect(snapshot.requireOriginRelayer).to.equal(
+      config.requireOriginRelayer,
+    );
+    expect(snapshot.tombstoneBlocksRecreate).to.equal(
+      config.tombstoneBlocksRecreate,
+    );
+    expect(snapshot.minWalletLamports.toString()).to.equal(
+      config.minWalletLamports.toString(),
+    );
+    expect(snapshot.subsidyMinTier).to.equal(config.subsidyMinTier);
+    expect(snapshot.subsidyEpochCapLamports.toString()).to.equal(
+      config.subsidyEpochCapLamports.toString(),
+    );
+    expect(snapshot.restrictBundles).to.equal(config.restrictBundles);
+    expect(
+      snapshot.deniedPrograms.map((key: PublicKey) => key.toBase58()),
+    ).to.deep.equal(
+      config.deniedPrograms.map((key: PublicKey) => key.toBase58()),
+    );
+    expect(snapshot.probationSeconds.toString()).to.equal(
+      config.probationSeconds.toString(),
+    );
+    expect(snapshot.hookProgram.toBase58()).to.equal(
+      config.hookProgram.toBase58(),
+    );
+    expect(snapshot.allowedAccountTypes).to.equal(config.allowedAccountTypes);
+    expect(snapshot.pauser.toBase58()).to.equal(config.pauser.toBase58());
+    expect(snapshot.paused).to.equal(config.paused);
+    expect(snapshot.requireExplicitCreate).to.equal(
+      config.requireExplicitCreate,
+    );
+    expect(snapshot.tierTableFrozen).to.equal(config.tierTableFrozen);
+    expect(snapshot.proverKey.toBase58()).to.equal(config.proverKey.toBase58());
+    for (const field of [
+      "badgeValiditySeconds",
+      "maxProofAgeSeconds",
+      "timelockSlots",
+      "minWalletAgeSlots",
+      "vkRotationSlot",
+      "postRotationCooldownSlots",
+      "maxValiditySeconds",
+    ]) {
+      expect(snapshot[field].toString()).to.equal(config[field].toString());
+    }
+    expect(snapshot.tiers.length).to.equal(config.tiers.length);
+    snapshot.tiers.forEach((tier: any, i: number) => {
+      expect(tier.lower.toString()).to.equal(config.tiers[i].lower.toString());
+      expect(tier.upper.toString()).to.equal(config.tiers[i].upper.toString());
+    });
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 13: Revoke after a refresh at the expiry boundary
+  // ---------------------------------------------------------------------------
+
+  it("rejects a revoke that lands right after a refresh", async () => {
+    const SHORT_VALIDITY = 10;
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const fetchBadge = () =>
+      (program.account as any).tierBadge.fetch(badgePDA);
+    const verify = async (proof: Awaited<ReturnType<typeof proveFor>>) =>
+      program.methods
+        .verifyAndStoreTier(...proof.args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, proof.publicInputs))
+        .signers([wallet])
+        .rpc();
+    // Shorten validity so the badge reaches its expiry during the test
+    await setBadgeValidity(SHORT_VALIDITY);
+    await verify(await proveFor(wallet.publicKey));
+    const expiresAt = (await fetchBadge()).expiresAt.toNumber();
+
+    // Wait until the badge is revocable
+    await waitPastUnixTime(provider.connection, expiresAt);
+
+    // Refresh, then revoke in the very next transaction
+    await verify(await proveFor(wallet.publicKey));
+    try {
+      await program.methods
+        .revokeExpiredTier()
+        .accounts({
+          user: wallet.publicKey,
+          tierBadge: badgePDA,
+          expiryBucket: null,
+          votingPower: await votingPowerOf(badgePDA),
+          nullifierRecord: await nullifierRecordOf(badgePDA),
+        })
+        .signers([wallet])
+        .rpc();
+      expect.fail("Expected transaction to fail — badge was just refreshed");
+    } catch (err: any) {
+      expect(err.message || "").to.include("BadgeNotExpired");
+    }
+
+    const badge = await fetchBadge();
+    expect(badge.expiresAt.toNumber()).to.be.greaterThan(expiresAt);
+    console.log("    Revoke after refresh rejected, badge kept ✓");
+
+    // Restore the 30-day validity window
+    await setBadgeValidity(30 * 24 * 60 * 60);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 14: Client request id dedupe
+  // ---------------------------------------------------------------------------
+
+  it("rejects a retried request with the same client_request_id", async () => {
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const submit = async (requestId: number[]) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      const [requestReceipt] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("request"),
+          wallet.publicKey.toBuffer(),
+          Buffer.from(requestId),
+        ],
+        programId,
+      );
+      return program.methods
+        .verifyAndStoreTier(
+          args[0],
+          args[1],
+          args[2],
+          args[3],
+          requestId,
+          args[5],
+          args[6],
+          LAYOUT_V1,
+        )
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          requestReceipt,
+        })
+        .signers([wallet])
+        .rpc();
+    };
+    const requestId = Array.from(Keypair.generate().publicKey.toBytes()).slice(
+      0,
+      16,
+    );
+
+    await submit(requestId);
+    const firstVerifiedAt = (
+      await (program.account as any).tierBadge.fetch(badgePDA)
+    ).verifiedAt.toNumber();
+
+    // The retry carries a fresh proof but the same request id
+    await new Promise((resolve) => setTimeout(resolve, 1000));
+    try {
+      await submit(requestId);
+      expect.fail("Expected transaction to fail — duplicate request");
+    } catch (err: any) {
+      expect(err.message || "").to.include("DuplicateRequest");
+    }
+    const badge = await (program.account as any).tierBadge.fetch(badgePDA);
+    expect(badge.verifiedAt.toNumber()).to.equal(firstVerifiedAt);
+    console.log("    Retry with the same request id rejected ✓");
+
+    // A new request id is processed normally
+    await submit(requestId.map((byte) => byte ^ 0xff));
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 15: All-zero tier bounds
+  // ---------------------------------------------------------------------------
+
+  it("rejects all-zero tier bounds with ZeroTierBounds", async () => {
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    const zeroed = args[3].map((input, i) =>
+      i < 2 ? new Array(32).fill(0) : [...input],
+    );
+
+    try {
+      await program.methods
+        .verifyAndStoreTier(
+          args[0],
+          args[1],
+          args[2],
+          zeroed,
+          null,
+          args[5],
+          args[6],
+          LAYOUT_V1,
+        )
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+      expect.fail("Expected transaction to fail — zero tier bounds");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ZeroTierBounds");
+    }
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 16: Prover attestation
+  // ---------------------------------------------------------------------------
+
+  it("marks badges attested only with a valid prover signature", async () => {
+    const prover = Keypair.generate();
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+
+    // Registering a prover key is sensitive, so it waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, proverKey: prover.publicKey })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    const submit = async (signer: Keypair | null, withSysvar: boolean) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      const pre = signer
+        ? [
+            Ed25519Program.createInstructionWithPrivateKey({
+              privateKey: signer.secretKey,
+              message: Buffer.concat(inputs),
+            }),
+          ]
+        : [];
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          instructionsSysvar: withSysvar ? SYSVAR_INSTRUCTIONS_PUBKEY : null,
+        })
+        .preInstructions(pre)
+        .signers([wallet])
+        .rpc();
+      return (await (program.account as any).tierBadge.fetch(badgePDA))
+        .proverAttested;
+    };
+    const expectInvalid = async (promise: Promise<unknown>) => {
+      try {
+        await promise;
+        expect.fail("Expected transaction to fail — invalid attestation");
+      } catch (err: any) {
+        expect(err.message || "").to.include("ProverSignatureInvalid");
+      }
+    };
+
+    // Unattested flow
+    expect(await submit(null, false)).to.equal(false);
+
+    // Attested flow
+    expect(await submit(prover, true)).to.equal(true);
+    console.log("    Attested badge recorded ✓");
+
+    // Refreshing without an attestation clears the flag
+    expect(await submit(null, false)).to.equal(false);
+
+    // Signed by the wrong key, or claimed without any signature
+    await expectInvalid(submit(Keypair.generate(), true));
+    await expectInvalid(submit(null, true));
+    console.log("    Rejected forged and missing attestations ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 17: Voluntary downgrade
+  // ---------------------------------------------------------------------------
+
+  it("lets the owner downgrade but never upgrade a badge", async () => {
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, inputs))
+      .signers([wallet])
+      .rpc();
+
+    const downgrade = (tier: number) =>
+      program.methods
+        .downgradeBadge(tier)
+        .accounts({
+          user: wallet.publicKey,
+          tierBadge: badgePDA,
+          config: configPDA,
+          votingPower: deriveVotingPowerPDA(inputs[2], programId)[0],
+        })
+        .signers([wallet])
+        .rpc();
+    const fetchBadge = () =>
+      (program.account as any).tierBadge.fetch(badgePDA);
+
+    // Moon (6) down to Tree (3)
+    const before = await fetchBadge();
+    const sig = await downgrade(3);
+    const after = await fetchBadge();
+    expect(after.tier).to.equal(3);
+    expect(after.tierLowerBound.toNumber()).to.equal(1_000_000);
+    expect(after.tierUpperBound.toNumber()).to.equal(5_000_000);
+    expect(after.expiresAt.toNumber()).to.equal(before.expiresAt.toNumber());
+    console.log("    Downgraded Moon → Tree ✓");
+
+    // A voluntary downgrade gets its own event, not a revocation
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const events = [
+      ...new anchor.EventParser(programId, program.coder).parseLogs(
+        tx!.meta!.logMessages!,
+      ),
+    ];
+    const downgraded = events.find((e) => e.name === "tierDowngraded");
+    expect(downgraded!.data.tierBadge.toBase58()).to.equal(
+      badgePDA.toBase58(),
+    );
+    expect(downgraded!.data.owner.toBase58()).to.equal(
+      wallet.publicKey.toBase58(),
+    );
+    expect(downgraded!.data.from).to.equal(6);
+    expect(downgraded!.data.to).to.equal(3);
+    expect(events.some((e) => e.name === "badgeClosed")).to.equal(false);
+
+    // Same tier, a higher tier and tier 0 are all rejected
+    for (const tier of [3, 6, 0]) {
+      try {
+        await downgrade(tier);
+        expect.fail(`Expected transaction to fail — tier ${tier}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include("InvalidDowngrade");
+      }
+    }
+    expect((await fetchBadge()).tier).to.equal(3);
+    console.log("    Rejected upgrade via downgrade ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 18: Validity cap
+  // ---------------------------------------------------------------------------
+
+  it("rejects a badge validity above max_validity_seconds", async () => {
+    const DAY = 24 * 60 * 60;
+    const expectCapped = async (update: Record<string, unknown>) => {
+      try {
+        await program.methods
+          .updateConfig({ ...noChange, ...update })
+          .accounts({ authority: user.publicKey, config: configPDA })
+          .rpc();
+        expect.fail("Expected transaction to fail — validity above cap");
+      } catch (err: any) {
+        expect(err.message || "").to.include("ValidityExceedsCap");
+      }
+    };
+
+    // The default cap is 365 days
+    expect((await fetchConfig()).maxValiditySeconds.toNumber()).to.equal(
+      365 * DAY,
+    );
+    await expectCapped({ badgeValiditySeconds: new anchor.BN(366 * DAY) });
+
+    // The cap can't be lowered below the current 30-day validity
+    await expectCapped({ maxValiditySeconds: new anchor.BN(29 * DAY) });
+
+    // Lowering the cap tightens it, so it applies immediately
+    await program.methods
+      .updateConfig({ ...noChange, maxValiditySeconds: new anchor.BN(60 * DAY) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).maxValiditySeconds.toNumber()).to.equal(
+      60 * DAY,
+    );
+    await expectCapped({ badgeValiditySeconds: new anchor.BN(90 * DAY) });
+    console.log("    Validity above cap rejected ✓");
+
+    // Raising the cap together with the validity is allowed, via timelock
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        badgeValiditySeconds: new anchor.BN(30 * DAY),
+        maxValiditySeconds: new anchor.BN(365 * DAY),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).maxValiditySeconds.toNumber()).to.equal(
+      365 * DAY,
+    );
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 19: Badge owner is always the signer
+  // ---------------------------------------------------------------------------
+
+  it("rejects writing a badge owned by anyone but the signer", async () => {
+    const owner = await fundedKeypair();
+    const attacker = await fundedKeypair();
+    const [ownerBadge] = deriveTierBadgePDA(owner.publicKey, programId);
+
+    const proof = await proveFor(owner.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...proof.args, LAYOUT_V1)
+      .accounts(verifyAccounts(owner.publicKey, proof.publicInputs))
+      .signers([owner])
+      .rpc();
+
+    // The attacker signs but targets the owner's badge
+    const { args, publicInputs: inputs } = await proveFor(attacker.publicKey);
+    try {
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(attacker.publicKey, inputs),
+          tierBadge: ownerBadge,
+        })
+        .signers([attacker])
+        .rpc();
+      expect.fail("Expected transaction to fail — badge owned by another wallet");
+    } catch (err: any) {
+      expect(err.message || "").to.match(/ConstraintSeeds|OwnerSignerMismatch/);
+    }
+
+    const badge = await (program.account as any).tierBadge.fetch(ownerBadge);
+    expect(badge.owner.toBase58()).to.equal(owner.publicKey.toBase58());
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 20: Compute budget recommendation
+  // ---------------------------------------------------------------------------
+
+  it("stays within RECOMMENDED_VERIFY_COMPUTE_UNITS", async () => {
+    const constant = (name: string) =>
+      Number(
+        (program.idl as any).constants.find((c: any) => c.name === name)
+          .value,
+      );
+    const recommended = constant("RECOMMENDED_VERIFY_COMPUTE_UNITS");
+    const keys = constant("COMPILED_VERIFYING_KEYS");
+    const wallet = await fundedKeypair();
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+    const simulate = async (inputs: number[][]) => {
+      const tx = await program.methods
+        .verifyAndStoreTier(
+          ...args.slice(0, 3),
+          inputs,
+          ...args.slice(4),
+          LAYOUT_V1,
+        )
+        .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+        ])
+        .transaction();
+      return (await provider.connection.simulateTransaction(tx, [wallet]))
+        .value;
+    };
+
+    // First-time verification also initializes the badge and nullifier
+    // record, so it is the most expensive path
+    const value = await simulate(args[3]);
+    expect(value.err).to.equal(null);
+    console.log(
+      `    Measured ${value.unitsConsumed} CU (recommended ${recommended})`,
+    );
+    expect(value.unitsConsumed).to.be.at.most(recommended);
+
+    // A proof made under an old key during an overlap first fails a full
+    // pairing against each newer key. A proof one timestamp off fails the
+    // pairing the same way, which prices each of those extra attempts
+    const shifted = args[3].map((input) => [...input]);
+    shifted[3][31] ^= 1;
+    const failed = await simulate(shifted);
+    expect(JSON.stringify(failed.err)).to.not.equal("null");
+    const oldKey = value.unitsConsumed! + (keys - 1) * failed.unitsConsumed!;
+    console.log(`    Oldest of ${keys} key(s) priced at ${oldKey} CU ✓`);
+    expect(oldKey).to.be.at.most(recommended);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 21: Sweep orphaned nullifier records
+  // ---------------------------------------------------------------------------
+
+  it("sweeps only nullifier records no open badge is registered against", async () => {
+    const orphan = await fundedKeypair();
+    const live = await fundedKeypair();
+    const APP = Array.from(Buffer.from("swept-ns"));
+    const [appBadge] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tier_badge"),
+        orphan.publicKey.toBuffer(),
+        Buffer.from(APP),
+      ],
+      programId,
+    );
+    const verify = async (wallet: Keypair, namespace: number[] | null) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args.slice(0, 6), namespace, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          tierBadge: namespace
+            ? appBadge
+            : deriveTierBadgePDA(wallet.publicKey, programId)[0],
+        })
+        .signers([wallet])
+        .rpc();
+      return deriveNullifierPDA(inputs[2], programId)[0];
+    };
+    const sweepAccounts = (record: PublicKey, payer: PublicKey) => [
+      { pubkey: record, isSigner: false, isWritable: true },
+      { pubkey: payer, isSigner: false, isWritable: true },
+    ];
+    const sweep = (remaining: anchor.web3.AccountMeta[], signer?: Keypair) =>
+      program.methods
+        .sweepOrphanedNullifiers()
+        .accounts({
+          authority: (signer ?? user).publicKey,
+          config: configPDA,
+        })
+        .remainingAccounts(remaining)
+        .signers(signer ? [signer] : [])
+        .rpc();
+    const fetchRecord = (record: PublicKey) =>
+      (program.account as any).nullifierRecord.fetchNullable(record);
+
+    // The orphan wallet holds a short-lived default badge and a namespaced
+    // one, both registered against its one record
+    await setBadgeValidity(10);
+    const orphanRecord = await verify(orphan, null);
+    await setBadgeValidity(30 * 24 * 60 * 60);
+    await verify(orphan, APP);
+    const liveRecord = await verify(live, null);
+    let record = await fetchRecord(orphanRecord);
+    expect(record.badges).to.equal(2);
+    expect(record.payer.toBase58()).to.equal(orphan.publicKey.toBase58());
+
+    // Revoke the default badge once it expires
+    const [orphanBadge] = deriveTierBadgePDA(orphan.publicKey, programId);
+    await waitPastUnixTime(
+      provider.connection,
+      (
+        await (program.account as any).tierBadge.fetch(orphanBadge)
+      ).expiresAt.toNumber(),
+    );
+    const revokeSig = await program.methods
+      .revokeExpiredTier()
+      .accounts({
+        user: orphan.publicKey,
+        tierBadge: orphanBadge,
+        expiryBucket: null,
+        votingPower: await votingPowerOf(orphanBadge),
+        nullifierRecord: orphanRecord,
+      })
+      .signers([orphan])
+      .rpc();
+    expect((await fetchRecord(orphanRecord)).badges).to.equal(1);
+
+    // The revocation event names the closed badge account
+    await provider.connection.confirmTransaction(revokeSig, "confirmed");
+    const revokeTx = await provider.connection.getTransaction(revokeSig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const revokeEvents = [
+      ...new anchor.EventParser(programId, program.coder).parseLogs(
+        revokeTx!.meta!.logMessages!,
+      ),
+    ];
+    const closed = revokeEvents.find((e) => e.name === "badgeClosed");
+    expect(closed!.data.tierBadge.toBase58()).to.equal(orphanBadge.toBase58());
+    expect(closed!.data.owner.toBase58()).to.equal(orphan.publicKey.toBase58());
+    // ...and, since the account is gone, only the closure event
+    expect(revokeEvents.some((e) => e.name === "tierDowngraded")).to.equal(false);
+
+    // Only the authority may sweep
+    const stranger = await fundedKeypair();
+    try {
+      await sweep(sweepAccounts(orphanRecord, orphan.publicKey), stranger);
+      expect.fail("Expected transaction to fail — not the authority");
+    } catch (err: any) {
+      expect(err.message || "").to.include("Unauthorized");
+    }
+
+    // The rent must go back to the wallet that paid for the record
+    try {
+      await sweep(sweepAccounts(orphanRecord, stranger.publicKey));
+      expect.fail("Expected transaction to fail — wrong rent recipient");
+    } catch (err: any) {
+      expect(err.message || "").to.include("InvalidSweepAccounts");
+    }
+
+    // The namespaced badge still backs the orphan's record
+    await sweep([
+      ...sweepAccounts(orphanRecord, orphan.publicKey),
+      ...sweepAccounts(liveRecord, live.publicKey),
+    ]);
+    expect(await fetchRecord(orphanRecord)).to.not.equal(null);
+    expect(await fetchRecord(liveRecord)).to.not.equal(null);
+    console.log("    Record backing a namespaced badge kept ✓");
+
+    // Burning it leaves the record orphaned
+    await program.methods
+      .burnBadge()
+      .accounts({
+        user: orphan.publicKey,
+        tierBadge: appBadge,
+        badgeTombstone: await tombstoneOf(appBadge),
+        expiryBucket: null,
+        votingPower: await votingPowerOf(appBadge),
+        nullifierRecord: orphanRecord,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([orphan])
+      .rpc();
+    record = await fetchRecord(orphanRecord);
+    expect(record.badges).to.equal(0);
+
+    const before = await provider.connection.getBalance(orphan.publicKey);
+    await sweep([
+      ...sweepAccounts(orphanRecord, orphan.publicKey),
+      ...sweepAccounts(liveRecord, live.publicKey),
+    ]);
+
+    expect(await fetchRecord(orphanRecord)).to.equal(null);
+    expect(await provider.connection.getBalance(orphan.publicKey)).to.be.greaterThan(
+      before,
+    );
+    expect(await fetchRecord(liveRecord)).to.not.equal(null);
+    console.log("    Swept orphaned record, kept live one ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 22: Explicit badge creation
+  // ---------------------------------------------------------------------------
+
+  it("separates creates from refreshes in strict mode only", async () => {
+    const lenient = await fundedKeypair();
+    const strict = await fundedKeypair();
+    const submit = async (
+      method: "verifyAndStoreTier" | "createBadge",
+      wallet: Keypair,
+    ) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods[method](...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const expectError = async (promise: Promise<void>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    // Lenient (default): verify_and_store_tier creates and refreshes,
+    // create_badge still refuses existing badges
+    expect((await fetchConfig()).requireExplicitCreate).to.equal(false);
+    await submit("verifyAndStoreTier", lenient);
+    await submit("verifyAndStoreTier", lenient);
+    await expectError(submit("createBadge", lenient), "BadgeAlreadyExists");
+
+    // Turning strict mode on takes effect immediately
+    await program.methods
+      .updateConfig({ ...noChange, requireExplicitCreate: true })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).requireExplicitCreate).to.equal(true);
+
+    await expectError(submit("verifyAndStoreTier", strict), "BadgeNotCreated");
+    await submit("createBadge", strict);
+    await expectError(submit("createBadge", strict), "BadgeAlreadyExists");
+    await submit("verifyAndStoreTier", strict);
+    await submit("verifyAndStoreTier", lenient);
+    console.log("    Strict mode requires create_badge for first badges ✓");
+
+    // Turning it back off waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, requireExplicitCreate: false })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).requireExplicitCreate).to.equal(true);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).requireExplicitCreate).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 23: Tier ladder view
+  // ---------------------------------------------------------------------------
+
+  it("lists every tier from the active tier table", async () => {
+    const tiers = await program.methods
+      .listTiers()
+      .accounts({ config: configPDA })
+      .view();
+    const config = await fetchConfig();
+
+    expect(tiers.length).to.equal(config.tiers.length);
+    const names = tiers.map((t: any) =>
+      Buffer.from(t.nameBytes).toString("utf8").replace(/\0+$/, ""),
+    );
+    expect(names).to.deep.equal([
+      "Seed",
+      "Sprout",
+      "Tree",
+      "Mountain",
+      "Ocean",
+      "Moon",
+      "Sun",
+    ]);
+    tiers.forEach((t: any, i: number) => {
+      expect(t.tier).to.deep.equal(tierValue(i + 1));
+      expect(t.lower.toString()).to.equal(config.tiers[i].lower.toString());
+      expect(t.upper.toString()).to.equal(config.tiers[i].upper.toString());
+    });
+    console.log("    Tier ladder matches the config ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 24: Malformed proof_b
+  // ---------------------------------------------------------------------------
+
+  it("rejects an out-of-field proof_b with ProofMalformed", async () => {
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    const modulus = Array.from(decimalTo32BytesBE(CURVE_ORDER.toString()));
+
+    for (const [half, value] of [
+      [1, modulus],
+      [3, new Array(32).fill(0xff)],
+    ] as const) {
+      const proofB = [...args[1]];
+      proofB.splice(half * 32, 32, ...value);
+      try {
+        await program.methods
+          .verifyAndStoreTier(
+            args[0],
+            proofB,
+            args[2],
+            args[3],
+            args[4],
+            args[5],
+            args[6],
+            LAYOUT_V1,
+          )
+          .accounts(verifyAccounts(wallet.publicKey, inputs))
+          .signers([wallet])
+          .rpc();
+        expect.fail("Expected transaction to fail — proof_b out of field");
+      } catch (err: any) {
+        expect(err.message || "").to.include("ProofMalformed");
+      }
+    }
+    console.log("    Rejected out-of-field proof_b halves ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 25: Pauser role
+  // ---------------------------------------------------------------------------
+
+  it("lets the pauser pause but not change parameters", async () => {
+    const pauser = await fundedKeypair();
+    const stranger = await fundedKeypair();
+    const setPaused = (paused: boolean, signer: Keypair) =>
+      program.methods
+        .setPaused(paused)
+        .accounts({ pauser: signer.publicKey, config: configPDA })
+        .signers([signer])
+        .rpc();
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    // The authority assigns the pauser through a timelocked change
+    await program.methods
+      .updateConfig({ ...noChange, pauser: pauser.publicKey })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).pauser.toBase58()).to.equal(
+      pauser.publicKey.toBase58(),
+    );
+
+    await expectError(setPaused(true, stranger), "NotPauser");
+    await setPaused(true, pauser);
+    expect((await fetchConfig()).paused).to.equal(true);
+
+    // ...but it holds no other config powers
+    const config = await fetchConfig();
+    for (const update of [
+      { badgeValiditySeconds: new anchor.BN(60) },
+      { tiers: config.tiers },
+    ]) {
+      await expectError(
+        program.methods
+          .updateConfig({ ...noChange, ...update })
+          .accounts({ authority: pauser.publicKey, config: configPDA })
+          .signers([pauser])
+          .rpc(),
+        "Unauthorized",
+      );
+    }
+
+    await setPaused(false, pauser);
+    const after = await fetchConfig();
+    expect(after.paused).to.equal(false);
+    expect(after.badgeValiditySeconds.toString()).to.equal(
+      config.badgeValiditySeconds.toString(),
+    );
+    console.log("    Pauser toggled pause, config changes rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 26: Account types
+  // ---------------------------------------------------------------------------
+
+  it("records allowed account types and emits them in TierVerified", async () => {
+    const INSTITUTION = { institution: {} };
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    const verifyAs = (accountType: object) =>
+      program.methods
+        .verifyAndStoreTier(
+          args[0],
+          args[1],
+          args[2],
+          args[3],
+          args[4],
+          accountType,
+          args[6],
+          LAYOUT_V1,
+        )
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    const updateMask = (mask: number) =>
+      program.methods
+        .updateConfig({ ...noChange, allowedAccountTypes: mask })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+
+    // Only individuals are allowed by default
+    expect((await fetchConfig()).allowedAccountTypes).to.equal(0b001);
+    try {
+      await verifyAs(INSTITUTION);
+      expect.fail("Expected transaction to fail — account type not allowed");
+    } catch (err: any) {
+      expect(err.message || "").to.include("AccountTypeNotAllowed");
+    }
+
+    // Empty or unknown masks are rejected outright
+    for (const mask of [0, 0b1000]) {
+      try {
+        await updateMask(mask);
+        expect.fail("Expected transaction to fail — invalid account type mask");
+      } catch (err: any) {
+        expect(err.message || "").to.include("InvalidConfig");
+      }
+    }
+
+    // Allow institutions alongside individuals
+    await updateMask(0b011);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    const sig = await verifyAs(INSTITUTION);
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.accountType).to.deep.equal(INSTITUTION);
+
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(programId, program.coder);
+    const events = [...parser.parseLogs(tx!.meta!.logMessages!)];
+    const verified = events.find((e) => e.name === "tierVerified");
+    expect(verified, "TierVerified event").to.not.equal(undefined);
+    expect(verified!.data.owner.toBase58()).to.equal(
+      wallet.publicKey.toBase58(),
+    );
+    expect(verified!.data.tier).to.equal(badge.tier);
+    expect(verified!.data.accountType).to.deep.equal(INSTITUTION);
+    expect(verified!.data.currencyCode).to.deep.equal(badge.currencyCode);
+    expect(verified!.data.tierBadge.toBase58()).to.equal(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0].toBase58(),
+    );
+    console.log("    Account type gated, stored and emitted ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 27: Non-canonical nullifier
+  // ---------------------------------------------------------------------------
+
+  it("rejects a nullifier at or above the BN254 scalar field order", async () => {
+    const SCALAR_ORDER = BigInt(
+      "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+    );
+    const wallet = await fundedKeypair();
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+
+    // r + n encodes the same field element as n in different bytes
+    const nullifier = BigInt("0x" + publicInputs[2].toString("hex"));
+    const aliased = decimalTo32BytesBE((nullifier + SCALAR_ORDER).toString());
+    const inputs = [publicInputs[0], publicInputs[1], aliased, publicInputs[3]];
+    try {
+      await program.methods
+        .verifyAndStoreTier(
+          args[0],
+          args[1],
+          args[2],
+          inputs.map((buf) => Array.from(buf)),
+          args[4],
+          args[5],
+          args[6],
+          LAYOUT_V1,
+        )
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+      expect.fail("Expected transaction to fail — non-canonical nullifier");
+    } catch (err: any) {
+      expect(err.message || "").to.include("NonCanonicalNullifier");
+    }
+    expect(
+      await provider.connection.getAccountInfo(
+        deriveNullifierPDA(aliased, programId)[0],
+      ),
+    ).to.equal(null);
+    console.log("    Rejected aliased nullifier ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 28: Verification hook
+  // ---------------------------------------------------------------------------
+
+  it("calls the configured hook and rolls back when it fails", async () => {
+    const MOCK_HOOK_ID = new PublicKey(
+      "3hErqKZPaZwy7evE3Ajf3wtP9r2f2EJ6G6XuxAymfgmq",
+    );
+    const setHook = async (hookProgram: PublicKey) => {
+      await program.methods
+        .updateConfig({ ...noChange, hookProgram })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+    const verify = async (
+      wallet: Keypair,
+      hookProgram: PublicKey | null,
+      inputs: Record<string, string> = {},
+    ) => {
+      const proof = await proveFor(wallet.publicKey, inputs);
+      return program.methods
+        .verifyAndStoreTier(...proof.args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, proof.publicInputs, { hookProgram }),
+        )
+        .signers([wallet])
+        .rpc();
+    };
+
+    // The program can't be its own hook
+    try {
+      await program.methods
+        .updateConfig({ ...noChange, hookProgram: programId })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      expect.fail("Expected transaction to fail — self hook");
+    } catch (err: any) {
+      expect(err.message || "").to.include("InvalidConfig");
+    }
+
+    await setHook(MOCK_HOOK_ID);
+    const wallet = await fundedKeypair();
+    try {
+      await verify(wallet, null);
+      expect.fail("Expected transaction to fail — hook account missing");
+    } catch (err: any) {
+      expect(err.message || "").to.include("HookProgramMismatch");
+    }
+
+    const sig = await verify(wallet, MOCK_HOOK_ID);
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    expect(tx!.meta!.logMessages).to.include(
+      `Program log: Mock hook: badge ${badgePDA.toBase58()} at tier 6`,
+    );
+
+    // The mock rejects Sun badges; the failed hook undoes the verification
+    const sun = await fundedKeypair();
+    try {
+      await verify(sun, MOCK_HOOK_ID, {
+        balance_1: "600000000",
+        balance_2: "600000000",
+        balance_3: "600000000",
+        tier_lower_bound: "500000000",
+        tier_upper_bound: "10000000000000",
+      });
+      expect.fail("Expected transaction to fail — hook rejected");
+    } catch (err: any) {
+      expect(err.message || "").to.include("Rejected");
+    }
+    expect(
+      await provider.connection.getAccountInfo(
+        deriveTierBadgePDA(sun.publicKey, programId)[0],
+      ),
+    ).to.equal(null);
+
+    await setHook(PublicKey.default);
+    console.log("    Hook called, and its failure rolled back ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 29: Badge probation
+  // ---------------------------------------------------------------------------
+
+  it("keeps new badges in probation until trusted_after", async () => {
+    const PROBATION = 15;
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const fetchBadge = () =>
+      (program.account as any).tierBadge.fetch(badgePDA);
+    const verify = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      return program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc({ commitment: "confirmed" });
+    };
+    const requireTrusted = () =>
+      program.methods
+        .requireTrustedBadge()
+        .accounts({ tierBadge: badgePDA, nullifierRecord: null })
+        .rpc();
+
+    // Badges created without probation are trusted right away
+    await program.methods
+      .requireTrustedBadge()
+      .accounts({ tierBadge: tierBadgePDA, nullifierRecord: null })
+      .rpc();
+
+    // A longer probation tightens the config, so it applies immediately
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        probationSeconds: new anchor.BN(PROBATION),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    const sig = await verify();
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const trustedAfter = (await fetchBadge()).trustedAfter.toNumber();
+    expect(trustedAfter).to.be.closeTo(tx!.blockTime! + PROBATION, 1);
+
+    try {
+      await requireTrusted();
+      expect.fail("Expected transaction to fail — badge in probation");
+    } catch (err: any) {
+      expect(err.message || "").to.include("BadgeInProbation");
+    }
+
+    // Refreshing doesn't restart the window
+    await verify();
+    expect((await fetchBadge()).trustedAfter.toNumber()).to.equal(trustedAfter);
+
+    await waitPastUnixTime(provider.connection, trustedAfter);
+    await requireTrusted();
+    console.log("    Badge trusted once probation ended ✓");
+
+    // Shortening probation back to zero waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, probationSeconds: new anchor.BN(0) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).probationSeconds.toNumber()).to.equal(0);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 30: Bundle restrictions
+  // ---------------------------------------------------------------------------
+
+  it("rejects verifications bundled with denied programs", async () => {
+    const MEMO_PROGRAM_ID = new PublicKey(
+      "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+    );
+    const memo = new TransactionInstruction({
+      programId: MEMO_PROGRAM_ID,
+      keys: [],
+      data: Buffer.from("bundled"),
+    });
+    const budget = ComputeBudgetProgram.setComputeUnitLimit({
+      units: 400_000,
+    });
+    const wallet = await fundedKeypair();
+    const verifyWith = async (
+      pre: TransactionInstruction[],
+      withSysvar = true,
+    ) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, inputs, {
+            bundleSysvar: withSysvar ? SYSVAR_INSTRUCTIONS_PUBKEY : null,
+          }),
+        )
+        .preInstructions(pre)
+        .signers([wallet])
+        .rpc();
+    };
+    const expectDisallowed = async (promise: Promise<void>) => {
+      try {
+        await promise;
+        expect.fail("Expected transaction to fail — disallowed bundle");
+      } catch (err: any) {
+        expect(err.message || "").to.include("DisallowedInstructionBundle");
+      }
+    };
+
+    // Deny the memo program; the list itself waits out the timelock
+    const deniedPrograms = new Array(4).fill(PublicKey.default);
+    deniedPrograms[0] = MEMO_PROGRAM_ID;
+    await program.methods
+      .updateConfig({ ...noChange, deniedPrograms })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    // Without restrict_bundles the list isn't enforced
+    await verifyWith([memo]);
+
+    // Enabling the restriction tightens the config, so it applies at once
+    await program.methods
+      .updateConfig({ ...noChange, restrictBundles: true })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    await verifyWith([budget]);
+    await expectDisallowed(verifyWith([budget, memo]));
+    await expectDisallowed(verifyWith([budget], false));
+    console.log("    Allowed bundle accepted, denied bundle rejected ✓");
+
+    await program.methods
+      .updateConfig({ ...noChange, restrictBundles: false })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).restrictBundles).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 31: Referrals
+  // ---------------------------------------------------------------------------
+
+  it("registers a referral alongside a verification", async () => {
+    const referrer = await fundedKeypair();
+    const referee = await fundedKeypair();
+    const stranger = Keypair.generate();
+    const deriveReferralPDA = (wallet: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("referral"), wallet.toBuffer()],
+        programId,
+      )[0];
+    const badgeIn = (wallet: PublicKey, namespace: number[] | null) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("tier_badge"),
+          wallet.toBuffer(),
+          ...(namespace ? [Buffer.from(namespace)] : []),
+        ],
+        programId,
+      )[0];
+    const referVia = async (
+      wallet: Keypair,
+      referrerKey: PublicKey,
+      referrerNamespace: number[] | null = null,
+    ) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      return program.methods
+        .verifyWithReferral(
+          ...args,
+          LAYOUT_V1,
+          referrerKey,
+          referrerNamespace,
+        )
+        .accounts({
+          verify: verifyAccounts(wallet.publicKey, inputs),
+          referrerBadge: badgeIn(referrerKey, referrerNamespace),
+          referral: deriveReferralPDA(wallet.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc({ commitment: "confirmed" });
+    };
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    // The referrer needs a badge of their own first
+    await expectError(
+      referVia(referee, stranger.publicKey),
+      "ReferrerWithoutBadge",
+    );
+    await expectError(referVia(referee, referee.publicKey), "SelfReferral");
+    expect(
+      await provider.connection.getAccountInfo(
+        deriveReferralPDA(referee.publicKey),
+      ),
+    ).to.equal(null);
+
+    const { args, publicInputs: inputs } = await proveFor(referrer.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(referrer.publicKey, inputs))
+      .signers([referrer])
+      .rpc();
+
+    const sig = await referVia(referee, referrer.publicKey);
+    const referral = await (program.account as any).referral.fetch(
+      deriveReferralPDA(referee.publicKey),
+    );
+    expect(referral.referee.toBase58()).to.equal(referee.publicKey.toBase58());
+    expect(referral.referrer.toBase58()).to.equal(
+      referrer.publicKey.toBase58(),
+    );
+
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(programId, program.coder);
+    const registered = [...parser.parseLogs(tx!.meta!.logMessages!)].find(
+      (e) => e.name === "referralRegistered",
+    );
+    expect(registered!.data.referrer.toBase58()).to.equal(
+      referrer.publicKey.toBase58(),
+    );
+
+    // Referrals are one-time and immutable
+    await expectError(referVia(referee, referrer.publicKey), "already in use");
+    console.log("    Referral registered once, invalid referrers rejected ✓");
+
+    // A referrer holding only an app-scoped badge names its namespace
+    const APP = Array.from(Buffer.from("referapp"));
+    const appReferrer = await fundedKeypair();
+    const appReferee = await fundedKeypair();
+    const appProof = await proveFor(appReferrer.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...appProof.args.slice(0, 6), APP, LAYOUT_V1)
+      .accounts({
+        ...verifyAccounts(appReferrer.publicKey, appProof.publicInputs),
+        tierBadge: badgeIn(appReferrer.publicKey, APP),
+      })
+      .signers([appReferrer])
+      .rpc();
+    await expectError(
+      referVia(appReferee, appReferrer.publicKey),
+      "ReferrerWithoutBadge",
+    );
+    await referVia(appReferee, appReferrer.publicKey, APP);
+    const appReferral = await (program.account as any).referral.fetch(
+      deriveReferralPDA(appReferee.publicKey),
+    );
+    expect(appReferral.referrer.toBase58()).to.equal(
+      appReferrer.publicKey.toBase58(),
+    );
+    console.log("    Referrer vouched for by a namespaced badge ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 32: Minimum wallet balance
+  // ---------------------------------------------------------------------------
+
+  it("requires the configured minimum wallet balance", async () => {
+    const wallet = await fundedKeypair();
+    const verify = async (signer = wallet) => {
+      const { args, publicInputs: inputs } = await proveFor(signer.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(signer.publicKey, inputs))
+        .signers([signer])
+        .rpc();
+    };
+    const verifyDelegated = async (owner: Keypair, custodian: Keypair) => {
+      const { args, publicInputs } = await proveFor(owner.publicKey);
+      await program.methods
+        .verifyDelegated(...args, LAYOUT_V1, SIG_SCHEME_ED25519)
+        .accounts({
+          custodian: custodian.publicKey,
+          owner: owner.publicKey,
+          tierBadge: deriveTierBadgePDA(owner.publicKey, programId)[0],
+          nullifierRecord: deriveNullifierPDA(publicInputs[2], programId)[0],
+          walletMarker: null,
+          requestReceipt: null,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          badgeTombstone: null,
+          rewardClaim: null,
+          shared: sharedAccounts(),
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: owner.secretKey,
+            message: Buffer.concat([
+              Buffer.from("proof-of-love:delegate"),
+              custodian.publicKey.toBuffer(),
+              Buffer.alloc(8), // default namespace
+              ...publicInputs,
+            ]),
+          }),
+        ])
+        .signers([custodian])
+        .rpc();
+    };
+    const setMinimum = (lamports: number) =>
+      program.methods
+        .updateConfig({
+          ...noChange,
+          minWalletLamports: new anchor.BN(lamports),
+        })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+
+    // Create the badge first; refreshes pay no rent and the provider pays
+    // the fees, so the wallet's balance is exact during later checks
+    await verify();
+    const balance = await provider.connection.getBalance(wallet.publicKey);
+
+    // Raising the minimum tightens the config, so it applies immediately
+    await setMinimum(balance);
+    await verify();
+    console.log("    Wallet at the threshold accepted ✓");
+
+    await setMinimum(balance + 1);
+    try {
+      await verify();
+      expect.fail("Expected transaction to fail — wallet underfunded");
+    } catch (err: any) {
+      expect(err.message || "").to.include("WalletUnderfunded");
+    }
+    console.log("    Wallet one lamport short rejected ✓");
+
+    // A first verification is judged on the balance before its rent
+    const fresh = await fundedKeypair();
+    await setMinimum(await provider.connection.getBalance(fresh.publicKey));
+    await verify(fresh);
+    console.log("    New wallet at the threshold accepted despite rent ✓");
+
+    // A delegated verification reads the owner, not the paying custodian
+    const minimum = (await fetchConfig()).minWalletLamports.toNumber();
+    const owner = Keypair.generate();
+    const custodian = await fundedKeypair();
+    try {
+      await verifyDelegated(owner, custodian);
+      expect.fail("Expected transaction to fail — owner underfunded");
+    } catch (err: any) {
+      expect(err.message || "").to.include("WalletUnderfunded");
+    }
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(owner.publicKey, minimum),
+      "confirmed",
+    );
+    await verifyDelegated(owner, custodian);
+    console.log("    Delegated verification checks the owner's balance ✓");
+
+    await setMinimum(0);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).minWalletLamports.toNumber()).to.equal(0);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 33: Dry-run tier classification
+  // ---------------------------------------------------------------------------
+
+  it("classifies arbitrary bounds against the tier table", async () => {
+    const config = await fetchConfig();
+    const classify = (lower: anchor.BN, upper: anchor.BN) =>
+      program.methods
+        .classifyBounds(lower, upper)
+        .accounts({ config: configPDA })
+        .view();
+
+    // Every tier's exact bounds map back to that tier
+    for (const [i, t] of config.tiers.entries()) {
+      expect(await classify(t.lower, t.upper)).to.deep.equal(
+        tierValue(i + 1),
+      );
+    }
+
+    // Bounds one off either edge of a tier match nothing
+    const tier = config.tiers[2];
+    const one = new anchor.BN(1);
+    for (const [lower, upper] of [
+      [tier.lower.sub(one), tier.upper],
+      [tier.lower.add(one), tier.upper],
+      [tier.lower, tier.upper.sub(one)],
+      [tier.lower, tier.upper.add(one)],
+      [new anchor.BN(0), new anchor.BN(0)],
+    ]) {
+      let message = "";
+      try {
+        await classify(lower, upper);
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include("InvalidTier");
+    }
+    console.log("    Tier bounds classify on-chain ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 34: Treasury rent subsidy
+  // ---------------------------------------------------------------------------
+
+  it("pays badge rent from the treasury for subsidized tiers", async () => {
+    const [treasuryPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury")],
+      programId,
+    );
+    const balanceOf = (key: PublicKey) => provider.connection.getBalance(key);
+    const applyUpdate = async (update: object) => {
+      await program.methods
+        .updateConfig({ ...noChange, ...update })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+    /** Verify a fresh wallet, returning the lamports it spent */
+    const verifyNew = async (inputs: Record<string, string> = {}) => {
+      const wallet = await fundedKeypair();
+      const before = await balanceOf(wallet.publicKey);
+      const { args, publicInputs } = await proveFor(wallet.publicKey, inputs);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, publicInputs, {
+            treasury: treasuryPDA,
+          }),
+        )
+        .signers([wallet])
+        .rpc();
+      return before - (await balanceOf(wallet.publicKey));
+    };
+
+    // Only the authority can fund the treasury
+    const stranger = await fundedKeypair();
+    try {
+      await program.methods
+        .fundTreasury(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL))
+        .accounts({
+          authority: stranger.publicKey,
+          config: configPDA,
+          treasury: treasuryPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([stranger])
+        .rpc();
+      expect.fail("Expected transaction to fail — not the authority");
+    } catch (err: any) {
+      expect(err.message || "").to.include("Unauthorized");
+    }
+    await program.methods
+      .fundTreasury(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL))
+      .accounts({
+        authority: user.publicKey,
+        config: configPDA,
+        treasury: treasuryPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // Subsidize Moon (tier 6) and above
+    await applyUpdate({
+      subsidyMinTier: 6,
+      subsidyEpochCapLamports: new anchor.BN(anchor.web3.LAMPORTS_PER_SOL),
+    });
+    const badgeRent =
+      await provider.connection.getMinimumBalanceForRentExemption(
+        (program.account as any).tierBadge.size,
+      );
+
+    // An Ocean (tier 5) wallet pays its own badge rent
+    const treasuryBefore = await balanceOf(treasuryPDA);
+    const oceanSpent = await verifyNew({
+      balance_1: "30000000",
+      balance_2: "30000000",
+      balance_3: "30000000",
+      tier_lower_bound: "25000000",
+      tier_upper_bound: "100000000",
+    });
+    expect(await balanceOf(treasuryPDA)).to.equal(treasuryBefore);
+    console.log("    Unsubsidized tier paid its own rent ✓");
+
+    // A Moon wallet gets the badge rent back from the treasury
+    const moonSpent = await verifyNew();
+    expect(oceanSpent - moonSpent).to.equal(badgeRent);
+    expect(await balanceOf(treasuryPDA)).to.equal(treasuryBefore - badgeRent);
+    expect(
+      (
+        await (program.account as any).treasury.fetch(treasuryPDA)
+      ).subsidizedLamports.toNumber(),
+    ).to.equal(badgeRent);
+    console.log("    Subsidized tier's rent paid by the treasury ✓");
+
+    // A delegated verification refunds the owner, not the custodian
+    const owner = Keypair.generate();
+    const custodian = await fundedKeypair();
+    const { args, publicInputs } = await proveFor(owner.publicKey);
+    const custodianBefore = await balanceOf(custodian.publicKey);
+    await program.methods
+      .verifyDelegated(...args, LAYOUT_V1, SIG_SCHEME_ED25519)
+      .accounts({
+        custodian: custodian.publicKey,
+        owner: owner.publicKey,
+        tierBadge: deriveTierBadgePDA(owner.publicKey, programId)[0],
+        nullifierRecord: deriveNullifierPDA(publicInputs[2], programId)[0],
+        walletMarker: null,
+        requestReceipt: null,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        badgeTombstone: null,
+        rewardClaim: null,
+        shared: sharedAccounts({ treasury: treasuryPDA }),
+        systemProgram: SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: owner.secretKey,
+          message: Buffer.concat([
+            Buffer.from("proof-of-love:delegate"),
+            custodian.publicKey.toBuffer(),
+            Buffer.alloc(8), // default namespace
+            ...publicInputs,
+          ]),
+        }),
+      ])
+      .signers([custodian])
+      .rpc();
+    expect(await balanceOf(owner.publicKey)).to.equal(badgeRent);
+    expect(await balanceOf(custodian.publicKey)).to.be.below(
+      custodianBefore - badgeRent,
+    );
+    console.log("    Delegated subsidy paid to the owner ✓");
+
+    // Lowering the epoch cap applies immediately; past it, subsidies fail
+    await program.methods
+      .updateConfig({ ...noChange, subsidyEpochCapLamports: new anchor.BN(0) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    try {
+      await verifyNew();
+      expect.fail("Expected transaction to fail — epoch cap reached");
+    } catch (err: any) {
+      expect(err.message || "").to.include("TreasuryInsufficient");
+    }
+    console.log("    Subsidy past the epoch cap rejected ✓");
+
+    await applyUpdate({ subsidyMinTier: 0 });
+    expect((await fetchConfig()).subsidyMinTier).to.equal(0);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 35: Compressed proofs
+  // ---------------------------------------------------------------------------
+
+  it("verifies a proof sent with compressed points", async () => {
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    const [proofA, proofB, proofC, ...rest] = args;
+    const compressed = [
+      Array.from(compressG1(Buffer.from(proofA as number[]))),
+      Array.from(compressG2(Buffer.from(proofB as number[]))),
+      Array.from(compressG1(Buffer.from(proofC as number[]))),
+    ];
+
+    // Points that aren't on the curve fail before verification
+    try {
+      await program.methods
+        .verifyAndStoreTierCompressed(
+          Array(32).fill(0x3f),
+          compressed[1],
+          compressed[2],
+          ...rest,
+          LAYOUT_V1,
+        )
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+      expect.fail("Expected transaction to fail — undecodable proof_a");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ProofDecompressionFailed");
+    }
+    console.log("    Undecodable compressed point rejected ✓");
+
+    // Compressed proofs take the same public input layouts
+    const [publicInputs, ...options] = rest;
+    const [lower, upper, nullifier, timestamp] = publicInputs as number[][];
+    await program.methods
+      .verifyAndStoreTierCompressed(
+        ...compressed,
+        [nullifier, lower, upper, timestamp],
+        ...options,
+        LAYOUT_V2,
+      )
+      .accounts(verifyAccounts(wallet.publicKey, inputs))
+      .signers([wallet])
+      .rpc();
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(badge.tier).to.equal(6);
+    expect(Buffer.from(badge.nullifier)).to.deep.equal(inputs[2]);
+    console.log("    Compressed proof verified and badge written ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 36: Unbounded top tier
+  // ---------------------------------------------------------------------------
+
+  it("treats the top tier's upper bound as unbounded", async () => {
+    const config = await fetchConfig();
+    const sun = config.tiers[config.tiers.length - 1];
+    const classify = (upper: anchor.BN) =>
+      program.methods
+        .classifyBounds(sun.lower, upper)
+        .accounts({ config: configPDA })
+        .view();
+
+    // Any ceiling at, just above or far above the cap is still Sun
+    const u64Max = new anchor.BN("18446744073709551615");
+    for (const upper of [sun.upper, sun.upper.add(new anchor.BN(1)), u64Max]) {
+      expect(await classify(upper)).to.deep.equal(tierValue(7));
+    }
+    try {
+      await classify(sun.upper.sub(new anchor.BN(1)));
+      expect.fail("Expected view to fail — ceiling below the cap");
+    } catch (err: any) {
+      expect(err.message || "").to.include("InvalidTier");
+    }
+    console.log("    Top tier classifies at and above its cap ✓");
+
+    // A balance above the cap, proven under a larger ceiling, verifies
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey, {
+      balance_1: "20000000000000", // $200B — above the table's cap
+      balance_2: "20000000000000",
+      balance_3: "20000000000000",
+      tier_lower_bound: sun.lower.toString(),
+      tier_upper_bound: "10000000000000000", // MAX_BALANCE
+    });
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, inputs))
+      .signers([wallet])
+      .rpc();
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.tier).to.equal(7);
+    expect(badge.tierUpperBound.toString()).to.equal("10000000000000000");
+    console.log("    Balance above the cap verified as Sun ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 37: Badge namespaces
+  // ---------------------------------------------------------------------------
+
+  it("keeps app-scoped badges in separate namespaces", async () => {
+    const wallet = await fundedKeypair();
+    const APP_ONE = Array.from(Buffer.from("app-one!"));
+    const APP_TWO = Array.from(Buffer.from("app-two!"));
+    const badgeFor = (namespace: number[]) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("tier_badge"),
+          wallet.publicKey.toBuffer(),
+          Buffer.from(namespace),
+        ],
+        programId,
+      )[0];
+    const verifyIn = async (namespace: number[] | null) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args.slice(0, 6), namespace, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          tierBadge: namespace
+            ? badgeFor(namespace)
+            : deriveTierBadgePDA(wallet.publicKey, programId)[0],
+        })
+        .signers([wallet])
+        .rpc();
+    };
+    const fetchBadge = (address: PublicKey) =>
+      (program.account as any).tierBadge.fetchNullable(address);
+
+    // The default badge keeps its original address; each namespace gets its
+    // own, all sharing the wallet's nullifier
+    await setBadgeValidity(10);
+    await verifyIn(null);
+    await verifyIn(APP_ONE);
+    await verifyIn(APP_TWO);
+    await setBadgeValidity(30 * 24 * 60 * 60);
+
+    const defaultBadge = await fetchBadge(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(defaultBadge.namespace).to.deep.equal(Array(8).fill(0));
+    const appOne = await fetchBadge(badgeFor(APP_ONE));
+    const appTwo = await fetchBadge(badgeFor(APP_TWO));
+    expect(appOne.namespace).to.deep.equal(APP_ONE);
+    expect(appTwo.namespace).to.deep.equal(APP_TWO);
+    expect(appOne.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    console.log("    Default and two namespaced badges coexist ✓");
+
+    // Revoking one namespace leaves the others in place
+    await waitPastUnixTime(provider.connection, appOne.expiresAt.toNumber());
+    await program.methods
+      .revokeExpiredTier()
+      .accounts({
+        user: wallet.publicKey,
+        tierBadge: badgeFor(APP_ONE),
+        expiryBucket: null,
+        votingPower: await votingPowerOf(badgeFor(APP_ONE)),
+        nullifierRecord: await nullifierRecordOf(badgeFor(APP_ONE)),
+      })
+      .signers([wallet])
+      .rpc();
+    expect(await fetchBadge(badgeFor(APP_ONE))).to.equal(null);
+    expect(await fetchBadge(badgeFor(APP_TWO))).to.not.equal(null);
+    expect(
+      await fetchBadge(deriveTierBadgePDA(wallet.publicKey, programId)[0]),
+    ).to.not.equal(null);
+    console.log("    Namespaced revoke left the other badges ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 38: Health check
+  // ---------------------------------------------------------------------------
+
+  it("reports config invariants through health_check", async () => {
+    const TIER_TABLE_CONTIGUOUS = 1 << 0;
+    const VALIDITY_WITHIN_CAP = 1 << 1;
+    const VK_HASH_MATCHES = 1 << 2;
+    const NOT_PAUSED = 1 << 3;
+    const ALL_CHECKS =
+      TIER_TABLE_CONTIGUOUS | VALIDITY_WITHIN_CAP | VK_HASH_MATCHES | NOT_PAUSED;
+
+    // Hash the verifying key the way parse-vk.js encodes it on-chain
+    const vk = JSON.parse(
+      fs.readFileSync(
+        path.join(CIRCUIT_BUILD_DIR, "keys", "verification_key.json"),
+        "utf-8",
+      ),
+    );
+    const vkHash = createHash("sha256")
+      .update(
+        Buffer.concat([
+          encodeProofC(vk.vk_alpha_1),
+          encodeProofB(vk.vk_beta_2),
+          encodeProofB(vk.vk_gamma_2),
+          encodeProofB(vk.vk_delta_2),
+          ...vk.IC.map((point: string[]) => encodeProofC(point)),
+        ]),
+      )
+      .digest();
+    const healthCheck = (expected: Buffer) =>
+      program.methods
+        .healthCheck(Array.from(expected))
+        .accounts({ config: configPDA })
+        .view();
+
+    expect(await healthCheck(vkHash)).to.equal(ALL_CHECKS);
+    console.log("    Healthy deployment passes every check ✓");
+
+    // A different expected key clears only its bit
+    const otherHash = Buffer.from(vkHash);
+    otherHash[0] ^= 1;
+    expect(await healthCheck(otherHash)).to.equal(
+      ALL_CHECKS & ~VK_HASH_MATCHES,
+    );
+
+    // Pausing clears NOT_PAUSED; update_config refuses broken tier tables
+    // and validity windows, so those bits only flip on a corrupted account
+    await program.methods
+      .updateConfig({ ...noChange, paused: true })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect(await healthCheck(vkHash)).to.equal(ALL_CHECKS & ~NOT_PAUSED);
+    console.log("    Mismatched key and pause flip their bits ✓");
+
+    // Unpausing through update_config waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, paused: false })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).paused).to.equal(true);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect(await healthCheck(vkHash)).to.equal(ALL_CHECKS);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 39: Raw range badges
+  // ---------------------------------------------------------------------------
+
+  it("stores raw proven ranges in a RangeBadge", async () => {
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey, {
+      tier_lower_bound: "150000000", // $1.5M — not a tier boundary
+      tier_upper_bound: "250000000",
+    });
+    const [rangeBadgePDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("range_badge"), wallet.publicKey.toBuffer()],
+      programId,
+    );
+    const accounts = {
+      user: wallet.publicKey,
+      rangeBadge: rangeBadgePDA,
+      nullifierRecord: deriveNullifierPDA(inputs[2], programId)[0],
+      config: configPDA,
+      systemProgram: SystemProgram.programId,
+    };
+    const [proofA, proofB, proofC] = args;
+    const verifyRange = (lower: string, upper: string) =>
+      program.methods
+        .verifyRange(proofA, proofB, proofC, [
+          Array.from(decimalTo32BytesBE(lower)),
+          Array.from(decimalTo32BytesBE(upper)),
+          Array.from(inputs[2]),
+          Array.from(inputs[3]),
+        ])
+        .accounts(accounts)
+        .signers([wallet])
+        .rpc();
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      let message = "";
+      try {
+        await promise;
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include(error);
+    };
+
+    // Empty, inverted and oversized ranges fail before verification
+    for (const [lower, upper] of [
+      ["250000000", "250000000"],
+      ["250000000", "150000000"],
+      ["150000000", "10000000000000001"],
+      ["150000000", "18446744073709551616"], // 2^64
+    ]) {
+      await expectError(verifyRange(lower, upper), "InvalidRange");
+    }
+    console.log("    Invalid ranges rejected ✓");
+
+    await verifyRange("150000000", "250000000");
+    const badge = await (program.account as any).rangeBadge.fetch(
+      rangeBadgePDA,
+    );
+    expect(badge.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(badge.lowerBound.toString()).to.equal("150000000");
+    expect(badge.upperBound.toString()).to.equal("250000000");
+    expect(Buffer.from(badge.nullifier)).to.deep.equal(inputs[2]);
+    expect(badge.expiresAt.toNumber()).to.be.greaterThan(
+      badge.verifiedAt.toNumber(),
+    );
+    // The range badge keeps the nullifier's record from being swept
+    const record = await (program.account as any).nullifierRecord.fetch(
+      accounts.nullifierRecord,
+    );
+    expect(record.badges).to.equal(1);
+    console.log("    Range badge stores the raw proven bounds ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 40: Badge burning
+  // ---------------------------------------------------------------------------
+
+  it("leaves a tombstone when a badge is burned", async () => {
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const APP = Array.from(Buffer.from("app-one!"));
+    const appBadge = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tier_badge"),
+        wallet.publicKey.toBuffer(),
+        Buffer.from(APP),
+      ],
+      programId,
+    )[0];
+    const verify = async (
+      owner: Keypair,
+      namespace: number[] | null = null,
+    ) => {
+      const { args, publicInputs: inputs } = await proveFor(owner.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args.slice(0, 6), namespace, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(owner.publicKey, inputs),
+          tierBadge: namespace
+            ? appBadge
+            : deriveTierBadgePDA(owner.publicKey, programId)[0],
+          badgeTombstone: deriveTombstonePDA(inputs[2], programId)[0],
+        })
+        .signers([owner])
+        .rpc();
+    };
+    const setBlocking = (block: boolean) =>
+      program.methods
+        .updateConfig({ ...noChange, tombstoneBlocksRecreate: block })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+
+    await verify(wallet);
+    const tombstonePDA = await tombstoneOf(badgePDA);
+    const burnSig = await program.methods
+      .burnBadge()
+      .accounts({
+        user: wallet.publicKey,
+        tierBadge: badgePDA,
+        badgeTombstone: tombstonePDA,
+        expiryBucket: null,
+        votingPower: await votingPowerOf(badgePDA),
+        nullifierRecord: await nullifierRecordOf(badgePDA),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+
+    // Burning closes the account without revoking anything
+    await provider.connection.confirmTransaction(burnSig, "confirmed");
+    const burnTx = await provider.connection.getTransaction(burnSig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const burnEvents = [
+      ...new anchor.EventParser(programId, program.coder).parseLogs(
+        burnTx!.meta!.logMessages!,
+      ),
+    ];
+    const burnClosed = burnEvents.find((e) => e.name === "badgeClosed");
+    expect(burnClosed!.data.tierBadge.toBase58()).to.equal(badgePDA.toBase58());
+    expect(burnClosed!.data.owner.toBase58()).to.equal(
+      wallet.publicKey.toBase58(),
+    );
+    expect(burnEvents.some((e) => e.name === "tierDowngraded")).to.equal(false);
+
+    expect(await provider.connection.getAccountInfo(badgePDA)).to.equal(null);
+    const tombstone = await (program.account as any).badgeTombstone.fetch(
+      tombstonePDA,
+    );
+    expect(tombstone.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(tombstone.tier).to.equal(6);
+    expect(tombstone.burnedAt.toNumber()).to.be.greaterThan(0);
+    console.log("    Badge closed and tombstone written ✓");
+
+    // Blocking re-creation tightens the config, so it applies immediately
+    await setBlocking(true);
+    try {
+      await verify(wallet);
+      expect.fail("Expected transaction to fail — badge was burned");
+    } catch (err: any) {
+      expect(err.message || "").to.include("BadgeBurned");
+    }
+
+    // The tombstone is the identity's, so another namespace is blocked too
+    try {
+      await verify(wallet, APP);
+      expect.fail("Expected transaction to fail — identity was burned");
+    } catch (err: any) {
+      expect(err.message || "").to.include("BadgeBurned");
+    }
+    expect(await provider.connection.getAccountInfo(appBadge)).to.equal(null);
+    const fresh = await fundedKeypair();
+    await verify(fresh);
+    console.log("    Burned identity can't re-create a badge, others can ✓");
+
+    // The tombstone survives the failed re-creations
+    expect(await provider.connection.getAccountInfo(tombstonePDA)).to.not.equal(
+      null,
+    );
+
+    await setBlocking(false);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).tombstoneBlocksRecreate).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 41: Origin relayer
+  // ---------------------------------------------------------------------------
+
+  it("keeps refreshes on the badge's origin relayer when required", async () => {
+    const wallet = await fundedKeypair();
+    const relayer = await fundedKeypair();
+    const other = await fundedKeypair();
+    const verify = async (via: Keypair | null) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, inputs, {
+            relayer: via ? via.publicKey : null,
+          }),
+        )
+        .signers(via ? [wallet, via] : [wallet])
+        .rpc();
+    };
+
+    await verify(relayer);
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.originRelayer.toBase58()).to.equal(
+      relayer.publicKey.toBase58(),
+    );
+
+    // Off by default: any submitter may refresh
+    await verify(other);
+    console.log("    Origin relayer recorded; refreshes unrestricted by default ✓");
+
+    // Requiring the origin relayer tightens the config, so it applies now
+    await program.methods
+      .updateConfig({ ...noChange, requireOriginRelayer: true })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await verify(relayer);
+    for (const via of [other, null]) {
+      try {
+        await verify(via);
+        expect.fail("Expected transaction to fail — wrong relayer");
+      } catch (err: any) {
+        expect(err.message || "").to.include("RelayerMismatch");
+      }
+    }
+    console.log("    Refresh through another relayer rejected ✓");
+
+    await program.methods
+      .updateConfig({ ...noChange, requireOriginRelayer: false })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).requireOriginRelayer).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 42: Delegated verification
+  // ---------------------------------------------------------------------------
+
+  it("lets a custodian verify with the owner's signed authorization", async () => {
+    const owner = Keypair.generate(); // never signs or pays a transaction
+    const custodian = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(owner.publicKey, programId);
+    const { args, publicInputs: inputs } = await proveFor(owner.publicKey);
+    const authorization = (signer: Keypair, forCustodian: PublicKey) =>
+      Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: signer.secretKey,
+        message: Buffer.concat([
+          Buffer.from("proof-of-love:delegate"),
+          forCustodian.toBuffer(),
+          Buffer.alloc(8), // default namespace
+          ...inputs,
+        ]),
+      });
+    const [lower, upper, nullifier, timestamp] = args[3];
+    const verifyDelegated = (
+      pre: anchor.web3.TransactionInstruction,
+      layoutArgs: unknown[] = [...args, LAYOUT_V1],
+    ) =>
+      program.methods
+        .verifyDelegated(...layoutArgs, SIG_SCHEME_ED25519)
+        .accounts({
+          custodian: custodian.publicKey,
+          owner: owner.publicKey,
+          tierBadge: badgePDA,
+          nullifierRecord: deriveNullifierPDA(inputs[2], programId)[0],
+          walletMarker: null,
+          requestReceipt: null,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          badgeTombstone: null,
+          rewardClaim: null,
+          shared: sharedAccounts(),
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([pre])
+        .signers([custodian])
+        .rpc();
+
+    // Signed by another key, or by the owner for a different custodian
+    for (const pre of [
+      authorization(Keypair.generate(), custodian.publicKey),
+      authorization(owner, Keypair.generate().publicKey),
+    ]) {
+      try {
+        await verifyDelegated(pre);
+        expect.fail("Expected transaction to fail — forged authorization");
+      } catch (err: any) {
+        expect(err.message || "").to.include("DelegationSignatureInvalid");
+      }
+    }
+    console.log("    Forged authorizations rejected ✓");
+
+    // Sent nullifier-first, still authorized over the circuit's order
+    await verifyDelegated(authorization(owner, custodian.publicKey), [
+      ...args.slice(0, 3),
+      [nullifier, lower, upper, timestamp],
+      ...args.slice(4),
+      LAYOUT_V2,
+    ]);
+    const badge = await (program.account as any).tierBadge.fetch(badgePDA);
+    expect(badge.owner.toBase58()).to.equal(owner.publicKey.toBase58());
+    expect(badge.tier).to.equal(6);
+    expect(badge.sigScheme).to.equal(SIG_SCHEME_ED25519);
+    const record = await (program.account as any).nullifierRecord.fetch(
+      deriveNullifierPDA(inputs[2], programId)[0],
+    );
+    expect(record.owner.toBase58()).to.equal(owner.publicKey.toBase58());
+    console.log("    Custodian created a badge owned by the user ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 43: Refresh limit
+  // ---------------------------------------------------------------------------
+
+  it("caps refreshes per window and resets when the window rolls over", async () => {
+    const WINDOW_SECONDS = 20;
+    const applyPending = async () => {
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+
+    // Setting a limit applies now; shortening the window waits
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        maxRefreshesPerWindow: 2,
+        refreshWindowSeconds: new anchor.BN(WINDOW_SECONDS),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await applyPending();
+
+    // One proof, resubmitted, so the refreshes land well inside a window
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    const verify = () =>
+      program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    const expectLimited = async () => {
+      try {
+        await verify();
+        expect.fail("Expected transaction to fail — refresh limit");
+      } catch (err: any) {
+        expect(err.message || "").to.include("RefreshLimitReached");
+      }
+    };
+
+    await verify(); // creation doesn't count
+    await verify();
+    await verify();
+    await expectLimited();
+    console.log("    Third refresh in the window rejected ✓");
+
+    await new Promise((resolve) =>
+      setTimeout(resolve, (WINDOW_SECONDS + 2) * 1000),
+    );
+    await verify();
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.refreshCount).to.equal(1);
+    console.log("    Limit reset once the window rolled over ✓");
+
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        maxRefreshesPerWindow: 0,
+        refreshWindowSeconds: new anchor.BN(24 * 60 * 60),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await applyPending();
+    expect((await fetchConfig()).maxRefreshesPerWindow).to.equal(0);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 44: Public input layouts
+  // ---------------------------------------------------------------------------
+
+  it("decodes public inputs in each layout and rejects unknown ones", async () => {
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    const [lower, upper, nullifier, timestamp] = args[3];
+    const submit = (publicInputs: number[][], layout: number) =>
+      program.methods
+        .verifyAndStoreTier(
+          args[0],
+          args[1],
+          args[2],
+          publicInputs,
+          args[4],
+          args[5],
+          args[6],
+          layout,
+        )
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+
+    try {
+      await submit(args[3], 9);
+      expect.fail("Expected transaction to fail — unknown layout");
+    } catch (err: any) {
+      expect(err.message || "").to.include("UnknownLayoutVersion");
+    }
+    console.log("    Unknown layout rejected ✓");
+
+    await submit([nullifier, lower, upper, timestamp], LAYOUT_V2);
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const badge = await (program.account as any).tierBadge.fetch(badgePDA);
+    expect(badge.tier).to.equal(6);
+    expect(Buffer.from(badge.nullifier)).to.deep.equal(Buffer.from(nullifier));
+    console.log("    Nullifier-first layout verified ✓");
+
+    // The same proof in the circuit's own order refreshes the same badge
+    await submit(args[3], LAYOUT_V1);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 45: Expiry anchoring
+  // ---------------------------------------------------------------------------
+
+  it("anchors expiry to the proof's timestamp or to submission", async () => {
+    const wallet = await fundedKeypair();
+    const provedAt = Math.floor(Date.now() / 1000) - 300;
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey, {
+      timestamp: provedAt.toString(),
+    });
+    const verify = () =>
+      program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    const fetchExpiry = async () =>
+      (
+        await (program.account as any).tierBadge.fetch(
+          deriveTierBadgePDA(wallet.publicKey, programId)[0],
+        )
+      ).expiresAt.toNumber();
+    const validity = (await fetchConfig()).badgeValiditySeconds.toNumber();
+
+    // Default: the proof's own timestamp
+    await verify();
+    expect(await fetchExpiry()).to.equal(provedAt + validity);
+    console.log("    Expiry anchored to proof time ✓");
+
+    // Anchoring to submission lengthens badges, so it waits out the timelock
+    await program.methods
+      .updateConfig({ ...noChange, anchorExpiryToSubmission: true })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).anchorExpiryToSubmission).to.equal(false);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    const before = Math.floor(Date.now() / 1000);
+    await verify();
+    const anchored = (await fetchExpiry()) - validity;
+    expect(anchored).to.be.greaterThan(provedAt + 250);
+    expect(anchored).to.be.within(before - 30, before + 30);
+    console.log("    Expiry anchored to submission time ✓");
+
+    await program.methods
+      .updateConfig({ ...noChange, anchorExpiryToSubmission: false })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).anchorExpiryToSubmission).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 46: Expiry buckets
+  // ---------------------------------------------------------------------------
+
+  it("lists badges by expiry day and moves them on refresh", async () => {
+    const DAY = 24 * 60 * 60;
+    const wallet = await fundedKeypair();
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const validity = (await fetchConfig()).badgeValiditySeconds.toNumber();
+    const createBucket = async (day: number) => {
+      const [bucket] = deriveExpiryBucketPDA(day, programId);
+      await program.methods
+        .createExpiryBucket(new anchor.BN(day))
+        .accounts({
+          payer: user.publicKey,
+          expiryBucket: bucket,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      return bucket;
+    };
+    const listed = async (bucket: PublicKey) =>
+      (
+        await program.methods
+          .listExpiringBadges(0)
+          .accounts({ expiryBucket: bucket })
+          .view()
+      ).map((key: PublicKey) => key.toBase58());
+    const expiryDay = (inputs: Buffer[], seconds: number) =>
+      Math.floor((Number(inputs[3].readBigUInt64BE(24)) + seconds) / DAY);
+    const verify = (
+      proof: { args: any; publicInputs: Buffer[] },
+      expiryBucket: PublicKey,
+      previousExpiryBucket: PublicKey | null = null,
+    ) =>
+      program.methods
+        .verifyAndStoreTier(...proof.args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, proof.publicInputs, {
+            expiryBucket,
+            previousExpiryBucket,
+          }),
+        )
+        .signers([wallet])
+        .rpc();
+
+    const first = await proveFor(wallet.publicKey);
+    const firstDay = expiryDay(first.publicInputs, validity);
+    const firstBucket = await createBucket(firstDay);
+    const wrongBucket = await createBucket(firstDay + 2);
+    try {
+      await verify(first, wrongBucket);
+      expect.fail("Expected transaction to fail — wrong expiry day");
+    } catch (err: any) {
+      expect(err.message || "").to.include("InvalidExpiryBucket");
+    }
+    await verify(first, firstBucket);
+    expect(await listed(firstBucket)).to.deep.equal([badgePDA.toBase58()]);
+    console.log("    Badge listed under its expiry day ✓");
+
+    // A day longer validity moves the next expiry to the following day
+    await setBadgeValidity(validity + DAY);
+    const second = await proveFor(wallet.publicKey);
+    const nextBucket = await createBucket(
+      expiryDay(second.publicInputs, validity + DAY),
+    );
+    try {
+      await verify(second, nextBucket);
+      expect.fail("Expected transaction to fail — old bucket left out");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ExpiryBucketRequired");
+    }
+    await verify(second, nextBucket, firstBucket);
+    expect(await listed(firstBucket)).to.deep.equal([]);
+    expect(await listed(nextBucket)).to.deep.equal([badgePDA.toBase58()]);
+    console.log("    Refresh moved the badge to its new expiry day ✓");
+    await setBadgeValidity(validity);
+
+    // Burning the badge drops it from its bucket, which it can't skip
+    const nullifier = second.publicInputs[2];
+    const burn = (expiryBucket: PublicKey | null) =>
+      program.methods
+        .burnBadge()
+        .accounts({
+          user: wallet.publicKey,
+          tierBadge: badgePDA,
+          badgeTombstone: deriveTombstonePDA(nullifier, programId)[0],
+          expiryBucket,
+          votingPower: deriveVotingPowerPDA(nullifier, programId)[0],
+          nullifierRecord: deriveNullifierPDA(nullifier, programId)[0],
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+    try {
+      await burn(null);
+      expect.fail("Expected transaction to fail — listing bucket left out");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ExpiryBucketRequired");
+    }
+    await burn(nextBucket);
+    expect(await listed(nextBucket)).to.deep.equal([]);
+    console.log("    Burn dropped the badge from its bucket ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 47: Denied nullifier prefixes
+  // ---------------------------------------------------------------------------
+
+  it("rejects nullifiers matching a denied prefix only", async () => {
+    const blocked = await fundedKeypair();
+    const allowed = await fundedKeypair();
+    const blockedProof = await proveFor(blocked.publicKey);
+    const allowedProof = await proveFor(allowed.publicKey);
+    const unused = { len: 0, bytes: Array(8).fill(0) };
+    const prefixes = (...denied: object[]) => [
+      ...denied,
+      ...Array(4 - denied.length).fill(unused),
+    ];
+    const updatePrefixes = (deniedNullifierPrefixes: object[]) =>
+      program.methods
+        .updateConfig({ ...noChange, deniedNullifierPrefixes })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+
+    // Denying is immediate
+    const prefix = Array.from(blockedProof.publicInputs[2].subarray(0, 4));
+    await updatePrefixes(
+      prefixes({ len: 4, bytes: [...prefix, 0, 0, 0, 0] }),
+    );
+    expect((await fetchConfig()).deniedNullifierPrefixes[0].len).to.equal(4);
+
+    try {
+      await program.methods
+        .verifyAndStoreTier(...blockedProof.args, LAYOUT_V1)
+        .accounts(verifyAccounts(blocked.publicKey, blockedProof.publicInputs))
+        .signers([blocked])
+        .rpc();
+      expect.fail("Expected transaction to fail — denied nullifier prefix");
+    } catch (err: any) {
+      expect(err.message || "").to.include("NullifierDenied");
+    }
+    console.log("    Matching nullifier rejected ✓");
+
+    await program.methods
+      .verifyAndStoreTier(...allowedProof.args, LAYOUT_V1)
+      .accounts(verifyAccounts(allowed.publicKey, allowedProof.publicInputs))
+      .signers([allowed])
+      .rpc();
+    console.log("    Non-matching nullifier verified ✓");
+
+    // Lifting the denial waits out the timelock
+    await updatePrefixes(prefixes());
+    expect((await fetchConfig()).deniedNullifierPrefixes[0].len).to.equal(4);
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).deniedNullifierPrefixes[0].len).to.equal(0);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 48: Composite gate
+  // ---------------------------------------------------------------------------
+
+  it("reports whether a badge passes a composite gate and why not", async () => {
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, inputs))
+      .signers([wallet])
+      .rpc();
+    const tierBadge = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const gate = (minTier: number, minRemainingSeconds: number) =>
+      program.methods
+        .gateCheck(minTier, false, true, new anchor.BN(minRemainingSeconds))
+        .accounts({ tierBadge, config: configPDA, nullifierRecord: null })
+        .view();
+
+    expect(await gate(6, 60)).to.deep.equal({ passed: true, reason: 0 });
+    expect(await gate(7, 60)).to.deep.equal({ passed: false, reason: 1 });
+    const validity = (await fetchConfig()).badgeValiditySeconds.toNumber();
+    expect(await gate(6, validity + 60)).to.deep.equal({
+      passed: false,
+      reason: 4,
+    });
+    console.log("    Gate passes, and reports tier and horizon failures ✓");
+
+    // A horizon up to the validity cap is a fair question...
+    const cap = (await fetchConfig()).maxValiditySeconds.toNumber();
+    expect(await gate(6, cap)).to.deep.equal({ passed: false, reason: 4 });
+    // ...but one beyond it can never pass, so it's reported as a caller bug
+    try {
+      await gate(6, cap + 1);
+      expect.fail("Expected view to fail — horizon beyond the validity cap");
+    } catch (err: any) {
+      expect(err.message || "").to.include("HorizonExceedsMaxValidity");
+    }
+    console.log("    Horizon beyond max validity rejected ✓");
+
+    // Lowering the cap applies immediately, but this badge was verified
+    // under the old one, so a horizon within its own window still passes
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        badgeValiditySeconds: new anchor.BN(60),
+        maxValiditySeconds: new anchor.BN(120),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect(await gate(6, 121)).to.deep.equal({ passed: true, reason: 0 });
+    try {
+      await gate(6, validity + 60);
+      expect.fail("Expected view to fail — horizon beyond the badge's window");
+    } catch (err: any) {
+      expect(err.message || "").to.include("HorizonExceedsMaxValidity");
+    }
+    console.log("    Older badge gated on its own window after a lower cap ✓");
+
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        badgeValiditySeconds: new anchor.BN(validity),
+        maxValiditySeconds: new anchor.BN(cap),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).maxValiditySeconds.toNumber()).to.equal(cap);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 49: Tier high-water mark
+  // ---------------------------------------------------------------------------
+
+  it("keeps the tier high-water mark when a later proof is lower", async () => {
+    const wallet = await fundedKeypair();
+    const tierBadge = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const verify = async (inputs: Record<string, string> = {}) => {
+      const proof = await proveFor(wallet.publicKey, inputs);
+      await program.methods
+        .verifyAndStoreTier(...proof.args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, proof.publicInputs))
+        .signers([wallet])
+        .rpc();
+      return (program.account as any).tierBadge.fetch(tierBadge);
+    };
+
+    let badge = await verify();
+    expect([badge.tier, badge.tierHighWater]).to.deep.equal([6, 6]);
+
+    // A Tree-tier proof lowers the current tier only
+    badge = await verify({
+      balance_1: "2000000",
+      balance_2: "2000000",
+      balance_3: "2000000",
+      tier_lower_bound: "1000000",
+      tier_upper_bound: "5000000",
+    });
+    expect([badge.tier, badge.tierHighWater]).to.deep.equal([3, 6]);
+    console.log("    Tier dropped, high-water mark kept ✓");
+
+    const gate = (useHighWater: boolean) =>
+      program.methods
+        .gateCheck(6, useHighWater, false, new anchor.BN(0))
+        .accounts({ tierBadge, config: configPDA, nullifierRecord: null })
+        .view();
+    expect(await gate(false)).to.deep.equal({ passed: false, reason: 1 });
+    expect(await gate(true)).to.deep.equal({ passed: true, reason: 0 });
+    console.log("    Gate can check the high-water mark instead ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 50: Caller-supplied verifying key
+  //
+  // Only runs against a program built with `--features test-vk`.
+  // ---------------------------------------------------------------------------
+
+  it("verifies against a caller-supplied key in test-vk builds", async function () {
+    if ((program.methods as any).verifyWithTestKey === undefined) {
+      console.log("    Program built without test-vk, skipping");
+      this.skip();
+    }
+
+    // A fresh phase-2 contribution gives a key the program has never seen
+    const localZkey = path.join(
+      os.tmpdir(),
+      `wealth_tier_local_${Date.now()}.zkey`,
+    );
+    await snarkjs.zKey.contribute(ZKEY_PATH, localZkey, "local", "test-vk");
+    const localVk = await snarkjs.zKey.exportVerificationKey(localZkey);
+    const pinnedVk = JSON.parse(
+      fs.readFileSync(
+        path.join(CIRCUIT_BUILD_DIR, "keys", "verification_key.json"),
+        "utf-8",
+      ),
+    );
+    // Compressed points in the order health::vk_hash hashes them
+    const encodeVk = (vk: any) =>
+      Buffer.concat([
+        compressG1(encodeProofC(vk.vk_alpha_1)),
+        compressG2(encodeProofB(vk.vk_beta_2)),
+        compressG2(encodeProofB(vk.vk_gamma_2)),
+        compressG2(encodeProofB(vk.vk_delta_2)),
+        ...vk.IC.map((point: string[]) => compressG1(encodeProofC(point))),
+      ]);
+
+    const wallet = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(
+      wallet.publicKey,
+      {},
+      localZkey,
+    );
+    fs.rmSync(localZkey);
+    const [proofA, proofB, proofC, signals] = args;
+    const verifyWith = (vk: Buffer) =>
+      program.methods
+        .verifyWithTestKey(proofA, proofB, proofC, signals, vk)
+        .accounts({ user: wallet.publicKey })
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+        ])
+        .signers([wallet])
+        .rpc();
+
+    await verifyWith(encodeVk(localVk));
+    console.log("    Proof verifies against its own local key ✓");
+
+    // Neither the pinned key nor the production path accepts it
+    try {
+      await verifyWith(encodeVk(pinnedVk));
+      expect.fail("Expected transaction to fail — proof is for another key");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ProofVerificationFailed");
+    }
+    try {
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+      expect.fail("Expected transaction to fail — proof is for another key");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ProofVerificationFailed");
+    }
+    console.log("    Pinned key rejects the local proof ✓");
+
+    // A truncated key is rejected before any pairing
+    try {
+      await verifyWith(encodeVk(localVk).subarray(32));
+      expect.fail("Expected transaction to fail — key is truncated");
+    } catch (err: any) {
+      expect(err.message || "").to.include("InvalidVerifyingKey");
+    }
+    console.log("    Malformed key rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 51: Currency code
+  // ---------------------------------------------------------------------------
+
+  it("records the tier table's currency on the config and badges", async () => {
+    const USD = Array.from(Buffer.from("USD"));
+    const EUR = Array.from(Buffer.from("EUR"));
+    const wallet = await fundedKeypair();
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const verify = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const setCurrency = async (code: number[]) => {
+      await program.methods
+        .updateConfig({ ...noChange, currencyCode: code })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+
+    // Default: USD
+    expect((await fetchConfig()).currencyCode).to.deep.equal(USD);
+    await verify();
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).currencyCode,
+    ).to.deep.equal(USD);
+    console.log("    Config and badge default to USD ✓");
+
+    // Re-denominating the table waits out the timelock, and badges verified
+    // afterwards carry the new code
+    await setCurrency(EUR);
+    expect((await fetchConfig()).currencyCode).to.deep.equal(EUR);
+    await verify();
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).currencyCode,
+    ).to.deep.equal(EUR);
+    console.log("    EUR round-trips through the badge ✓");
+
+    // Codes must be three uppercase ASCII letters
+    try {
+      await program.methods
+        .updateConfig({
+          ...noChange,
+          currencyCode: Array.from(Buffer.from("eur")),
+        })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      expect.fail("Expected transaction to fail — lowercase currency code");
+    } catch (err: any) {
+      expect(err.message || "").to.include("InvalidConfig");
+    }
+    console.log("    Malformed currency code rejected ✓");
+
+    await setCurrency(USD);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 52: Verify and stake
+  // ---------------------------------------------------------------------------
+
+  it("stakes into a tier-gated vault only on a passing verification", async () => {
+    const MOON_TIER = 6;
+    const wallet = await fundedKeypair();
+    const pool = Keypair.generate();
+    const stakeMint = await createMint(provider, user.publicKey);
+    const source = await createTokenAccount(
+      provider,
+      stakeMint,
+      wallet.publicKey,
+    );
+    const vault = await createTokenAccount(provider, stakeMint, pool.publicKey);
+    await mintTokens(provider, stakeMint, source, 1_000);
+    const poolFor = (vaultKey: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("stake_pool"), vaultKey.toBuffer()],
+        programId,
+      )[0];
+    const createPool = (
+      vaultKey: PublicKey,
+      authority: Keypair,
+      tier: number,
+    ) =>
+      program.methods
+        .createStakePool(tier)
+        .accounts({
+          payer: user.publicKey,
+          authority: authority.publicKey,
+          vault: vaultKey,
+          stakePool: poolFor(vaultKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Only the vault's owner can set its minimum
+    try {
+      await createPool(vault, wallet, 1);
+      expect.fail("Expected transaction to fail — not the vault's owner");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ConstraintTokenOwner");
+    }
+    await createPool(vault, pool, MOON_TIER);
+    const sunPool = Keypair.generate();
+    const sunVault = await createTokenAccount(
+      provider,
+      stakeMint,
+      sunPool.publicKey,
+    );
+    await createPool(sunVault, sunPool, MOON_TIER + 1);
+    console.log("    Pools registered by their vault owners ✓");
+    const balance = async (account: PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(account)).value.amount;
+
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const receiptFor = (minTier: number, vaultKey = vault) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("stake_receipt"),
+          badgePDA.toBuffer(),
+          vaultKey.toBuffer(),
+          Buffer.from([minTier]),
+        ],
+        programId,
+      )[0];
+    const stake = (
+      proof: Awaited<ReturnType<typeof proveFor>>,
+      amount: number,
+      inputs = proof.args[3],
+      vaultKey = vault,
+      minTier = MOON_TIER,
+    ) =>
+      program.methods
+        .verifyAndStake(
+          proof.args[0],
+          proof.args[1],
+          proof.args[2],
+          inputs,
+          null,
+          INDIVIDUAL,
+          null,
+          LAYOUT_V1,
+          new anchor.BN(amount),
+        )
+        .accounts({
+          verify: verifyAccounts(wallet.publicKey, proof.publicInputs),
+          source,
+          stakePool: poolFor(vaultKey),
+          vault: vaultKey,
+          stakeReceipt: receiptFor(minTier, vaultKey),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+
+    // A Moon proof meets a Moon pool's minimum
+    await stake(await proveFor(wallet.publicKey), 400);
+    expect(await balance(vault)).to.equal("400");
+    expect(await balance(source)).to.equal("600");
+    const receipt = await (program.account as any).stakeReceipt.fetch(
+      receiptFor(MOON_TIER),
+    );
+    expect(receipt.badge.toBase58()).to.equal(badgePDA.toBase58());
+    expect(receipt.vault.toBase58()).to.equal(vault.toBase58());
+    expect(receipt.minTier).to.equal(MOON_TIER);
+    expect(receipt.amount.toNumber()).to.equal(400);
+    console.log("    Verified and staked into the vault ✓");
+
+    // ...but not a Sun pool's
+    try {
+      const proof = await proveFor(wallet.publicKey);
+      await stake(proof, 100, proof.args[3], sunVault, MOON_TIER + 1);
+      expect.fail("Expected transaction to fail — tier below the pool's");
+    } catch (err: any) {
+      expect(err.message || "").to.include("TierBelowPoolMinimum");
+    }
+    expect(await balance(sunVault)).to.equal("0");
+    console.log("    Insufficient tier rejected, nothing staked ✓");
+
+    // A tampered proof stakes nothing
+    const tampered = await proveFor(wallet.publicKey);
+    const badInputs = tampered.args[3].map((input) => [...input]);
+    badInputs[3][31] ^= 1;
+    try {
+      await stake(tampered, 100, badInputs);
+      expect.fail("Expected transaction to fail — tampered proof");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ProofVerificationFailed");
+    }
+    expect(await balance(vault)).to.equal("400");
+    console.log("    Invalid proof rejected, nothing staked ✓");
+
+    // Later stakes add to the same receipt
+    await stake(await proveFor(wallet.publicKey), 100);
+    expect(
+      (
+        await (program.account as any).stakeReceipt.fetch(receiptFor(MOON_TIER))
+      ).amount.toNumber(),
+    ).to.equal(500);
+    expect(await balance(vault)).to.equal("500");
+    console.log("    Repeat stakes accumulate ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 53: Secp256k1 delegation
+  // ---------------------------------------------------------------------------
+
+  it("accepts a delegation signed with Secp256k1", async () => {
+    const custodian = await fundedKeypair();
+    const privateKey = randomBytes(32);
+    const authorization = (key: Buffer, inputs: Buffer[]) =>
+      Secp256k1Program.createInstructionWithPrivateKey({
+        privateKey: key,
+        message: Buffer.concat([
+          Buffer.from("proof-of-love:delegate"),
+          custodian.publicKey.toBuffer(),
+          Buffer.alloc(8), // default namespace
+          ...inputs,
+        ]),
+      });
+    // The instruction data carries the signer's address after the header
+    const ethAddress = authorization(privateKey, []).data.subarray(12, 32);
+    const [owner] = PublicKey.findProgramAddressSync(
+      [Buffer.from("secp256k1_owner"), ethAddress],
+      programId,
+    );
+    const [badgePDA] = deriveTierBadgePDA(owner, programId);
+    const { args, publicInputs: inputs } = await proveFor(owner);
+    const verifyDelegated = (
+      sigScheme: number,
+      pre: anchor.web3.TransactionInstruction,
+    ) =>
+      program.methods
+        .verifyDelegated(...args, LAYOUT_V1, sigScheme)
+        .accounts({
+          custodian: custodian.publicKey,
+          owner,
+          tierBadge: badgePDA,
+          nullifierRecord: deriveNullifierPDA(inputs[2], programId)[0],
+          walletMarker: null,
+          requestReceipt: null,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          badgeTombstone: null,
+          rewardClaim: null,
+          shared: sharedAccounts(),
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([pre])
+        .signers([custodian])
+        .rpc();
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    // Another key's signature, or the right one checked as Ed25519
+    await expectError(
+      verifyDelegated(
+        SIG_SCHEME_SECP256K1,
+        authorization(randomBytes(32), inputs),
+      ),
+      "DelegationSignatureInvalid",
+    );
+    await expectError(
+      verifyDelegated(SIG_SCHEME_ED25519, authorization(privateKey, inputs)),
+      "DelegationSignatureInvalid",
+    );
+    console.log("    Forged or mismatched Secp256k1 authorizations rejected ✓");
+
+    // Schemes other than Ed25519 and Secp256k1
+    await expectError(
+      verifyDelegated(2, authorization(privateKey, inputs)),
+      "UnsupportedSigScheme",
+    );
+    console.log("    Unknown signature scheme rejected ✓");
+
+    await verifyDelegated(
+      SIG_SCHEME_SECP256K1,
+      authorization(privateKey, inputs),
+    );
+    const badge = await (program.account as any).tierBadge.fetch(badgePDA);
+    expect(badge.owner.toBase58()).to.equal(owner.toBase58());
+    expect(badge.sigScheme).to.equal(SIG_SCHEME_SECP256K1);
+    console.log("    Secp256k1 key authorized its badge, scheme recorded ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 54: Tier memo
+  // ---------------------------------------------------------------------------
+
+  it("writes a tier memo only when the config enables it", async () => {
+    const MEMO_PROGRAM_ID = new PublicKey(
+      "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+    );
+    const wallet = await fundedKeypair();
+    const verifyLogs = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      const sig = await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, inputs, {
+            memoProgram: MEMO_PROGRAM_ID,
+          }),
+        )
+        .signers([wallet])
+        .rpc();
+      await provider.connection.confirmTransaction(sig, "confirmed");
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return tx!.meta!.logMessages!.join("\n");
+    };
+    const setMemo = async (emit: boolean) => {
+      await program.methods
+        .updateConfig({ ...noChange, emitTierMemo: emit })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+
+    // Disabled by default: no memo even with the Memo program passed
+    let logs = await verifyLogs();
+    expect(logs).to.not.include(`Program ${MEMO_PROGRAM_ID.toBase58()} invoke`);
+    expect(logs).to.not.include("POL:T");
+    console.log("    No memo while disabled ✓");
+
+    await setMemo(true);
+    logs = await verifyLogs();
+    expect(logs).to.include(`Program ${MEMO_PROGRAM_ID.toBase58()} invoke`);
+    expect(logs).to.include('"POL:T6"');
+    console.log("    POL:T6 memo written once enabled ✓");
+
+    await setMemo(false);
+    expect((await fetchConfig()).emitTierMemo).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 55: Badge labels
+  // ---------------------------------------------------------------------------
+
+  it("lets the owner set a validated display label", async () => {
+    const wallet = await fundedKeypair();
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const padded = (label: Buffer) => [
+      ...label,
+      ...new Array(32 - label.length).fill(0),
+    ];
+    const eventsOf = async (sig: string) => {
+      await provider.connection.confirmTransaction(sig, "confirmed");
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return [
+        ...new anchor.EventParser(programId, program.coder).parseLogs(
+          tx!.meta!.logMessages!,
+        ),
+      ];
+    };
+    const setLabel = (label: Buffer) =>
+      program.methods
+        .setLabel(label)
+        .accounts({ user: wallet.publicKey, tierBadge: badgePDA })
+        .signers([wallet])
+        .rpc();
+    const verify = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      return program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      let message = "";
+      try {
+        await promise;
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include(error);
+    };
+
+    await verify();
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).label,
+    ).to.deep.equal(new Array(32).fill(0));
+
+    // UTF-8 labels up to 32 bytes are stored zero-padded
+    const label = Buffer.from("whale 🐋");
+    const labelSet = (await eventsOf(await setLabel(label))).find(
+      (e) => e.name === "badgeLabelSet",
+    );
+    expect(labelSet!.data.tierBadge.toBase58()).to.equal(badgePDA.toBase58());
+    expect(labelSet!.data.label).to.deep.equal(padded(label));
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).label,
+    ).to.deep.equal(padded(label));
+    await setLabel(Buffer.alloc(32, "a"));
+    await setLabel(label);
+    console.log("    Label stored and announced ✓");
+
+    // Refreshes keep the label and report it
+    const verified = (await eventsOf(await verify())).find(
+      (e) => e.name === "tierVerified",
+    );
+    expect(verified!.data.label).to.deep.equal(padded(label));
+    console.log("    Label survives refresh ✓");
+
+    // Over-length, invalid UTF-8 and control characters are rejected
+    await expectError(setLabel(Buffer.alloc(33, "a")), "InvalidLabel");
+    await expectError(setLabel(Buffer.from([0xff, 0xfe])), "InvalidLabel");
+    await expectError(setLabel(Buffer.from("line\nbreak")), "InvalidLabel");
+    await expectError(setLabel(Buffer.from("a\0b")), "InvalidLabel");
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).label,
+    ).to.deep.equal(padded(label));
+    console.log("    Malformed labels rejected ✓");
+
+    // Only the owner can label the badge
+    const stranger = await fundedKeypair();
+    await expectError(
+      program.methods
+        .setLabel(Buffer.from("mine"))
+        .accounts({ user: stranger.publicKey, tierBadge: badgePDA })
+        .signers([stranger])
+        .rpc(),
+      "ConstraintSeeds",
+    );
+
+    // An empty label clears it
+    await setLabel(Buffer.alloc(0));
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).label,
+    ).to.deep.equal(new Array(32).fill(0));
+    console.log("    Label cleared ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 56: Verifying key fingerprint
+  // ---------------------------------------------------------------------------
+
+  it("exposes the verifying key's fingerprint", async () => {
+    const vk = JSON.parse(
+      fs.readFileSync(
+        path.join(CIRCUIT_BUILD_DIR, "keys", "verification_key.json"),
+        "utf-8",
+      ),
+    );
+    const vkHash = createHash("sha256")
+      .update(
+        Buffer.concat([
+          encodeProofC(vk.vk_alpha_1),
+          encodeProofB(vk.vk_beta_2),
+          encodeProofB(vk.vk_gamma_2),
+          encodeProofB(vk.vk_delta_2),
+          ...vk.IC.map((point: string[]) => encodeProofC(point)),
+        ]),
+      )
+      .digest();
+
+    const fingerprint = await program.methods
+      .getVkFingerprint()
+      .accounts({ config: configPDA })
+      .view();
+    expect(fingerprint).to.deep.equal(Array.from(vkHash.subarray(0, 8)));
+    console.log(
+      `    Fingerprint ${Buffer.from(fingerprint).toString("hex")} matches the embedded key ✓`,
+    );
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 57: First-verification reward
+  // ---------------------------------------------------------------------------
+
+  it("pays each identity's first verification a reward once", async () => {
+    const REWARD = 10_000_000;
+    const [rewardVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward_vault")],
+      programId,
+    );
+    const claimFor = (inputs: Buffer[]) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("reward_claim"), inputs[2]],
+        programId,
+      )[0];
+    const balanceOf = (key: PublicKey) => provider.connection.getBalance(key);
+    const fund = (lamports: number, authority?: Keypair) =>
+      program.methods
+        .fundRewardVault(new anchor.BN(lamports))
+        .accounts({
+          authority: authority ? authority.publicKey : user.publicKey,
+          config: configPDA,
+          rewardVault: rewardVaultPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers(authority ? [authority] : [])
+        .rpc();
+    const verify = async (wallet: Keypair) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs, {
+            rewardVault: rewardVaultPDA,
+          }),
+          rewardClaim: claimFor(inputs),
+        })
+        .signers([wallet])
+        .rpc();
+      return inputs;
+    };
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      let message = "";
+      try {
+        await promise;
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include(error);
+    };
+
+    // Only the authority can fund the vault
+    await expectError(fund(REWARD, await fundedKeypair()), "Unauthorized");
+    await fund(REWARD / 10);
+
+    // Raising the reward waits out the timelock
+    await program.methods
+      .updateConfig({
+        ...noChange,
+        firstVerifyRewardLamports: new anchor.BN(REWARD),
+      })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).firstVerifyRewardLamports.toNumber()).to.equal(
+      0,
+    );
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+
+    // A vault that can't cover the reward fails the verification
+    const wallet = await fundedKeypair();
+    await expectError(verify(wallet), "RewardVaultEmpty");
+    console.log("    Underfunded vault rejected ✓");
+
+    await fund(REWARD * 10);
+    const vaultBefore = await balanceOf(rewardVaultPDA);
+    const inputs = await verify(wallet);
+    expect(await balanceOf(rewardVaultPDA)).to.equal(vaultBefore - REWARD);
+    const claim = await (program.account as any).rewardClaim.fetch(
+      claimFor(inputs),
+    );
+    expect(claim.recipient.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(claim.amount.toNumber()).to.equal(REWARD);
+    expect(
+      (
+        await (program.account as any).rewardVault.fetch(rewardVaultPDA)
+      ).paidOutLamports.toNumber(),
+    ).to.equal(REWARD);
+    console.log("    First verification rewarded ✓");
+
+    // Refreshing pays nothing
+    await verify(wallet);
+    expect(await balanceOf(rewardVaultPDA)).to.equal(vaultBefore - REWARD);
+    console.log("    Refresh not rewarded ✓");
+
+    // Nor does recreating the badge under the same nullifier
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    await program.methods
+      .burnBadge()
+      .accounts({
+        user: wallet.publicKey,
+        tierBadge: badgePDA,
+        badgeTombstone: await tombstoneOf(badgePDA),
+        expiryBucket: null,
+        votingPower: await votingPowerOf(badgePDA),
+        nullifierRecord: await nullifierRecordOf(badgePDA),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+    await verify(wallet);
+    expect(await balanceOf(rewardVaultPDA)).to.equal(vaultBefore - REWARD);
+    console.log("    Recreated badge not rewarded again ✓");
+
+    // Lowering the reward applies immediately
+    await program.methods
+      .updateConfig({ ...noChange, firstVerifyRewardLamports: new anchor.BN(0) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).firstVerifyRewardLamports.toNumber()).to.equal(
+      0,
+    );
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 58: Verifying key windows
+  // ---------------------------------------------------------------------------
+
+  it("accepts proofs only under keys whose window covers them", async () => {
+    const I64_MIN = new anchor.BN("-9223372036854775808");
+    const I64_MAX = new anchor.BN("9223372036854775807");
+    const ALWAYS = { validFrom: I64_MIN, validUntil: I64_MAX };
+    const NEVER = { validFrom: I64_MAX, validUntil: I64_MIN };
+    const wallet = await fundedKeypair();
+    const badgePDA = deriveTierBadgePDA(wallet.publicKey, programId)[0];
+    const verify = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, inputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const setWindows = (windows: object[]) =>
+      program.methods
+        .updateConfig({ ...noChange, vkWindows: windows })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      let message = "";
+      try {
+        await promise;
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include(error);
+    };
+
+    // By default the compiled key, id 0, accepts every proof
+    const windows = (await fetchConfig()).vkWindows;
+    expect(windows[0].validFrom.toString()).to.equal(I64_MIN.toString());
+    expect(windows[0].validUntil.toString()).to.equal(I64_MAX.toString());
+    await verify();
+    expect(
+      (await (program.account as any).tierBadge.fetch(badgePDA)).vkId,
+    ).to.equal(0);
+    console.log("    Proof accepted under key 0 and recorded ✓");
+
+    // Closing a key's window narrows it, so it applies immediately, and
+    // proofs made after it closed are rejected
+    await setWindows([
+      { validFrom: I64_MIN, validUntil: new anchor.BN(1_000_000_000) },
+      NEVER,
+      NEVER,
+    ]);
+    await expectError(verify(), "NoActiveVerifyingKey");
+    console.log("    Proof after the window closed rejected ✓");
+
+    // Reopening it accepts more proofs, so it waits out the timelock
+    await setWindows([ALWAYS, NEVER, NEVER]);
+    await expectError(verify(), "NoActiveVerifyingKey");
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await verify();
+    console.log("    Reopened window accepts proofs after the timelock ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 59: Vault withdrawals
+  // ---------------------------------------------------------------------------
+
+  it("withdraws only vault balances above their reserve", async () => {
+    const [treasuryPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury")],
+      programId,
+    );
+    const [rewardVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward_vault")],
+      programId,
+    );
+    const balanceOf = (key: PublicKey) => provider.connection.getBalance(key);
+    const reserveOf = async (key: PublicKey) =>
+      provider.connection.getMinimumBalanceForRentExemption(
+        (await provider.connection.getAccountInfo(key))!.data.length,
+      );
+    const withdraw = (
+      amount: number,
+      vaults: { treasury?: PublicKey; rewardVault?: PublicKey },
+      recipient: PublicKey = user.publicKey,
+      authority?: Keypair,
+    ) =>
+      program.methods
+        .withdrawTreasury(new anchor.BN(amount))
+        .accounts({
+          authority: authority ? authority.publicKey : user.publicKey,
+          config: configPDA,
+          treasury: vaults.treasury ?? null,
+          rewardVault: vaults.rewardVault ?? null,
+          recipient,
+        })
+        .signers(authority ? [authority] : [])
+        .rpc();
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      let message = "";
+      try {
+        await promise;
+      } catch (err: any) {
+        message = err.message || "";
+      }
+      expect(message).to.include(error);
+    };
+
+    // Exactly one vault per withdrawal
+    await expectError(withdraw(1, {}), "InvalidWithdrawalSource");
+    await expectError(
+      withdraw(1, { treasury: treasuryPDA, rewardVault: rewardVaultPDA }),
+      "InvalidWithdrawalSource",
+    );
+
+    // Only the authority withdraws, and only to itself or the configured
+    // recipient
+    const stranger = await fundedKeypair();
+    await expectError(
+      withdraw(1, { treasury: treasuryPDA }, stranger.publicKey, stranger),
+      "Unauthorized",
+    );
+    await expectError(
+      withdraw(1, { treasury: treasuryPDA }, stranger.publicKey),
+      "Unauthorized",
+    );
+    console.log("    Foreign signers and recipients rejected ✓");
+
+    // Badge accounts can't stand in for a vault
+    const owner = await fundedKeypair();
+    const { args, publicInputs: inputs } = await proveFor(owner.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(owner.publicKey, inputs))
+      .signers([owner])
+      .rpc();
+    const badgePDA = deriveTierBadgePDA(owner.publicKey, programId)[0];
+    const badgeBalance = await balanceOf(badgePDA);
+    try {
+      await withdraw(1, { treasury: badgePDA });
+      expect.fail("Expected transaction to fail — badge passed as treasury");
+    } catch (err: any) {
+      expect(err.message || "").to.match(
+        /AccountDiscriminatorMismatch|ConstraintSeeds/,
+      );
+    }
+    expect(await balanceOf(badgePDA)).to.equal(badgeBalance);
+    console.log("    Badge rent untouchable ✓");
+
+    // Subsidies the cap still allows this epoch and the next reward are
+    // held back
+    const HELD = 1_000_000;
+    const applyPending = async (update: object) => {
+      await program.methods
+        .updateConfig({ ...noChange, ...update })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+    const treasury = await (program.account as any).treasury.fetch(
+      treasuryPDA,
+    );
+    const { epoch } = await provider.connection.getEpochInfo();
+    const spent =
+      treasury.epoch.toNumber() === epoch
+        ? treasury.subsidizedLamports.toNumber()
+        : 0;
+    await applyPending({
+      subsidyMinTier: 7,
+      subsidyEpochCapLamports: new anchor.BN(spent + HELD),
+      firstVerifyRewardLamports: new anchor.BN(HELD),
+    });
+    for (const vault of [
+      { treasury: treasuryPDA },
+      { rewardVault: rewardVaultPDA },
+    ]) {
+      const key = (vault.treasury ?? vault.rewardVault)!;
+      const free = (await balanceOf(key)) - (await reserveOf(key)) - HELD;
+      expect(free).to.be.greaterThan(0);
+      await expectError(
+        withdraw(free + 1, vault),
+        "WithdrawalExceedsAvailable",
+      );
+    }
+    console.log("    Committed subsidies and rewards held back ✓");
+
+    // Turning both off releases them
+    await applyPending({
+      subsidyMinTier: 0,
+      subsidyEpochCapLamports: new anchor.BN(0),
+      firstVerifyRewardLamports: new anchor.BN(0),
+    });
+
+    // Each vault keeps its rent-exempt reserve
+    for (const vault of [
+      { treasury: treasuryPDA },
+      { rewardVault: rewardVaultPDA },
+    ]) {
+      const key = (vault.treasury ?? vault.rewardVault)!;
+      const reserve = await reserveOf(key);
+      const available = (await balanceOf(key)) - reserve;
+      expect(available).to.be.greaterThan(0);
+      await expectError(
+        withdraw(available + 1, vault),
+        "WithdrawalExceedsAvailable",
+      );
+
+      const sig = await withdraw(available, vault);
+      expect(await balanceOf(key)).to.equal(reserve);
+      await provider.connection.confirmTransaction(sig, "confirmed");
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const withdrawn = [
+        ...new anchor.EventParser(programId, program.coder).parseLogs(
+          tx!.meta!.logMessages!,
+        ),
+      ].find((e) => e.name === "vaultWithdrawn");
+      expect(withdrawn!.data.vault.toBase58()).to.equal(key.toBase58());
+      expect(withdrawn!.data.amount.toNumber()).to.equal(available);
+
+      await expectError(withdraw(1, vault), "WithdrawalExceedsAvailable");
+    }
+    console.log("    Vaults drained down to their reserve only ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 60: Maximum accepted tier
+  // ---------------------------------------------------------------------------
+
+  it("rejects tiers above the deployment's maximum", async () => {
+    const SUN = {
+      balance_1: "600000000",
+      balance_2: "600000000",
+      balance_3: "600000000",
+      tier_lower_bound: "500000000",
+      tier_upper_bound: "10000000000000",
+    };
+    const verify = async (wallet: Keypair, inputs = {}) => {
+      const { args, publicInputs } = await proveFor(wallet.publicKey, inputs);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const setMaxTier = async (tier: number) => {
+      await program.methods
+        .updateConfig({ ...noChange, maxAcceptedTier: tier })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+
+    // Out-of-range maximums are invalid
+    for (const tier of [0, 8]) {
+      try {
+        await program.methods
+          .updateConfig({ ...noChange, maxAcceptedTier: tier })
+          .accounts({ authority: user.publicKey, config: configPDA })
+          .rpc();
+        expect.fail(`Expected transaction to fail — max tier ${tier}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include("InvalidConfig");
+      }
+    }
+
+    // Serving tiers 1-5 turns a Sun proof away
+    await setMaxTier(5);
+    const sun = await fundedKeypair();
+    try {
+      await verify(sun, SUN);
+      expect.fail("Expected transaction to fail — tier above maximum");
+    } catch (err: any) {
+      expect(err.message || "").to.include("TierAboveMaximum");
+    }
+    expect(
+      await provider.connection.getAccountInfo(
+        deriveTierBadgePDA(sun.publicKey, programId)[0],
+      ),
+    ).to.equal(null);
+    console.log("    Tier 7 rejected with max tier 5 ✓");
+
+    // Back at 7 every tier is accepted
+    await setMaxTier(7);
+    await verify(sun, SUN);
+    expect(
+      (
+        await (program.account as any).tierBadge.fetch(
+          deriveTierBadgePDA(sun.publicKey, programId)[0],
+        )
+      ).tier,
+    ).to.equal(7);
+    console.log("    Tier 7 accepted with max tier 7 ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 61: Revocation report
+  // ---------------------------------------------------------------------------
+
+  it("reports reclaimed rent from revoke_expired_tier", async () => {
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+
+    await setBadgeValidity(10);
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+      .signers([wallet])
+      .rpc();
+    await setBadgeValidity(30 * 24 * 60 * 60);
+    await waitPastUnixTime(
+      provider.connection,
+      (
+        await (program.account as any).tierBadge.fetch(badge)
+      ).expiresAt.toNumber(),
+    );
+
+    const badgeRent = await provider.connection.getBalance(badge);
+    const sig = await program.methods
+      .revokeExpiredTier()
+      .accounts({
+        user: wallet.publicKey,
+        tierBadge: badge,
+        expiryBucket: null,
+        votingPower: await votingPowerOf(badge),
+        nullifierRecord: await nullifierRecordOf(badge),
+      })
+      .signers([wallet])
+      .rpc();
+    await provider.connection.confirmTransaction(sig, "confirmed");
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+
+    // RevocationReport is a u64 lamports_reclaimed then a u32 badges_closed
+    const prefix = `Program return: ${programId.toBase58()} `;
+    const line = tx!.meta!.logMessages!.find((l) => l.startsWith(prefix));
+    const report = Buffer.from(line!.slice(prefix.length), "base64");
+    const lamportsReclaimed = Number(report.readBigUInt64LE(0));
+    expect(report.readUInt32LE(8)).to.equal(1);
+
+    // The report matches the lamports that actually moved
+    const keys = tx!.transaction.message.getAccountKeys().staticAccountKeys;
+    const index = (key: PublicKey) => keys.findIndex((k) => k.equals(key));
+    const { preBalances, postBalances, fee } = tx!.meta!;
+    expect(lamportsReclaimed).to.equal(badgeRent);
+    expect(preBalances[index(badge)] - postBalances[index(badge)]).to.equal(
+      lamportsReclaimed,
+    );
+    expect(
+      postBalances[index(wallet.publicKey)] -
+        preBalances[index(wallet.publicKey)] +
+        fee,
+    ).to.equal(lamportsReclaimed);
+    console.log(`    Reported ${lamportsReclaimed} lamports reclaimed ✓`);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 62: Program version
+  // ---------------------------------------------------------------------------
+
+  it("stamps new badges with the program version", async () => {
+    const PROGRAM_VERSION = 1;
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+      .signers([wallet])
+      .rpc();
+
+    expect(
+      (await (program.account as any).tierBadge.fetch(badge)).programVersion,
+    ).to.equal(PROGRAM_VERSION);
+    console.log(`    Badge created under version ${PROGRAM_VERSION} ✓`);
+
+    // Consumers can require a minimum version
+    await program.methods
+      .requireBadgeVersion(PROGRAM_VERSION)
+      .accounts({ tierBadge: badge, nullifierRecord: null })
+      .rpc();
+    try {
+      await program.methods
+        .requireBadgeVersion(PROGRAM_VERSION + 1)
+        .accounts({ tierBadge: badge, nullifierRecord: null })
+        .rpc();
+      expect.fail("Expected transaction to fail — badge version too old");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ProgramVersionTooOld");
+    }
+    console.log("    Newer minimum version rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 63: Two-stage verification
+  // ---------------------------------------------------------------------------
+
+  it("verifies across two transactions through a staged proof", async () => {
+    const PENDING_VERIFICATION_SLOTS = 16;
+    const pendingFor = (wallet: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("pending_verification"), wallet.toBuffer()],
+        programId,
+      )[0];
+    const stage1 = async (wallet: Keypair) => {
+      const { args, publicInputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyProofStage1(args[0], args[1], args[2], args[3], LAYOUT_V1)
+        .accounts({
+          user: wallet.publicKey,
+          pendingVerification: pendingFor(wallet.publicKey),
+          config: configPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+      return publicInputs;
+    };
+    const stage2 = (wallet: Keypair, inputs: Buffer[]) =>
+      program.methods
+        .verifyProofStage2(null, INDIVIDUAL, null)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          pendingVerification: pendingFor(wallet.publicKey),
+        })
+        .signers([wallet])
+        .rpc();
+
+    // Happy path: stage 2 right behind stage 1 stores the badge
+    const wallet = await fundedKeypair();
+    const inputs = await stage1(wallet);
+    const pending = await (program.account as any).pendingVerification.fetch(
+      pendingFor(wallet.publicKey),
+    );
+    expect(Buffer.from(pending.publicInputs[2])).to.deep.equal(inputs[2]);
+    await stage2(wallet, inputs);
+
+    const badge = await (program.account as any).tierBadge.fetch(
+      deriveTierBadgePDA(wallet.publicKey, programId)[0],
+    );
+    expect(badge.tier).to.equal(6);
+    expect(Buffer.from(badge.nullifier)).to.deep.equal(inputs[2]);
+    expect(
+      await provider.connection.getAccountInfo(pendingFor(wallet.publicKey)),
+    ).to.equal(null);
+    console.log("    Two-stage verification stored a Moon badge ✓");
+
+    // A stage 2 once the staged proof has gone stale is rejected
+    const late = await fundedKeypair();
+    const lateInputs = await stage1(late);
+    await waitSlots(provider.connection, PENDING_VERIFICATION_SLOTS + 1);
+    try {
+      await stage2(late, lateInputs);
+      expect.fail("Expected transaction to fail — stale staged proof");
+    } catch (err: any) {
+      expect(err.message || "").to.include("StaleVerification");
+    }
+    expect(
+      await provider.connection.getAccountInfo(
+        deriveTierBadgePDA(late.publicKey, programId)[0],
+      ),
+    ).to.equal(null);
+    console.log("    Stale stage 2 rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 64: Verification epoch
+  // ---------------------------------------------------------------------------
+
+  it("rejects badges from an earlier epoch in strict mode", async () => {
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const verify = async () => {
+      const { args, publicInputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const requireCurrentEpoch = () =>
+      program.methods
+        .requireCurrentEpoch()
+        .accounts({
+          tierBadge: badge,
+          config: configPDA,
+          nullifierRecord: null,
+        })
+        .rpc();
+    const update = (fields: object) =>
+      program.methods
+        .updateConfig({ ...noChange, ...fields })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+
+    // Minted in epoch 1, under strict mode
+    const epoch = (await fetchConfig()).verificationEpoch.toNumber();
+    expect(epoch).to.equal(1);
+    await update({ strictEpoch: true });
+    await verify();
+    expect(
+      (
+        await (program.account as any).tierBadge.fetch(badge)
+      ).verificationEpoch.toNumber(),
+    ).to.equal(epoch);
+    await requireCurrentEpoch();
+    console.log("    Epoch 1 badge accepted in epoch 1 ✓");
+
+    // Advancing the epoch applies at once and rejects the badge...
+    await update({ verificationEpoch: new anchor.BN(epoch + 1) });
+    expect((await fetchConfig()).verificationEpoch.toNumber()).to.equal(
+      epoch + 1,
+    );
+    try {
+      await requireCurrentEpoch();
+      expect.fail("Expected transaction to fail — badge from epoch 1");
+    } catch (err: any) {
+      expect(err.message || "").to.include("BadgeEpochExpired");
+    }
+    console.log("    Epoch 1 badge rejected in epoch 2 ✓");
+
+    // ...until it is verified again
+    await verify();
+    await requireCurrentEpoch();
+    console.log("    Re-verified badge accepted in epoch 2 ✓");
+
+    // Leaving strict mode waits out the timelock
+    await update({ strictEpoch: false });
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 65: Implausible bounds tripwire
+  // ---------------------------------------------------------------------------
+
+  it("rejects implausibly large tier bounds", async () => {
+    const verify = async (
+      wallet: Keypair,
+      lower: string,
+      upper: string,
+      balance: string,
+    ) => {
+      const { args, publicInputs } = await proveFor(wallet.publicKey, {
+        balance_1: balance,
+        balance_2: balance,
+        balance_3: balance,
+        tier_lower_bound: lower,
+        tier_upper_bound: upper,
+      });
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+        .signers([wallet])
+        .rpc();
+    };
+    const tierOf = async (wallet: Keypair) =>
+      (
+        await (program.account as any).tierBadge.fetch(
+          deriveTierBadgePDA(wallet.publicKey, programId)[0],
+        )
+      ).tier;
+
+    // A Sun proof up to MAX_BALANCE, the default maximum, is plausible
+    const plausible = await fundedKeypair();
+    await verify(plausible, "500000000", "10000000000000000", "600000000");
+    expect(await tierOf(plausible)).to.equal(7);
+    console.log("    Bounds up to MAX_BALANCE accepted ✓");
+
+    // The top tier has no real ceiling, so a Sun proof above it is too
+    const sun = await fundedKeypair();
+    await verify(sun, "500000000", "100000000000000000", "600000000");
+    expect(await tierOf(sun)).to.equal(7);
+    console.log("    Sun proof above MAX_BALANCE accepted ✓");
+
+    // Moon bounds 10^9 times too large look like a unit mismatch, even
+    // though they verify
+    const implausible = await fundedKeypair();
+    try {
+      await verify(
+        implausible,
+        "100000000000000000",
+        "500000000000000000",
+        "200000000000000000",
+      );
+      expect.fail("Expected transaction to fail — implausible bounds");
+    } catch (err: any) {
+      expect(err.message || "").to.include("BoundsImplausible");
+    }
+    console.log("    Other bounds above MAX_BALANCE rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 66: Expiry extension coupons
+  // ---------------------------------------------------------------------------
+
+  it("extends badges with a coupon until it is exhausted", async () => {
+    const DAY = 24 * 60 * 60;
+    const id = Array.from(Buffer.from("retain01"));
+    const [coupon] = PublicKey.findProgramAddressSync(
+      [Buffer.from("coupon"), Buffer.from(id)],
+      programId,
+    );
+    await program.methods
+      .createCoupon(
+        id,
+        new anchor.BN(DAY),
+        1,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 7 * DAY),
+      )
+      .accounts({
+        authority: user.publicKey,
+        config: configPDA,
+        coupon,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // Buckets may already exist from other tests
+    const bucketFor = async (day: number) => {
+      const [bucket] = deriveExpiryBucketPDA(day, programId);
+      if (!(await provider.connection.getAccountInfo(bucket))) {
+        await program.methods
+          .createExpiryBucket(new anchor.BN(day))
+          .accounts({
+            payer: user.publicKey,
+            expiryBucket: bucket,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+      return bucket;
+    };
+
+    // List the badge under its expiry day
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+    const validity = (await fetchConfig()).badgeValiditySeconds.toNumber();
+    const day = Math.floor(
+      (Number(publicInputs[3].readBigUInt64BE(24)) + validity) / DAY,
+    );
+    const dayBucket = await bucketFor(day);
+    const nextBucket = await bucketFor(day + 1);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(
+        verifyAccounts(wallet.publicKey, publicInputs, {
+          expiryBucket: dayBucket,
+        }),
+      )
+      .signers([wallet])
+      .rpc();
+    const fetchBadge = () => (program.account as any).tierBadge.fetch(badge);
+    const before = (await fetchBadge()).expiresAt.toNumber();
+    expect((await fetchBadge()).expiryListed).to.equal(true);
+
+    const redeem = (buckets: object = {}) =>
+      program.methods
+        .redeemCoupon()
+        .accounts({
+          user: wallet.publicKey,
+          tierBadge: badge,
+          coupon,
+          config: configPDA,
+          expiryBucket: null,
+          previousExpiryBucket: null,
+          ...buckets,
+        })
+        .signers([wallet])
+        .rpc();
+
+    // Moving a listed badge to the next day needs its old bucket
+    try {
+      await redeem();
+      expect.fail("Expected transaction to fail — old bucket left out");
+    } catch (err: any) {
+      expect(err.message || "").to.include("ExpiryBucketRequired");
+    }
+
+    // The one use extends the badge by a day, and moves its listing
+    await redeem({ expiryBucket: nextBucket, previousExpiryBucket: dayBucket });
+    expect((await fetchBadge()).expiresAt.toNumber()).to.equal(before + DAY);
+    const listed = async (bucket: PublicKey) =>
+      (
+        await (program.account as any).expiryBucket.fetch(bucket)
+      ).badges.map((key: PublicKey) => key.toBase58());
+    expect(await listed(dayBucket)).to.not.include(badge.toBase58());
+    expect(await listed(nextBucket)).to.include(badge.toBase58());
+    expect(
+      (await (program.account as any).coupon.fetch(coupon)).remainingUses,
+    ).to.equal(0);
+    console.log("    Coupon extended the badge by a day ✓");
+
+    // A second redemption finds the coupon used up
+    try {
+      await redeem();
+      expect.fail("Expected transaction to fail — coupon exhausted");
+    } catch (err: any) {
+      expect(err.message || "").to.include("CouponExhausted");
+    }
+    expect((await fetchBadge()).expiresAt.toNumber()).to.equal(before + DAY);
+    console.log("    Exhausted coupon rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 67: Nullifier record cross-check
+  // ---------------------------------------------------------------------------
+
+  it("detects a badge desynced from its nullifier record", async () => {
+    const verify = async (wallet: Keypair) => {
+      const { args, publicInputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+        .signers([wallet])
+        .rpc();
+      return deriveNullifierPDA(publicInputs[2], programId)[0];
+    };
+    const alice = await fundedKeypair();
+    const bob = await fundedKeypair();
+    const aliceRecord = await verify(alice);
+    const bobRecord = await verify(bob);
+    const [aliceBadge] = deriveTierBadgePDA(alice.publicKey, programId);
+
+    // The badge's own record agrees with it
+    await program.methods
+      .requireTrustedBadge()
+      .accounts({ tierBadge: aliceBadge, nullifierRecord: aliceRecord })
+      .rpc();
+    console.log("    Matching badge and record accepted ✓");
+
+    // Another identity's record is caught by every gating helper
+    for (const call of [
+      program.methods
+        .requireTrustedBadge()
+        .accounts({ tierBadge: aliceBadge, nullifierRecord: bobRecord }),
+      program.methods
+        .gateCheck(1, false, false, new anchor.BN(0))
+        .accounts({
+          tierBadge: aliceBadge,
+          config: configPDA,
+          nullifierRecord: bobRecord,
+        }),
+      program.methods
+        .requireBadgeVersion(0)
+        .accounts({ tierBadge: aliceBadge, nullifierRecord: bobRecord }),
+    ]) {
+      try {
+        await call.rpc();
+        expect.fail("Expected transaction to fail — desynced record");
+      } catch (err: any) {
+        expect(err.message || "").to.include("NullifierDesync");
+      }
+    }
+    console.log("    Desynced badge and record rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 68: Tier table hash
+  // ---------------------------------------------------------------------------
+
+  it("returns a tier table hash that tracks table updates", async () => {
+    const getHash = async () =>
+      Buffer.from(
+        await program.methods
+          .getTierTableHash()
+          .accounts({ config: configPDA })
+          .view(),
+      );
+    // Bounds as big-endian u64s in tier order, as a client would hash them
+    const hashTiers = (tiers: { lower: anchor.BN; upper: anchor.BN }[]) =>
+      createHash("sha256")
+        .update(
+          Buffer.concat(
+            tiers.flatMap((t) => [
+              t.lower.toArrayLike(Buffer, "be", 8),
+              t.upper.toArrayLike(Buffer, "be", 8),
+            ]),
+          ),
+        )
+        .digest();
+    const setTiers = async (tiers: object[]) => {
+      await program.methods
+        .updateConfig({ ...noChange, tiers })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+
+    const original = (await fetchConfig()).tiers;
+    const before = await getHash();
+    expect(before).to.deep.equal(hashTiers(original));
+    console.log("    Hash matches the client-side table ✓");
+
+    // Raising the Sun tier's ceiling changes the hash
+    const raised = original.map((t: any, i: number) =>
+      i === original.length - 1
+        ? { lower: t.lower, upper: t.upper.mul(new anchor.BN(2)) }
+        : t,
+    );
+    await setTiers(raised);
+    const after = await getHash();
+    expect(after).to.not.deep.equal(before);
+    expect(after).to.deep.equal(hashTiers(raised));
+    console.log("    Hash changed with the tier table ✓");
+
+    await setTiers(original);
+    expect(await getHash()).to.deep.equal(before);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 69: Nullifier enforcement flag
+  // ---------------------------------------------------------------------------
+
+  it("marks verified badges as nullifier-enforced", async () => {
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+      .signers([wallet])
+      .rpc();
+
+    // Verification always registers the nullifier, so the flag is set and
+    // the optional gate passes. Legacy badges created before enforcement
+    // carry false until backfilled, and fail it with NullifierNotEnforced
+    expect(
+      (await (program.account as any).tierBadge.fetch(badge))
+        .nullifierEnforced,
+    ).to.be.true;
+    await program.methods
+      .requireNullifierEnforced()
+      .accounts({ tierBadge: badge, nullifierRecord: null })
+      .rpc();
+    console.log("    Verified badge passes the enforcement gate ✓");
+
+    // Backfilling an already registered nullifier keeps the flag
+    const [nullifierPDA] = deriveNullifierPDA(publicInputs[2], programId);
+    await program.methods
+      .backfillNullifier()
+      .accounts({
+        authority: user.publicKey,
+        config: configPDA,
+        tierBadge: badge,
+        nullifierRecord: nullifierPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .requireNullifierEnforced()
+      .accounts({ tierBadge: badge, nullifierRecord: nullifierPDA })
+      .rpc();
+    console.log("    Flag kept across backfill ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 70: Verification history log
+  // ---------------------------------------------------------------------------
+
+  it("appends verifications to the wallet's history log", async () => {
+    const LOG_DEPTH = 16;
+    const sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    // Root of a depth-16 tree over `leaves`, padded with zero leaves
+    const rootOf = (leaves: Buffer[]) => {
+      let level = leaves;
+      let empty = Buffer.alloc(32);
+      for (let depth = 0; depth < LOG_DEPTH; depth++) {
+        const next: Buffer[] = [];
+        for (let i = 0; i < level.length; i += 2) {
+          next.push(sha256(level[i], level[i + 1] ?? empty));
+        }
+        level = next;
+        empty = sha256(empty, empty);
+      }
+      return level[0];
+    };
+
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const [logPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("verification_log"), wallet.publicKey.toBuffer()],
+      programId,
+    );
+    await program.methods
+      .createVerificationLog()
+      .accounts({
+        owner: wallet.publicKey,
+        verificationLog: logPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+    const fetchLog = () =>
+      (program.account as any).verificationLog.fetch(logPDA);
+    const setLogging = async (log: boolean) => {
+      await program.methods
+        .updateConfig({ ...noChange, logVerifications: log })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+      await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+      await program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    };
+    const verify = async () => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, inputs, { verificationLog: logPDA }),
+        )
+        .signers([wallet])
+        .rpc();
+      const tier = (await (program.account as any).tierBadge.fetch(badge))
+        .tier;
+      return sha256(
+        Buffer.from([tier]),
+        inputs[3].subarray(24, 32),
+        sha256(inputs[2]),
+      );
+    };
+
+    // Disabled by default: the log is left alone even when passed
+    await verify();
+    expect((await fetchLog()).leafCount.toNumber()).to.equal(0);
+    console.log("    Nothing logged while disabled ✓");
+
+    await setLogging(true);
+    const leaves = [await verify()];
+    let log = await fetchLog();
+    expect(log.leafCount.toNumber()).to.equal(1);
+    expect(Buffer.from(log.root)).to.deep.equal(rootOf(leaves));
+    const firstRoot = Buffer.from(log.root);
+
+    // A refresh appends a second leaf and moves the root
+    leaves.push(await verify());
+    log = await fetchLog();
+    expect(log.leafCount.toNumber()).to.equal(2);
+    expect(Buffer.from(log.root)).to.deep.equal(rootOf(leaves));
+    expect(Buffer.from(log.root)).to.not.deep.equal(firstRoot);
+    console.log("    Leaves appended and root updated ✓");
+
+    await setLogging(false);
+    expect((await fetchConfig()).logVerifications).to.equal(false);
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 71: Read-only badge accounts
+  // ---------------------------------------------------------------------------
+
+  it("rejects a read-only badge with BadgeNotWritable", async () => {
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const { args, publicInputs } = await proveFor(wallet.publicKey);
+
+    // Pass the badge read-only, as a CPI caller with a wrong account meta
+    // would, and expect the explicit error instead of a runtime failure
+    const sendReadOnly = async (ix: TransactionInstruction) => {
+      ix.keys = ix.keys.map((key) =>
+        key.pubkey.equals(badge) ? { ...key, isWritable: false } : key,
+      );
+      try {
+        await provider.sendAndConfirm(new Transaction().add(ix), [wallet]);
+        expect.fail("Expected transaction to fail — badge is read-only");
+      } catch (err: any) {
+        const logs = (err.logs ?? []).join("\n");
+        expect((err.message || "") + logs).to.include("BadgeNotWritable");
+      }
+    };
+
+    // Creating the badge is checked before the system program is called
+    await sendReadOnly(
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+        .instruction(),
+    );
+    expect(await provider.connection.getAccountInfo(badge)).to.equal(null);
+    console.log("    Read-only badge rejected on create ✓");
+
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, publicInputs))
+      .signers([wallet])
+      .rpc();
+
+    await sendReadOnly(
+      await program.methods
+        .setLabel(Buffer.from("read-only"))
+        .accounts({ user: wallet.publicKey, tierBadge: badge })
+        .instruction(),
+    );
+    console.log("    Read-only badge rejected by set_label ✓");
+
+    const refresh = await proveFor(wallet.publicKey);
+    await sendReadOnly(
+      await program.methods
+        .verifyAndStoreTier(...refresh.args, LAYOUT_V1)
+        .accounts(verifyAccounts(wallet.publicKey, refresh.publicInputs))
+        .instruction(),
+    );
+    console.log("    Read-only badge rejected on refresh ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 72: Tier-gated DAO membership
+  // ---------------------------------------------------------------------------
+
+  it("admits wallets to a gated DAO once, at its minimum tier", async () => {
+    const pda = (...seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(seeds, programId)[0];
+    const createGate = async (minTier: number) => {
+      const dao = Keypair.generate();
+      const gate = pda(Buffer.from("dao_gate"), dao.publicKey.toBuffer());
+      await program.methods
+        .createDaoGate(minTier)
+        .accounts({
+          payer: user.publicKey,
+          dao: dao.publicKey,
+          daoGate: gate,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([dao])
+        .rpc();
+      return { dao: dao.publicKey, gate };
+    };
+    const membershipOf = (dao: PublicKey, wallet: PublicKey) =>
+      pda(Buffer.from("membership"), dao.toBuffer(), wallet.toBuffer());
+    const join = async (
+      wallet: Keypair,
+      { dao, gate }: { dao: PublicKey; gate: PublicKey },
+    ) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+      return program.methods
+        .verifyAndJoin(...args, LAYOUT_V1)
+        .accounts({
+          verify: verifyAccounts(wallet.publicKey, inputs),
+          daoGate: gate,
+          membership: membershipOf(dao, wallet.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc({ commitment: "confirmed" });
+    };
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    // The default proof verifies at Tier 6
+    const wallet = await fundedKeypair();
+    const open = await createGate(6);
+    const sig = await join(wallet, open);
+    const membership = await (program.account as any).membership.fetch(
+      membershipOf(open.dao, wallet.publicKey),
+    );
+    expect(membership.member.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(membership.tier).to.equal(6);
+    expect(
+      (
+        await (program.account as any).daoGate.fetch(open.gate)
+      ).memberCount.toNumber(),
+    ).to.equal(1);
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const joined = [
+      ...new anchor.EventParser(programId, program.coder).parseLogs(
+        tx!.meta!.logMessages!,
+      ),
+    ].find((e) => e.name === "memberJoined");
+    expect(joined!.data.dao.toBase58()).to.equal(open.dao.toBase58());
+    console.log("    Wallet joined at Tier 6 ✓");
+
+    await expectError(join(wallet, open), "AlreadyMember");
+    console.log("    Duplicate join rejected ✓");
+
+    const exclusive = await createGate(7);
+    await expectError(join(wallet, exclusive), "TierBelowDaoMinimum");
+    expect(
+      await provider.connection.getAccountInfo(
+        membershipOf(exclusive.dao, wallet.publicKey),
+      ),
+    ).to.equal(null);
+    console.log("    Insufficient tier rejected ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 73: Tier-weighted voting power
+  // ---------------------------------------------------------------------------
+
+  it("keeps one voting power per identity and zeroes it on revoke", async () => {
+    const wallet = await fundedKeypair();
+    const [badge] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const APP = Array.from(Buffer.from("app-vote"));
+    const [appBadge] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("tier_badge"),
+        wallet.publicKey.toBuffer(),
+        Buffer.from(APP),
+      ],
+      programId,
+    );
+    const weights = (await fetchConfig()).tierWeights.map((w: any) =>
+      w.toNumber(),
+    );
+    let votingPowerPDA = PublicKey.default;
+    const verifyVoting = async (
+      inputs: Record<string, string> = {},
+      namespace: number[] | null = null,
+    ) => {
+      const proof = await proveFor(wallet.publicKey, inputs);
+      [votingPowerPDA] = deriveVotingPowerPDA(proof.publicInputs[2], programId);
+      await program.methods
+        .verifyAndSetVotingPower(
+          ...proof.args.slice(0, 6),
+          namespace,
+          LAYOUT_V1,
+        )
+        .accounts({
+          verify: {
+            ...verifyAccounts(wallet.publicKey, proof.publicInputs),
+            tierBadge: namespace ? appBadge : badge,
+          },
+          votingPower: votingPowerPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+      return (program.account as any).votingPower.fetch(votingPowerPDA);
+    };
+
+    // The default proof verifies at Tier 6
+    let power = await verifyVoting();
+    expect(power.tier).to.equal(6);
+    expect(power.weight.toNumber()).to.equal(weights[5]);
+    expect(power.owner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    console.log(`    Tier 6 badge votes with weight ${weights[5]} ✓`);
+
+    // A refresh at Ocean (Tier 5) recomputes the weight
+    power = await verifyVoting({
+      balance_1: "30000000",
+      balance_2: "30000000",
+      balance_3: "30000000",
+      tier_lower_bound: "25000000",
+      tier_upper_bound: "100000000",
+    });
+    expect(power.tier).to.equal(5);
+    expect(power.weight.toNumber()).to.equal(weights[4]);
+    console.log(`    Refresh at Tier 5 set the weight to ${weights[4]} ✓`);
+
+    // A downgrade recomputes it too
+    await program.methods
+      .downgradeBadge(3)
+      .accounts({
+        user: wallet.publicKey,
+        tierBadge: badge,
+        config: configPDA,
+        votingPower: votingPowerPDA,
+      })
+      .signers([wallet])
+      .rpc();
+    power = await (program.account as any).votingPower.fetch(votingPowerPDA);
+    expect(power.tier).to.equal(3);
+    expect(power.weight.toNumber()).to.equal(weights[2]);
+    console.log(`    Downgrade to Tier 3 set the weight to ${weights[2]} ✓`);
+
+    // A second namespace moves the identity's one weight rather than adding
+    // another
+    await setBadgeValidity(10);
+    power = await verifyVoting({}, APP);
+    expect(power.badge.toBase58()).to.equal(appBadge.toBase58());
+    expect(power.tier).to.equal(6);
+    const anyPower = await (program.account as any).votingPower.all();
+    expect(
+      anyPower.filter(
+        ({ account }: any) =>
+          account.owner.toBase58() === wallet.publicKey.toBase58(),
+      ),
+    ).to.have.length(1);
+    console.log("    Second namespace shares the identity's voting power ✓");
+
+    // The revoke can't skip the VotingPower account
+    await waitPastUnixTime(provider.connection, power.expiresAt.toNumber());
+    await setBadgeValidity(30 * 24 * 60 * 60);
+    const revoke = program.methods.revokeExpiredTier().accounts({
+      user: wallet.publicKey,
+      tierBadge: appBadge,
+      expiryBucket: null,
+      votingPower: votingPowerPDA,
+      nullifierRecord: await nullifierRecordOf(appBadge),
+    });
+    const ix = await revoke.instruction();
+    ix.keys = ix.keys.filter(({ pubkey }) => !pubkey.equals(votingPowerPDA));
+    try {
+      await provider.sendAndConfirm(new Transaction().add(ix), [wallet]);
+      expect.fail("Expected transaction to fail — VotingPower omitted");
+    } catch (err: any) {
+      const logs = (err.logs ?? []).join("\n");
+      expect((err.message || "") + logs).to.include("AccountNotEnoughKeys");
+    }
+    console.log("    Revoke without the VotingPower account rejected ✓");
+
+    // Revoking the badge that holds the weight zeroes it
+    await revoke.signers([wallet]).rpc();
+    power = await (program.account as any).votingPower.fetch(votingPowerPDA);
+    expect(power.tier).to.equal(0);
+    expect(power.weight.toNumber()).to.equal(0);
+    console.log("    Weight zeroed on revoke ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 74: Relaxed nullifier uniqueness
+  // ---------------------------------------------------------------------------
+
+  it("skips nullifier registration only in relaxed mode", async () => {
+    const nullifier = BigInt("0x" + randomBytes(31).toString("hex"));
+    const verifyShared = async (wallet: Keypair, withRecord: boolean) => {
+      const { args, publicInputs: inputs } = await proveFor(wallet.publicKey, {
+        nullifier: nullifier.toString(),
+      });
+      await program.methods
+        .verifyAndStoreTier(...args, LAYOUT_V1)
+        .accounts({
+          ...verifyAccounts(wallet.publicKey, inputs),
+          nullifierRecord: withRecord
+            ? deriveNullifierPDA(inputs[2], programId)[0]
+            : null,
+        })
+        .signers([wallet])
+        .rpc();
+      return inputs;
+    };
+    const enforcedOf = async (wallet: Keypair) =>
+      (
+        await (program.account as any).tierBadge.fetch(
+          deriveTierBadgePDA(wallet.publicKey, programId)[0],
+        )
+      ).nullifierEnforced;
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    // Strict by default: the record can't be left out
+    const first = await fundedKeypair();
+    await expectError(verifyShared(first, false), "NullifierRecordRequired");
+    console.log("    Strict mode requires the nullifier record ✓");
+
+    // Relaxed: two wallets share the nullifier without any record
+    await program.methods
+      .updateConfig({ ...noChange, requireNullifierUniqueness: false })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await program.methods
+      .applyConfig()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    const second = await fundedKeypair();
+    await verifyShared(first, false);
+    const inputs = await verifyShared(second, false);
+    expect(await enforcedOf(first)).to.be.false;
+    expect(await enforcedOf(second)).to.be.false;
+    expect(
+      await provider.connection.getAccountInfo(
+        deriveNullifierPDA(inputs[2], programId)[0],
+      ),
+    ).to.equal(null);
+    console.log("    Relaxed mode allows reuse, badges not enforced ✓");
+
+    // Re-enabling uniqueness is immediate, and blocks reuse again
+    await program.methods
+      .updateConfig({ ...noChange, requireNullifierUniqueness: true })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).requireNullifierUniqueness).to.equal(true);
+    await verifyShared(first, true);
+    expect(await enforcedOf(first)).to.be.true;
+    await expectError(verifyShared(second, true), "NullifierAlreadyUsed");
+    console.log("    Strict mode blocks reuse ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 75: Identity rotation
+  // ---------------------------------------------------------------------------
+
+  it("moves a badge to a new nullifier only on attestation", async () => {
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const newNullifier = "0x" + randomBytes(31).toString("hex");
+    const expectError = async (promise: Promise<unknown>, error: string) => {
+      try {
+        await promise;
+        expect.fail(`Expected transaction to fail with ${error}`);
+      } catch (err: any) {
+        expect(err.message || "").to.include(error);
+      }
+    };
+
+    const { args, publicInputs: oldInputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, oldInputs))
+      .signers([wallet])
+      .rpc();
+    const oldNullifier = Array.from(oldInputs[2]);
+    const [oldRecord] = deriveNullifierPDA(oldInputs[2], programId);
+    const [rotationPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("identity_rotation"), oldInputs[2]],
+      programId,
+    );
+
+    const rotated = await proveFor(wallet.publicKey, {
+      nullifier: BigInt(newNullifier).toString(),
+    });
+    const refresh = (withRotation: boolean) =>
+      program.methods
+        .verifyAndStoreTier(...rotated.args, LAYOUT_V1)
+        .accounts(
+          verifyAccounts(wallet.publicKey, rotated.publicInputs, {
+            identityRotation: withRotation ? rotationPDA : null,
+            previousNullifierRecord: withRotation ? oldRecord : null,
+          }),
+        )
+        .signers([wallet])
+        .rpc();
+
+    // Without an attestation, a new nullifier is a different identity
+    await expectError(refresh(false), "NullifierChanged");
+    console.log("    Unattested nullifier change rejected ✓");
+
+    // The wallet can't attest its own rotation
+    const newBuf = Buffer.from(newNullifier.slice(2).padStart(64, "0"), "hex");
+    const attest = (authority: Keypair) =>
+      program.methods
+        .attestIdentityRotation(
+          oldNullifier,
+          Array.from(newBuf),
+          wallet.publicKey,
+        )
+        .accounts({
+          authority: authority.publicKey,
+          config: configPDA,
+          identityRotation: rotationPDA,
+        })
+        .signers([authority])
+        .rpc();
+    await expectError(attest(wallet), "Unauthorized");
+    console.log("    Self-claimed rotation rejected ✓");
+
+    // Attested by the authority, the refresh moves the badge over
+    await attest(user.payer);
+    await refresh(true);
+    const badge = await (program.account as any).tierBadge.fetch(badgePDA);
+    expect(Buffer.from(badge.nullifier).equals(newBuf)).to.be.true;
+    expect(badge.nullifierEnforced).to.be.true;
+    expect(await provider.connection.getAccountInfo(oldRecord)).to.equal(null);
+    console.log("    Attested rotation moves badge, old record closed ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 76: Badge commitment
+  // ---------------------------------------------------------------------------
+
+  it("commits to a badge as circomlib's Poseidon does", async () => {
+    const { buildPoseidon } = await import("circomlibjs");
+    const poseidon = await buildPoseidon();
+    const wallet = await fundedKeypair();
+    const [badgePDA] = deriveTierBadgePDA(wallet.publicKey, programId);
+    const { args, publicInputs: inputs } = await proveFor(wallet.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...args, LAYOUT_V1)
+      .accounts(verifyAccounts(wallet.publicKey, inputs))
+      .signers([wallet])
+      .rpc();
+    const commitment = async () =>
+      Buffer.from(
+        await program.methods
+          .badgeCommitment()
+          .accounts({ tierBadge: badgePDA })
+          .view(),
+      );
+
+    // Reference: Poseidon over owner_hi, owner_lo, tier, bounds,
+    // nullifier and expiry, each a big-endian field element
+    const badge = await (program.account as any).tierBadge.fetch(badgePDA);
+    const owner = wallet.publicKey.toBuffer();
+    const toBigInt = (bytes: Buffer) => BigInt("0x" + bytes.toString("hex"));
+    const expected = poseidon.F.toObject(
+      poseidon([
+        toBigInt(owner.subarray(0, 16)),
+        toBigInt(owner.subarray(16)),
+        BigInt(badge.tier),
+        BigInt(badge.tierLowerBound.toString()),
+        BigInt(badge.tierUpperBound.toString()),
+        toBigInt(Buffer.from(badge.nullifier)),
+        BigInt(badge.expiresAt.toString()),
+      ]),
+    );
+    const first = await commitment();
+    expect(toBigInt(first)).to.equal(expected);
+    console.log("    Commitment matches circomlibjs ✓");
+
+    // Stable across calls, and distinct for another wallet's badge
+    expect((await commitment()).equals(first)).to.be.true;
+    const other = await fundedKeypair();
+    const otherProof = await proveFor(other.publicKey);
+    await program.methods
+      .verifyAndStoreTier(...otherProof.args, LAYOUT_V1)
+      .accounts(verifyAccounts(other.publicKey, otherProof.publicInputs))
+      .signers([other])
+      .rpc();
+    const otherCommitment = Buffer.from(
+      await program.methods
+        .badgeCommitment()
+        .accounts({
+          tierBadge: deriveTierBadgePDA(other.publicKey, programId)[0],
+        })
+        .view(),
+    );
+    expect(otherCommitment.equals(first)).to.be.false;
+    console.log("    Commitment stable, distinct per badge ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 77: Legacy badge migration
+  // ---------------------------------------------------------------------------
+
+  it("grows a badge in the first release's layout to the current one", async () => {
+    // Loaded at genesis from tests/fixtures/legacy-tier-badge.json: a Tier 6
+    // badge holding only the fields up to `bump`
+    const legacyBadge = new PublicKey(
+      "6dPhjwQmQm1yfoHaenfd9eDCNnYGFALMxR5MoQBxwMCs",
+    );
+    const migrate = (tierBadge: PublicKey) =>
+      program.methods
+        .migrateBadge()
+        .accounts({
+          payer: user.publicKey,
+          tierBadge,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    const badgeSize = (program.account as any).tierBadge.size;
+
+    let info = await provider.connection.getAccountInfo(legacyBadge);
+    expect(info!.data.length).to.equal(8 + 98);
+    const decodes = await (program.account as any).tierBadge
+      .fetch(legacyBadge)
+      .then(() => true)
+      .catch(() => false);
+    expect(decodes).to.equal(false);
+
+    // Anything but a TierBadge is refused
+    try {
+      await migrate(configPDA);
+      expect.fail("Expected transaction to fail — not a badge");
+    } catch (err: any) {
+      expect(err.message || "").to.include("UnknownBadgeLayout");
+    }
+    console.log("    Non-badge account rejected ✓");
+
+    await migrate(legacyBadge);
+    info = await provider.connection.getAccountInfo(legacyBadge);
+    expect(info!.data.length).to.equal(badgeSize);
+    expect(info!.lamports).to.equal(
+      await provider.connection.getMinimumBalanceForRentExemption(badgeSize),
+    );
+    const badge = await (program.account as any).tierBadge.fetch(legacyBadge);
+    expect(badge.tier).to.equal(6);
+    expect(badge.tierHighWater).to.equal(6);
+    expect(Buffer.from(badge.currencyCode).toString()).to.equal("USD");
+    expect(badge.namespace).to.deep.equal(Array(8).fill(0));
+    expect(badge.nullifierEnforced).to.equal(false);
+    console.log("    Legacy badge grown to the current layout ✓");
+
+    // Migrating again changes nothing
+    await migrate(legacyBadge);
+    expect(
+      (await provider.connection.getAccountInfo(legacyBadge))!.data,
+    ).to.deep.equal(info!.data);
+    console.log("    Current-layout badge left untouched ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 78: Legacy nullifier backfill
+  // ---------------------------------------------------------------------------
+
+  it("registers the nullifier of a migrated badge that has no record", async () => {
+    // The badge Test 77 migrated predates the registry, so nothing holds its
+    // nullifier yet
+    const legacyBadge = new PublicKey(
+      "6dPhjwQmQm1yfoHaenfd9eDCNnYGFALMxR5MoQBxwMCs",
+    );
+    const badge = await (program.account as any).tierBadge.fetch(legacyBadge);
+    const [nullifierPDA] = deriveNullifierPDA(
+      Buffer.from(badge.nullifier),
+      programId,
+    );
+    expect(await provider.connection.getAccountInfo(nullifierPDA)).to.equal(
+      null,
+    );
+    try {
+      await program.methods
+        .requireNullifierEnforced()
+        .accounts({ tierBadge: legacyBadge, nullifierRecord: null })
+        .rpc();
+      expect.fail("Expected transaction to fail — nullifier not registered");
+    } catch (err: any) {
+      expect(err.message || "").to.include("NullifierNotEnforced");
+    }
+
+    await program.methods
+      .backfillNullifier()
+      .accounts({
+        authority: user.publicKey,
+        config: configPDA,
+        tierBadge: legacyBadge,
+        nullifierRecord: nullifierPDA,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    const record = await (program.account as any).nullifierRecord.fetch(
+      nullifierPDA,
+    );
+    expect(record.owner.toBase58()).to.equal(badge.owner.toBase58());
+    expect(record.nullifier).to.deep.equal(badge.nullifier);
+    await program.methods
+      .requireNullifierEnforced()
+      .accounts({ tierBadge: legacyBadge, nullifierRecord: nullifierPDA })
+      .rpc();
+    console.log("    Legacy nullifier registered and badge enforced ✓");
+  });
+
+  // ---------------------------------------------------------------------------
+  // Test 79: Tier table freeze
+  //
+  // Keep this test last — the freeze is permanent for the test deployment.
+  // ---------------------------------------------------------------------------
+
+  it("freezes the tier table irreversibly", async () => {
+    const config = await fetchConfig();
+    const tiers = config.tiers;
+    const updateTiers = () =>
+      program.methods
+        .updateConfig({ ...noChange, tiers })
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc();
+    const expectFrozen = async (promise: Promise<unknown>) => {
+      try {
+        await promise;
+        expect.fail("Expected transaction to fail — tier table frozen");
+      } catch (err: any) {
+        expect(err.message || "").to.include("TierTableFrozen");
+      }
+    };
+
+    // Queue a tier change before freezing; it can no longer be applied
+    await updateTiers();
+    await program.methods
+      .freezeTierTable()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).tierTableFrozen).to.equal(true);
+
+    await waitSlots(provider.connection, TIMELOCK_SLOTS + 1);
+    await expectFrozen(
+      program.methods
+        .applyConfig()
+        .accounts({ authority: user.publicKey, config: configPDA })
+        .rpc(),
+    );
+    await expectFrozen(updateTiers());
+    console.log("    Tier updates rejected after freeze ✓");
+
+    // Freezing again is a no-op, and nothing can unfreeze
+    await program.methods
+      .freezeTierTable()
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+    expect((await fetchConfig()).tierTableFrozen).to.equal(true);
+
+    // Other parameters can still change
+    await program.methods
+      .updateConfig({ ...noChange, maxProofAgeSeconds: new anchor.BN(600) })
+      .accounts({ authority: user.publicKey, config: configPDA })
+      .rpc();
+  });
 });